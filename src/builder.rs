@@ -135,6 +135,7 @@ where
             options: self.options,
             madctl, // This is crate::dcs::SetAddressMode type
             sleeping: false,
+            address_window: None,
         })
     }
 }
@@ -152,6 +153,7 @@ pub enum ConfigurationError {
     UnsupportedInterface,
     InvalidDisplaySize,
     InvalidDisplayOffset,
+    InvalidInitScript,
 }
 
 impl<DIError, PinError> From<ModelInitError<DIError>> for InitError<DIError, PinError> {
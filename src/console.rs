@@ -0,0 +1,846 @@
+//! Scrolling text console.
+//!
+//! [`Console`] turns a display into a simple terminal that is well suited for
+//! boot and log output. New lines are appended at the bottom of the screen and
+//! the content is moved up using the hardware scroll region of the controller,
+//! which means that the screen never has to be redrawn.
+//!
+//! Text is rendered with an embedded-graphics [`MonoFont`] into a buffer that
+//! holds a single line of text and is sent to the display once the line is
+//! complete. A subset of the ANSI SGR escape sequences (`ESC [ ... m`) can be
+//! used to change the text and background colors.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use core::fmt::Write;
+//! use embedded_graphics::{mono_font::ascii::FONT_6X10, pixelcolor::{Rgb565, RgbColor}};
+//! use mipidsi::console::Console;
+//!
+//! // 240 pixels wide, 10 pixels high and 2 bytes per pixel.
+//! let mut line_buffer = [0u8; 240 * 10 * 2];
+//! let mut console = Console::<Rgb565, 2>::new(
+//!     &mut display,
+//!     &FONT_6X10,
+//!     &mut line_buffer,
+//!     Rgb565::WHITE,
+//!     Rgb565::BLACK,
+//! )
+//! .await?;
+//!
+//! writeln!(console, "\x1b[32mOK\x1b[0m mounted filesystem").unwrap();
+//! console.flush(&mut display).await?;
+//! ```
+
+use embedded_graphics::{
+    mono_font::{MonoFont, MonoTextStyleBuilder},
+    pixelcolor::Rgb888,
+    prelude::*,
+    text::{Baseline, Text},
+};
+use embedded_hal::digital::OutputPin;
+
+use crate::{
+    interface::Interface,
+    models::Model,
+    options::MemoryMapping,
+    raw_framebuf::{IntoRawBytes, RawFrameBuf},
+    Display, Scroller,
+};
+
+/// Capacity of the buffer used by the [`core::fmt::Write`] implementation.
+const PENDING_CAPACITY: usize = 256;
+
+/// Maximum number of parameters in a single escape sequence.
+const MAX_PARAMS: usize = 4;
+
+/// Tab stop distance in characters.
+const TAB_WIDTH: u16 = 8;
+
+/// Standard ANSI colors followed by their bright variants.
+const ANSI_COLORS: [Rgb888; 16] = [
+    Rgb888::new(0, 0, 0),
+    Rgb888::new(170, 0, 0),
+    Rgb888::new(0, 170, 0),
+    Rgb888::new(170, 85, 0),
+    Rgb888::new(0, 0, 170),
+    Rgb888::new(170, 0, 170),
+    Rgb888::new(0, 170, 170),
+    Rgb888::new(170, 170, 170),
+    Rgb888::new(85, 85, 85),
+    Rgb888::new(255, 85, 85),
+    Rgb888::new(85, 255, 85),
+    Rgb888::new(255, 255, 85),
+    Rgb888::new(85, 85, 255),
+    Rgb888::new(255, 85, 255),
+    Rgb888::new(85, 255, 255),
+    Rgb888::new(255, 255, 255),
+];
+
+/// Scrolling text console.
+///
+/// See the [module level documentation](self) for more information.
+pub struct Console<'a, C, const N: usize>
+where
+    C: IntoRawBytes<N>,
+{
+    line: RawFrameBuf<C, &'a mut [u8], N>,
+    font: &'a MonoFont<'a>,
+    scroller: Scroller,
+    columns: u16,
+    lines: u16,
+    column: u16,
+    row: u16,
+    line_dirty: bool,
+    default_text_color: C,
+    default_background_color: C,
+    text_color: C,
+    background_color: C,
+    bold: bool,
+    parser: Parser,
+    pending: [u8; PENDING_CAPACITY],
+    pending_len: usize,
+}
+
+impl<'a, C, const N: usize> Console<'a, C, N>
+where
+    C: IntoRawBytes<N> + From<Rgb888>,
+{
+    /// Creates a new console and clears the display.
+    ///
+    /// `buffer` must be large enough to hold one line of text, which is
+    /// `display width * font height * N` bytes.
+    ///
+    /// The console uses the hardware scroll region of the entire display. Rows
+    /// at the bottom of the display which can't fit a complete line of text are
+    /// configured as a bottom fixed area.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` is too small, if the display orientation scrolls
+    /// horizontally (see [`Scroller::is_horizontal`]) or if the display can't
+    /// fit a single character.
+    pub async fn new<DI, M, RST>(
+        display: &mut Display<DI, M, RST>,
+        font: &'a MonoFont<'a>,
+        buffer: &'a mut [u8],
+        text_color: C,
+        background_color: C,
+    ) -> Result<Self, DI::Error>
+    where
+        DI: Interface<Word = u8>,
+        M: Model,
+        RST: OutputPin,
+    {
+        let (width, height) = display.options.display_size();
+        let char_height = font.character_size.height as u16;
+        let advance = (font.character_size.width + font.character_spacing) as u16;
+
+        let columns = width / advance;
+        let lines = height / char_height;
+        assert!(
+            columns > 0 && lines > 0,
+            "Console font doesn't fit on the display."
+        );
+
+        // Check the orientation before the scroll area is configured.
+        assert!(
+            !MemoryMapping::from(display.options.orientation).swap_rows_and_columns,
+            "Console requires an orientation which scrolls vertically."
+        );
+        let scroller = display.scroller(0, height - lines * char_height).await?;
+
+        let mut console = Self {
+            line: RawFrameBuf::new(buffer, usize::from(width), usize::from(char_height)),
+            font,
+            scroller,
+            columns,
+            lines,
+            column: 0,
+            row: 0,
+            line_dirty: false,
+            default_text_color: text_color,
+            default_background_color: background_color,
+            text_color,
+            background_color,
+            bold: false,
+            parser: Parser::new(),
+            pending: [0; PENDING_CAPACITY],
+            pending_len: 0,
+        };
+        console.clear(display).await?;
+
+        Ok(console)
+    }
+
+    /// Returns the number of characters per line.
+    pub fn columns(&self) -> u16 {
+        self.columns
+    }
+
+    /// Returns the number of lines.
+    pub fn lines(&self) -> u16 {
+        self.lines
+    }
+
+    /// Clears the screen and moves the cursor to the top left corner.
+    ///
+    /// Text which was written using the [`core::fmt::Write`] implementation,
+    /// but wasn't flushed yet, is discarded.
+    pub async fn clear<DI, M, RST>(
+        &mut self,
+        display: &mut Display<DI, M, RST>,
+    ) -> Result<(), DI::Error>
+    where
+        DI: Interface<Word = u8>,
+        M: Model,
+        RST: OutputPin,
+    {
+        let (width, height) = display.options.display_size();
+        let char_height = self.char_height();
+
+        self.pending_len = 0;
+        self.scroller = display
+            .scroller(0, self.scroller.bottom_fixed_area())
+            .await?;
+        self.clear_line();
+
+        for row in 0..self.lines {
+            display
+                .show_raw_data(
+                    0,
+                    usize::from(row * char_height),
+                    usize::from(width),
+                    usize::from(char_height),
+                    self.line.as_bytes(),
+                )
+                .await?;
+        }
+
+        let remaining = height - self.lines * char_height;
+        if remaining > 0 {
+            let len = usize::from(width) * usize::from(remaining) * N;
+            display
+                .show_raw_data(
+                    0,
+                    usize::from(self.lines * char_height),
+                    usize::from(width),
+                    usize::from(remaining),
+                    &self.line.as_bytes()[..len],
+                )
+                .await?;
+        }
+
+        self.column = 0;
+        self.row = 0;
+        self.line_dirty = false;
+
+        Ok(())
+    }
+
+    /// Writes a string to the console.
+    ///
+    /// Text written by the [`core::fmt::Write`] implementation that wasn't
+    /// flushed yet is written first. The current line is sent to the display
+    /// once it is complete, call [`flush`](Self::flush) to send an incomplete
+    /// line.
+    pub async fn print<DI, M, RST>(
+        &mut self,
+        display: &mut Display<DI, M, RST>,
+        text: &str,
+    ) -> Result<(), DI::Error>
+    where
+        DI: Interface<Word = u8>,
+        M: Model,
+        RST: OutputPin,
+    {
+        self.write_pending(display).await?;
+        self.write_text(display, text).await
+    }
+
+    /// Writes all pending text to the display.
+    ///
+    /// This includes text written using the [`core::fmt::Write`]
+    /// implementation and the current, incomplete line.
+    pub async fn flush<DI, M, RST>(
+        &mut self,
+        display: &mut Display<DI, M, RST>,
+    ) -> Result<(), DI::Error>
+    where
+        DI: Interface<Word = u8>,
+        M: Model,
+        RST: OutputPin,
+    {
+        self.write_pending(display).await?;
+        self.send_line(display).await
+    }
+
+    fn char_height(&self) -> u16 {
+        self.font.character_size.height as u16
+    }
+
+    fn char_advance(&self) -> u16 {
+        (self.font.character_size.width + self.font.character_spacing) as u16
+    }
+
+    async fn write_pending<DI, M, RST>(
+        &mut self,
+        display: &mut Display<DI, M, RST>,
+    ) -> Result<(), DI::Error>
+    where
+        DI: Interface<Word = u8>,
+        M: Model,
+        RST: OutputPin,
+    {
+        if self.pending_len == 0 {
+            return Ok(());
+        }
+
+        let pending = self.pending;
+        let len = core::mem::take(&mut self.pending_len);
+
+        // The pending buffer only contains complete strings.
+        let text = core::str::from_utf8(&pending[..len]).unwrap_or_default();
+        self.write_text(display, text).await
+    }
+
+    async fn write_text<DI, M, RST>(
+        &mut self,
+        display: &mut Display<DI, M, RST>,
+        text: &str,
+    ) -> Result<(), DI::Error>
+    where
+        DI: Interface<Word = u8>,
+        M: Model,
+        RST: OutputPin,
+    {
+        for c in text.chars() {
+            match self.parser.advance(c) {
+                Action::Print(c) => self.write_char(display, c).await?,
+                Action::Sgr(params) => self.select_graphic_rendition(params.as_slice()),
+                Action::None => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn write_char<DI, M, RST>(
+        &mut self,
+        display: &mut Display<DI, M, RST>,
+        c: char,
+    ) -> Result<(), DI::Error>
+    where
+        DI: Interface<Word = u8>,
+        M: Model,
+        RST: OutputPin,
+    {
+        match c {
+            '\n' => self.new_line(display).await,
+            '\r' => {
+                self.column = 0;
+                Ok(())
+            }
+            '\x08' => {
+                self.column = self.column.saturating_sub(1);
+                Ok(())
+            }
+            '\t' => {
+                loop {
+                    self.draw_char(display, ' ').await?;
+                    if self.column % TAB_WIDTH == 0 {
+                        break;
+                    }
+                }
+                Ok(())
+            }
+            c if c.is_control() => Ok(()),
+            c => self.draw_char(display, c).await,
+        }
+    }
+
+    async fn draw_char<DI, M, RST>(
+        &mut self,
+        display: &mut Display<DI, M, RST>,
+        c: char,
+    ) -> Result<(), DI::Error>
+    where
+        DI: Interface<Word = u8>,
+        M: Model,
+        RST: OutputPin,
+    {
+        if self.column >= self.columns {
+            self.new_line(display).await?;
+        }
+
+        let style = MonoTextStyleBuilder::new()
+            .font(self.font)
+            .text_color(self.text_color)
+            .background_color(self.background_color)
+            .build();
+        let position = Point::new(i32::from(self.column * self.char_advance()), 0);
+
+        let mut encoded = [0u8; 4];
+        let _ = Text::with_baseline(c.encode_utf8(&mut encoded), position, style, Baseline::Top)
+            .draw(&mut self.line);
+
+        self.column += 1;
+        self.line_dirty = true;
+
+        Ok(())
+    }
+
+    async fn new_line<DI, M, RST>(
+        &mut self,
+        display: &mut Display<DI, M, RST>,
+    ) -> Result<(), DI::Error>
+    where
+        DI: Interface<Word = u8>,
+        M: Model,
+        RST: OutputPin,
+    {
+        self.send_line(display).await?;
+
+        if self.row + 1 < self.lines {
+            self.row += 1;
+        } else {
+            let char_height = self.char_height();
+            display
+                .scroll(&mut self.scroller, char_height as i16)
+                .await?;
+
+            // The exposed line still shows old content, clear it right away.
+            self.clear_line();
+            self.line_dirty = true;
+            self.send_line(display).await?;
+        }
+
+        self.clear_line();
+        self.column = 0;
+        self.line_dirty = true;
+
+        Ok(())
+    }
+
+    async fn send_line<DI, M, RST>(
+        &mut self,
+        display: &mut Display<DI, M, RST>,
+    ) -> Result<(), DI::Error>
+    where
+        DI: Interface<Word = u8>,
+        M: Model,
+        RST: OutputPin,
+    {
+        if !self.line_dirty {
+            return Ok(());
+        }
+
+        let char_height = self.char_height();
+        let y = self.scroller.row_at(self.row * char_height);
+        display
+            .show_raw_data(
+                0,
+                usize::from(y),
+                self.line.width(),
+                self.line.height(),
+                self.line.as_bytes(),
+            )
+            .await?;
+        self.line_dirty = false;
+
+        Ok(())
+    }
+
+    fn clear_line(&mut self) {
+        let _ = self.line.clear(self.background_color);
+    }
+
+    fn select_graphic_rendition(&mut self, params: &[u16]) {
+        if params.is_empty() {
+            self.reset_attributes();
+        }
+
+        for &param in params {
+            match param {
+                0 => self.reset_attributes(),
+                1 => self.bold = true,
+                22 => self.bold = false,
+                30..=37 => {
+                    let index = usize::from(param - 30) + if self.bold { 8 } else { 0 };
+                    self.text_color = ANSI_COLORS[index].into();
+                }
+                39 => self.text_color = self.default_text_color,
+                40..=47 => self.background_color = ANSI_COLORS[usize::from(param - 40)].into(),
+                49 => self.background_color = self.default_background_color,
+                90..=97 => self.text_color = ANSI_COLORS[usize::from(param - 90) + 8].into(),
+                100..=107 => {
+                    self.background_color = ANSI_COLORS[usize::from(param - 100) + 8].into()
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn reset_attributes(&mut self) {
+        self.text_color = self.default_text_color;
+        self.background_color = self.default_background_color;
+        self.bold = false;
+    }
+}
+
+impl<C, const N: usize> core::fmt::Write for Console<'_, C, N>
+where
+    C: IntoRawBytes<N>,
+{
+    /// Appends the string to the pending text.
+    ///
+    /// The text isn't sent to the display before [`Console::flush`] is called.
+    /// An error is returned if the pending text buffer is full.
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let end = self.pending_len + s.len();
+        if end > PENDING_CAPACITY {
+            return Err(core::fmt::Error);
+        }
+
+        self.pending[self.pending_len..end].copy_from_slice(s.as_bytes());
+        self.pending_len = end;
+
+        Ok(())
+    }
+}
+
+/// Parameters of a complete escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Params {
+    values: [u16; MAX_PARAMS],
+    len: usize,
+}
+
+impl Params {
+    const fn new() -> Self {
+        Self {
+            values: [0; MAX_PARAMS],
+            len: 0,
+        }
+    }
+
+    fn as_slice(&self) -> &[u16] {
+        &self.values[..self.len]
+    }
+}
+
+/// Output of the escape sequence [`Parser`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    /// Character that should be written to the console.
+    Print(char),
+    /// Select Graphic Rendition sequence.
+    Sgr(Params),
+    /// Character was consumed by the parser.
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    Csi,
+}
+
+/// Minimal ANSI escape sequence parser.
+///
+/// Only SGR sequences are reported, all other escape sequences are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Parser {
+    state: State,
+    params: Params,
+    has_value: bool,
+}
+
+impl Parser {
+    const fn new() -> Self {
+        Self {
+            state: State::Ground,
+            params: Params::new(),
+            has_value: false,
+        }
+    }
+
+    fn advance(&mut self, c: char) -> Action {
+        match self.state {
+            State::Ground if c == '\x1b' => {
+                self.state = State::Escape;
+                Action::None
+            }
+            State::Ground => Action::Print(c),
+            State::Escape if c == '[' => {
+                self.state = State::Csi;
+                self.params = Params::new();
+                self.has_value = false;
+                Action::None
+            }
+            State::Escape => {
+                self.state = State::Ground;
+                Action::None
+            }
+            State::Csi => match c {
+                '0'..='9' => {
+                    if !self.has_value && self.params.len < MAX_PARAMS {
+                        self.params.len += 1;
+                        self.has_value = true;
+                    }
+                    if self.has_value {
+                        let value = &mut self.params.values[self.params.len - 1];
+                        *value = value
+                            .saturating_mul(10)
+                            .saturating_add(c as u16 - u16::from(b'0'));
+                    }
+                    Action::None
+                }
+                ';' => {
+                    if !self.has_value && self.params.len < MAX_PARAMS {
+                        // Empty parameters default to 0.
+                        self.params.len += 1;
+                    }
+                    self.has_value = false;
+                    Action::None
+                }
+                '\x40'..='\x7e' => {
+                    if !self.has_value && self.params.len > 0 && self.params.len < MAX_PARAMS {
+                        // A trailing empty parameter defaults to 0.
+                        self.params.len += 1;
+                    }
+                    self.state = State::Ground;
+                    if c == 'm' {
+                        Action::Sgr(self.params)
+                    } else {
+                        Action::None
+                    }
+                }
+                _ => Action::None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use core::fmt::Write;
+    use std::{vec, vec::Vec};
+
+    use embassy_futures::block_on;
+    use embedded_graphics::{
+        mono_font::{ascii::FONT_4X6, MonoTextStyle},
+        pixelcolor::Rgb565,
+    };
+
+    use crate::{
+        models::GenericModel,
+        options::{Orientation, Rotation},
+        testing::{mock_display, mock_display_with, MockDisplay, MockInterface},
+    };
+
+    use super::*;
+
+    /// Creates a console with 3 columns and 3 lines on a 12x20 display.
+    fn console(buffer: &mut [u8]) -> (MockDisplay<GenericModel<12, 20>>, Console<'_, Rgb565, 2>) {
+        let mut display = mock_display(GenericModel::new(&[]), MockInterface::new(), (12, 20));
+        let console = block_on(Console::new(
+            &mut display,
+            &FONT_4X6,
+            buffer,
+            Rgb565::WHITE,
+            Rgb565::BLACK,
+        ))
+        .unwrap();
+        display.di.clear();
+
+        (display, console)
+    }
+
+    /// Returns the RASET rows and VSCRSADD offsets which were sent.
+    fn rows_and_offsets(display: &MockDisplay<GenericModel<12, 20>>) -> Vec<(u8, u16, u16)> {
+        display
+            .di
+            .commands()
+            .filter(|(instruction, _)| matches!(instruction, 0x2B | 0x37))
+            .map(|(instruction, params)| match params {
+                [a, b] => (instruction, u16::from_be_bytes([*a, *b]), 0),
+                [a, b, c, d] => (
+                    instruction,
+                    u16::from_be_bytes([*a, *b]),
+                    u16::from_be_bytes([*c, *d]),
+                ),
+                _ => panic!("unexpected parameters {params:?}"),
+            })
+            .collect()
+    }
+
+    /// Renders a line of text like the console does.
+    fn line(text: &str) -> Vec<u8> {
+        let mut buffer = vec![0; 12 * 6 * 2];
+        let mut line = RawFrameBuf::<Rgb565, _, 2>::new(&mut buffer[..], 12, 6);
+        line.clear(Rgb565::BLACK).unwrap();
+        let style = MonoTextStyle::new(&FONT_4X6, Rgb565::WHITE);
+        Text::with_baseline(text, Point::zero(), style, Baseline::Top)
+            .draw(&mut line)
+            .unwrap();
+        buffer
+    }
+
+    #[test]
+    fn appends_lines() {
+        let mut buffer = [0; 12 * 6 * 2];
+        let (mut display, mut console) = console(&mut buffer);
+        assert_eq!((console.columns(), console.lines()), (3, 3));
+
+        block_on(console.print(&mut display, "ab\ncd")).unwrap();
+        assert_eq!(rows_and_offsets(&display), [(0x2B, 0, 5)]);
+        assert_eq!(display.di.data(), line("ab"));
+
+        display.di.clear();
+        block_on(console.flush(&mut display)).unwrap();
+        assert_eq!(rows_and_offsets(&display), [(0x2B, 6, 11)]);
+        assert_eq!(display.di.data(), line("cd"));
+
+        display.di.clear();
+        block_on(console.flush(&mut display)).unwrap();
+        assert!(display.di.transfers().is_empty());
+    }
+
+    #[test]
+    fn wraps_long_lines() {
+        let mut buffer = [0; 12 * 6 * 2];
+        let (mut display, mut console) = console(&mut buffer);
+
+        block_on(console.print(&mut display, "abcd")).unwrap();
+        block_on(console.flush(&mut display)).unwrap();
+
+        assert_eq!(rows_and_offsets(&display), [(0x2B, 0, 5), (0x2B, 6, 11)]);
+        assert_eq!(display.di.data(), [line("abc"), line("d")].concat());
+    }
+
+    #[test]
+    fn scrolls_through_scroll_region() {
+        let mut buffer = [0; 12 * 6 * 2];
+        let (mut display, mut console) = console(&mut buffer);
+
+        block_on(console.print(&mut display, "1\n2\n3\n4\n5")).unwrap();
+        block_on(console.flush(&mut display)).unwrap();
+
+        // The bottom 2 rows are a fixed area, the scroll area is 18 rows high.
+        assert_eq!(
+            rows_and_offsets(&display),
+            [
+                (0x2B, 0, 5),
+                (0x2B, 6, 11),
+                (0x2B, 12, 17),
+                (0x37, 6, 0),
+                (0x2B, 0, 5),
+                (0x37, 12, 0),
+                (0x2B, 6, 11),
+            ]
+        );
+        // Each line exposed by scrolling is cleared right away.
+        assert_eq!(
+            display.di.data(),
+            ["1", "2", "3", "", "4", "", "5"].map(line).concat()
+        );
+    }
+
+    #[test]
+    fn rejects_horizontal_scrolling_before_configuring_scroll_area() {
+        let mut display = mock_display_with(
+            GenericModel::<12, 20>::new(&[]),
+            MockInterface::new(),
+            (12, 20),
+            |builder| builder.orientation(Orientation::new().rotate(Rotation::Deg90)),
+        );
+        display.di.clear();
+
+        let mut buffer = [0; 20 * 6 * 2];
+        let result = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| {
+            let _ = block_on(Console::<Rgb565, 2>::new(
+                &mut display,
+                &FONT_4X6,
+                &mut buffer,
+                Rgb565::WHITE,
+                Rgb565::BLACK,
+            ));
+        }));
+
+        assert!(result.is_err());
+        assert!(display.di.transfers().is_empty());
+    }
+
+    #[test]
+    fn formatted_text_is_sent_on_flush() {
+        let mut buffer = [0; 12 * 6 * 2];
+        let (mut display, mut console) = console(&mut buffer);
+
+        write!(console, "x{}", 1).unwrap();
+        assert!(display.di.transfers().is_empty());
+
+        block_on(console.flush(&mut display)).unwrap();
+        assert_eq!(rows_and_offsets(&display), [(0x2B, 0, 5)]);
+        assert_eq!(display.di.data(), line("x1"));
+
+        assert!(console.write_str(&"x".repeat(PENDING_CAPACITY)).is_ok());
+        assert!(console.write_str("x").is_err());
+    }
+
+    fn parse(text: &str) -> ([Option<char>; 8], Option<Params>) {
+        let mut parser = Parser::new();
+        let mut printed = [None; 8];
+        let mut sgr = None;
+        let mut i = 0;
+
+        for c in text.chars() {
+            match parser.advance(c) {
+                Action::Print(c) => {
+                    printed[i] = Some(c);
+                    i += 1;
+                }
+                Action::Sgr(params) => sgr = Some(params),
+                Action::None => {}
+            }
+        }
+
+        (printed, sgr)
+    }
+
+    #[test]
+    fn parser_passes_through_text() {
+        let (printed, sgr) = parse("ab\n");
+        assert_eq!(printed[..4], [Some('a'), Some('b'), Some('\n'), None]);
+        assert_eq!(sgr, None);
+    }
+
+    #[test]
+    fn parser_reports_sgr_params() {
+        let (printed, sgr) = parse("\x1b[1;31mx");
+        assert_eq!(printed[..2], [Some('x'), None]);
+        assert_eq!(sgr.unwrap().as_slice(), &[1, 31]);
+
+        let (_, sgr) = parse("\x1b[m");
+        assert_eq!(sgr.unwrap().as_slice(), &[] as &[u16]);
+
+        let (_, sgr) = parse("\x1b[;42m");
+        assert_eq!(sgr.unwrap().as_slice(), &[0, 42]);
+
+        let (_, sgr) = parse("\x1b[1;m");
+        assert_eq!(sgr.unwrap().as_slice(), &[1, 0]);
+
+        let (_, sgr) = parse("\x1b[;m");
+        assert_eq!(sgr.unwrap().as_slice(), &[0, 0]);
+    }
+
+    #[test]
+    fn parser_ignores_other_sequences() {
+        let (printed, sgr) = parse("\x1b[2Ja\x1b(b");
+        assert_eq!(printed[..2], [Some('a'), Some('b')]);
+        assert_eq!(sgr, None);
+    }
+}
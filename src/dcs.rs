@@ -129,3 +129,8 @@ dcs_basic_command!(
     WriteMemoryStart,
     0x2C
 );
+dcs_basic_command!(
+    /// Continue Framebuffer Memory Write
+    WriteMemoryContinue,
+    0x3C
+);
@@ -0,0 +1,388 @@
+//! Dithering for displays with less than 8 bits per color channel.
+//!
+//! Converting [`Rgb888`] colors to a lower color depth by truncation causes
+//! visible banding in gradients. [`Dither`] is a [`DrawTarget`] wrapper which
+//! accepts [`Rgb888`] colors and quantizes them for the wrapped draw target
+//! using either an ordered Bayer matrix or Floyd-Steinberg error diffusion.
+//!
+//! ```
+//! use embedded_graphics::{pixelcolor::{Rgb565, Rgb888}, prelude::*};
+//! use mipidsi::{
+//!     dither::{BayerMatrix, Dither},
+//!     raw_framebuf::RawFrameBuf,
+//! };
+//!
+//! let mut buffer = [0u8; 32 * 8 * 2];
+//! let mut framebuffer = RawFrameBuf::<Rgb565, _, 2>::new(&mut buffer[..], 32, 8);
+//!
+//! let mut dither = Dither::bayer(&mut framebuffer, BayerMatrix::Bayer4);
+//! dither.clear(Rgb888::new(100, 150, 200)).unwrap();
+//! ```
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::Dimensions,
+    pixelcolor::{Bgr565, PixelColor, Rgb565, Rgb666, Rgb888, RgbColor},
+    primitives::{PointsIter, Rectangle},
+    Pixel,
+};
+
+use crate::raw_framebuf::Rgb444;
+
+/// Color type which can be the output of a [`Dither`].
+pub trait DitherColor: PixelColor {
+    /// Number of bits of the red, green and blue channel.
+    const CHANNEL_BITS: [u8; 3];
+
+    /// Creates a color from red, green and blue values with [`CHANNEL_BITS`](Self::CHANNEL_BITS) bits.
+    fn from_channels(channels: [u8; 3]) -> Self;
+}
+
+impl DitherColor for Rgb565 {
+    const CHANNEL_BITS: [u8; 3] = [5, 6, 5];
+
+    fn from_channels([r, g, b]: [u8; 3]) -> Self {
+        Self::new(r, g, b)
+    }
+}
+
+impl DitherColor for Bgr565 {
+    const CHANNEL_BITS: [u8; 3] = [5, 6, 5];
+
+    fn from_channels([r, g, b]: [u8; 3]) -> Self {
+        Self::new(r, g, b)
+    }
+}
+
+impl DitherColor for Rgb666 {
+    const CHANNEL_BITS: [u8; 3] = [6, 6, 6];
+
+    fn from_channels([r, g, b]: [u8; 3]) -> Self {
+        Self::new(r, g, b)
+    }
+}
+
+impl DitherColor for Rgb444 {
+    const CHANNEL_BITS: [u8; 3] = [4, 4, 4];
+
+    fn from_channels([r, g, b]: [u8; 3]) -> Self {
+        Self::new(r, g, b)
+    }
+}
+
+/// Size of the Bayer matrix used for ordered dithering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BayerMatrix {
+    /// 2x2 matrix with 4 threshold levels.
+    Bayer2,
+    /// 4x4 matrix with 16 threshold levels.
+    Bayer4,
+    /// 8x8 matrix with 64 threshold levels.
+    Bayer8,
+}
+
+impl BayerMatrix {
+    /// Returns the base 2 logarithm of the matrix size.
+    fn order(self) -> u32 {
+        match self {
+            Self::Bayer2 => 1,
+            Self::Bayer4 => 2,
+            Self::Bayer8 => 3,
+        }
+    }
+
+    /// Returns the matrix entry for a pixel, in the range `0..size²`.
+    fn value(self, x: i32, y: i32) -> u32 {
+        let order = self.order();
+        let (x, y) = (x as u32, y as u32);
+
+        let mut value = 0;
+        for bit in 0..order {
+            let (x_bit, y_bit) = ((x >> bit) & 1, (y >> bit) & 1);
+            value |= ((x_bit ^ y_bit) << 1 | y_bit) << (2 * (order - 1 - bit));
+        }
+        value
+    }
+}
+
+/// Number of `i16` values per pixel of the error buffer used by
+/// [`Dither::floyd_steinberg`].
+///
+/// The buffer must hold two rows of errors: `2 * ERROR_VALUES_PER_PIXEL * width` values.
+pub const ERROR_VALUES_PER_PIXEL: usize = 3;
+
+enum Mode<'a> {
+    Ordered(BayerMatrix),
+    Diffusion {
+        errors: &'a mut [i16],
+        row: Option<i32>,
+    },
+}
+
+/// Draw target wrapper which dithers [`Rgb888`] colors.
+///
+/// Ordered dithering only depends on the pixel position, which makes it
+/// suitable for any drawing operation. Error diffusion distributes the
+/// quantization error to the pixels to the right and below and expects
+/// pixels to be drawn in row major order, like images drawn with
+/// [`fill_contiguous`](DrawTarget::fill_contiguous). The accumulated error is
+/// discarded whenever drawing doesn't continue in the same or the next row.
+pub struct Dither<'a, T>
+where
+    T: DrawTarget,
+    T::Color: DitherColor,
+{
+    target: &'a mut T,
+    mode: Mode<'a>,
+}
+
+impl<'a, T> Dither<'a, T>
+where
+    T: DrawTarget,
+    T::Color: DitherColor,
+{
+    /// Creates a dithering draw target which uses an ordered Bayer matrix.
+    pub fn bayer(target: &'a mut T, matrix: BayerMatrix) -> Self {
+        Self {
+            target,
+            mode: Mode::Ordered(matrix),
+        }
+    }
+
+    /// Creates a dithering draw target which uses Floyd-Steinberg error diffusion.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `errors` can't hold two rows of errors for the width of
+    /// `target`, see [`ERROR_VALUES_PER_PIXEL`].
+    pub fn floyd_steinberg(target: &'a mut T, errors: &'a mut [i16]) -> Self {
+        let len = 2 * ERROR_VALUES_PER_PIXEL * target.bounding_box().size.width as usize;
+        assert!(
+            errors.len() >= len,
+            "Error buffer is too small. Expected at least {}, got {}.",
+            len,
+            errors.len()
+        );
+
+        Self {
+            target,
+            mode: Mode::Diffusion {
+                errors: &mut errors[..len],
+                row: None,
+            },
+        }
+    }
+
+    /// Quantizes a color at the given position.
+    fn quantize(&mut self, Pixel(point, color): Pixel<Rgb888>, bounds: &Rectangle) -> T::Color {
+        let channels = [color.r(), color.g(), color.b()];
+        let bits = T::Color::CHANNEL_BITS;
+
+        let x = point.x - bounds.top_left.x;
+        let width = bounds.size.width as i32;
+
+        let quantized = match &mut self.mode {
+            Mode::Ordered(matrix) => {
+                let threshold = matrix.value(point.x, point.y);
+                let levels = 1 << (2 * matrix.order());
+                core::array::from_fn(|i| ordered(channels[i], bits[i], threshold, levels))
+            }
+            Mode::Diffusion { errors, row } if (0..width).contains(&x) => {
+                let values = ERROR_VALUES_PER_PIXEL * width as usize;
+                match *row {
+                    Some(row) if row == point.y => {}
+                    Some(row) if row + 1 == point.y => {
+                        errors.copy_within(values.., 0);
+                        errors[values..].fill(0);
+                    }
+                    _ => errors.fill(0),
+                }
+                *row = Some(point.y);
+
+                let (current, next) = errors.split_at_mut(values);
+                let x = x as usize;
+                core::array::from_fn(|i| {
+                    let index = x * ERROR_VALUES_PER_PIXEL + i;
+                    let value = (i16::from(channels[i]) + current[index]).clamp(0, 255);
+                    let quantized = nearest(value as u8, bits[i]);
+                    let error = value - i16::from(expand(quantized, bits[i]));
+
+                    let step = ERROR_VALUES_PER_PIXEL;
+                    if x + 1 < width as usize {
+                        current[index + step] += error * 7 / 16;
+                        next[index + step] += error / 16;
+                    }
+                    if x > 0 {
+                        next[index - step] += error * 3 / 16;
+                    }
+                    next[index] += error * 5 / 16;
+
+                    quantized
+                })
+            }
+            Mode::Diffusion { .. } => core::array::from_fn(|i| nearest(channels[i], bits[i])),
+        };
+
+        T::Color::from_channels(quantized)
+    }
+}
+
+/// Returns the nearest level of a `bits` bit channel.
+fn nearest(value: u8, bits: u8) -> u8 {
+    let max = (1u32 << bits) - 1;
+    ((u32::from(value) * max + 127) / 255) as u8
+}
+
+/// Expands a `bits` bit channel to 8 bits.
+fn expand(value: u8, bits: u8) -> u8 {
+    let max = (1u32 << bits) - 1;
+    ((u32::from(value) * 255 + max / 2) / max) as u8
+}
+
+/// Quantizes a channel using a threshold in the range `0..levels`.
+fn ordered(value: u8, bits: u8, threshold: u32, levels: u32) -> u8 {
+    let max = (1u32 << bits) - 1;
+    // floor(value * max / 255 + (threshold + 0.5) / levels)
+    let numerator = 2 * u32::from(value) * max * levels + (2 * threshold + 1) * 255;
+    (numerator / (2 * 255 * levels)).min(max) as u8
+}
+
+impl<T> Dimensions for Dither<'_, T>
+where
+    T: DrawTarget,
+    T::Color: DitherColor,
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
+
+impl<T> DrawTarget for Dither<'_, T>
+where
+    T: DrawTarget,
+    T::Color: DitherColor,
+{
+    type Color = Rgb888;
+    type Error = T::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let bounds = self.target.bounding_box();
+
+        // The pixels are quantized in batches, because the target can't be
+        // borrowed while the dither state is updated.
+        let mut pixels = pixels.into_iter().peekable();
+        let mut batch = [Pixel(Default::default(), T::Color::from_channels([0; 3])); 32];
+        while pixels.peek().is_some() {
+            let mut len = 0;
+            for pixel in pixels.by_ref().take(batch.len()) {
+                batch[len] = Pixel(pixel.0, self.quantize(pixel, &bounds));
+                len += 1;
+            }
+            self.target.draw_iter(batch[..len].iter().copied())?;
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.draw_iter(area.points().map(|point| Pixel(point, color)))
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let area = self.bounding_box();
+        self.fill_solid(&area, color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::{prelude::Point, Drawable};
+
+    use crate::raw_framebuf::{IntoRawBytes, RawFrameBuf};
+
+    use super::*;
+
+    fn bytes(colors: &[Rgb565]) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        for (chunk, color) in bytes.chunks_exact_mut(2).zip(colors) {
+            chunk.copy_from_slice(&color.into_raw_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn bayer_matrix_values() {
+        let values: [u32; 4] =
+            core::array::from_fn(|i| BayerMatrix::Bayer2.value(i as i32 % 2, i as i32 / 2));
+        assert_eq!(values, [0, 2, 3, 1]);
+
+        let mut seen = [false; 64];
+        for y in 0..8 {
+            for x in 0..8 {
+                seen[BayerMatrix::Bayer8.value(x, y) as usize] = true;
+            }
+        }
+        assert!(seen.iter().all(|seen| *seen));
+    }
+
+    #[test]
+    fn ordered_dithering() {
+        let mut buffer = [0u8; 8];
+        let mut fb = RawFrameBuf::<Rgb565, _, 2>::new(&mut buffer[..], 2, 2);
+        Dither::bayer(&mut fb, BayerMatrix::Bayer2)
+            .clear(Rgb888::new(128, 128, 128))
+            .unwrap();
+
+        let (low, high) = (Rgb565::new(15, 31, 15), Rgb565::new(16, 32, 16));
+        assert_eq!(fb.as_bytes(), &bytes(&[low, high, high, low]));
+    }
+
+    #[test]
+    fn ordered_dithering_keeps_exact_levels() {
+        for value in [0, 0x11, 0x88, 0xFF] {
+            for x in 0..4 {
+                for y in 0..4 {
+                    let threshold = BayerMatrix::Bayer4.value(x, y);
+                    assert_eq!(ordered(value, 4, threshold, 16), value >> 4);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn error_diffusion() {
+        let mut buffer = [0u8; 8];
+        let mut fb = RawFrameBuf::<Rgb565, _, 2>::new(&mut buffer[..], 4, 1);
+        let mut errors = [0; 2 * 3 * 4];
+        let mut dither = Dither::floyd_steinberg(&mut fb, &mut errors);
+        for x in 0..4 {
+            Pixel(Point::new(x, 0), Rgb888::new(128, 128, 128))
+                .draw(&mut dither)
+                .unwrap();
+        }
+
+        let (low, high) = (Rgb565::new(15, 32, 15), Rgb565::new(16, 32, 16));
+        assert_eq!(fb.as_bytes(), &bytes(&[high, low, high, low]));
+    }
+
+    #[test]
+    fn dithers_rgb444() {
+        let mut buffer = [0u8; 3];
+        let mut fb = crate::raw_framebuf::Rgb444FrameBuf::<Rgb444, _>::new(&mut buffer[..], 2, 1);
+        Dither::bayer(&mut fb, BayerMatrix::Bayer2)
+            .clear(Rgb888::new(0x88, 0x08, 0xFF))
+            .unwrap();
+
+        assert_eq!(fb.as_bytes(), &[0x80, 0xF8, 0x1F]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn error_buffer_must_hold_two_rows() {
+        let mut buffer = [0u8; 8];
+        let mut fb = RawFrameBuf::<Rgb565, _, 2>::new(&mut buffer[..], 4, 1);
+        Dither::floyd_steinberg(&mut fb, &mut [0; 8]);
+    }
+}
@@ -0,0 +1,243 @@
+//! Solid fills without a framebuffer.
+
+use embedded_graphics::{
+    prelude::{Point, Size},
+    primitives::Rectangle,
+};
+use embedded_hal::digital::OutputPin;
+
+use crate::{
+    interface::{Interface, InterfacePixelFormat, PIXEL_CHUNK_WORDS},
+    models::Model,
+    Display,
+};
+
+/// Fills `buffer` with copies of `color` and returns the number of words used.
+///
+/// Only whole pixels are written to the buffer.
+fn fill_pattern<C, DW>(buffer: &mut [DW], color: C) -> usize
+where
+    C: InterfacePixelFormat<DW>,
+    DW: Copy,
+{
+    let words = C::WORDS_PER_PIXEL;
+    assert!(
+        buffer.len() >= words,
+        "Fill buffer is too small. Expected at least {}, got {}.",
+        words,
+        buffer.len()
+    );
+
+    let len = buffer.len() - buffer.len() % words;
+    color.write_words(&mut buffer[..words]);
+    for start in (words..len).step_by(words) {
+        buffer.copy_within(0..words, start);
+    }
+    len
+}
+
+impl<DI, M, RST> Display<DI, M, RST>
+where
+    DI: Interface,
+    M: Model,
+    RST: OutputPin,
+{
+    /// Fills a rectangular area of the display with a solid color.
+    ///
+    /// The address window is set once and a small pattern buffer on the stack
+    /// is sent repeatedly until the area is covered. The area is clipped to
+    /// the display bounds.
+    pub async fn fill_rect<C>(&mut self, area: &Rectangle, color: C) -> Result<(), DI::Error>
+    where
+        C: InterfacePixelFormat<DI::Word>,
+        DI::Word: Default,
+    {
+        let mut buffer = [DI::Word::default(); PIXEL_CHUNK_WORDS];
+        self.fill_rect_with_buffer(area, color, &mut buffer).await
+    }
+
+    /// Fills a rectangular area of the display using a user supplied pattern buffer.
+    ///
+    /// Larger buffers need fewer transfers. See [`fill_rect`](Self::fill_rect)
+    /// for more information.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` can't hold at least one pixel.
+    pub async fn fill_rect_with_buffer<C>(
+        &mut self,
+        area: &Rectangle,
+        color: C,
+        buffer: &mut [DI::Word],
+    ) -> Result<(), DI::Error>
+    where
+        C: InterfacePixelFormat<DI::Word>,
+    {
+        let len = fill_pattern(buffer, color);
+
+        let (width, height) = self.options.display_size();
+        let bounds = Rectangle::new(
+            Point::zero(),
+            Size::new(u32::from(width), u32::from(height)),
+        );
+        let area = area.intersection(&bounds);
+        let Some(bottom_right) = area.bottom_right() else {
+            return Ok(());
+        };
+
+        self.set_address_window(
+            area.top_left.x as u16,
+            area.top_left.y as u16,
+            bottom_right.x as u16,
+            bottom_right.y as u16,
+        )
+        .await?;
+        M::write_memory_start(&mut self.di).await?;
+
+        let mut remaining =
+            area.size.width as usize * area.size.height as usize * C::WORDS_PER_PIXEL;
+        while remaining > 0 {
+            let chunk = remaining.min(len);
+            self.di.send_data_slice(&buffer[..chunk]).await?;
+            remaining -= chunk;
+        }
+
+        Ok(())
+    }
+
+    /// Fills the entire display with a solid color.
+    pub async fn clear<C>(&mut self, color: C) -> Result<(), DI::Error>
+    where
+        C: InterfacePixelFormat<DI::Word>,
+        DI::Word: Default,
+    {
+        let (width, height) = self.options.display_size();
+        let area = Rectangle::new(
+            Point::zero(),
+            Size::new(u32::from(width), u32::from(height)),
+        );
+        self.fill_rect(&area, color).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use embassy_futures::block_on;
+    use embedded_graphics::{
+        pixelcolor::{Rgb565, Rgb666},
+        prelude::{RgbColor, WebColors},
+    };
+    use std::{vec, vec::Vec};
+
+    use crate::{
+        models::ST7789,
+        testing::{mock_display, MockInterface, Parallel16Bit, Transfer},
+    };
+
+    use super::*;
+
+    const RED: [u8; 2] = [0xF8, 0x00];
+
+    #[test]
+    fn pattern_contains_whole_pixels() {
+        let mut buffer = [0u8; 8];
+        assert_eq!(fill_pattern(&mut buffer, Rgb666::RED), 6);
+        assert_eq!(buffer, [0xFC, 0, 0, 0xFC, 0, 0, 0, 0]);
+
+        let mut buffer = [0u16; 3];
+        assert_eq!(fill_pattern(&mut buffer, Rgb565::CSS_CYAN), 3);
+        assert_eq!(buffer, [0x07FF; 3]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn pattern_buffer_must_hold_a_pixel() {
+        fill_pattern(&mut [0u8; 2], Rgb666::RED);
+    }
+
+    #[test]
+    fn fills_area_in_chunks() {
+        let mut display = mock_display(ST7789, MockInterface::new(), (8, 8));
+        let area = Rectangle::new(Point::new(1, 2), Size::new(3, 2));
+        block_on(display.fill_rect_with_buffer(&area, Rgb565::RED, &mut [0; 5])).unwrap();
+
+        display.di.assert_transfers(&[
+            Transfer::Command(0x2A, vec![0, 1, 0, 3]),
+            Transfer::Command(0x2B, vec![0, 2, 0, 3]),
+            Transfer::Command(0x2C, vec![]),
+            Transfer::Data([RED; 2].concat()),
+            Transfer::Data([RED; 2].concat()),
+            Transfer::Data([RED; 2].concat()),
+        ]);
+    }
+
+    #[test]
+    fn clips_area_to_display() {
+        let mut display = mock_display(ST7789, MockInterface::new(), (8, 8));
+        let area = Rectangle::new(Point::new(-2, 6), Size::new(4, 4));
+        block_on(display.fill_rect(&area, Rgb565::RED)).unwrap();
+
+        display.di.assert_transfers(&[
+            Transfer::Command(0x2A, vec![0, 0, 0, 1]),
+            Transfer::Command(0x2B, vec![0, 6, 0, 7]),
+            Transfer::Command(0x2C, vec![]),
+            Transfer::Data([RED; 4].concat()),
+        ]);
+
+        display.di.clear();
+        for top_left in [Point::new(8, 0), Point::new(0, -4), Point::new(-3, 3)] {
+            let area = Rectangle::new(top_left, Size::new(3, 4));
+            block_on(display.fill_rect(&area, Rgb565::RED)).unwrap();
+        }
+        display.di.assert_transfers(&[]);
+    }
+
+    #[test]
+    fn fill_sends_whole_area() {
+        let mut display = mock_display(ST7789, MockInterface::new(), (16, 16));
+        let area = Rectangle::new(Point::zero(), Size::new(16, 16));
+        block_on(display.fill_rect(&area, Rgb565::RED)).unwrap();
+
+        let lengths = display
+            .di
+            .transfers()
+            .iter()
+            .filter_map(|transfer| match transfer {
+                Transfer::Data(data) => Some(data.len()),
+                Transfer::Command(..) => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(lengths, [192, 192, 128]);
+        assert_eq!(display.di.data(), RED.repeat(16 * 16));
+    }
+
+    #[test]
+    fn fills_with_u16_words() {
+        let mut display = mock_display(ST7789, MockInterface::<Parallel16Bit>::default(), (8, 8));
+        let area = Rectangle::new(Point::new(6, 6), Size::new(4, 4));
+        block_on(display.fill_rect_with_buffer(&area, Rgb565::RED, &mut [0; 3])).unwrap();
+
+        display.di.assert_transfers(&[
+            Transfer::Command(0x2A, vec![0, 6, 0, 7]),
+            Transfer::Command(0x2B, vec![0, 6, 0, 7]),
+            Transfer::Command(0x2C, vec![]),
+            Transfer::Data(vec![0xF800; 3]),
+            Transfer::Data(vec![0xF800]),
+        ]);
+    }
+
+    #[test]
+    fn clears_display() {
+        let mut display = mock_display(ST7789, MockInterface::<Parallel16Bit>::default(), (3, 2));
+        block_on(display.clear(Rgb565::RED)).unwrap();
+
+        display.di.assert_transfers(&[
+            Transfer::Command(0x2A, vec![0, 0, 0, 2]),
+            Transfer::Command(0x2B, vec![0, 0, 0, 1]),
+            Transfer::Command(0x2C, vec![]),
+            Transfer::Data(vec![0xF800; 6]),
+        ]);
+    }
+}
@@ -0,0 +1,586 @@
+//! Streaming of BMP and QOI images.
+//!
+//! Images are decoded while they are read and sent to the display in small
+//! chunks, no framebuffer is required. The image data can be read from a byte
+//! slice or from any [`embedded_io_async::Read`] implementation, for example a
+//! file on an SD card.
+//!
+//! ```
+//! # async fn example<DI, M, RST>(display: &mut mipidsi::Display<DI, M, RST>)
+//! # where DI: mipidsi::interface::Interface<Word = u8>, M: mipidsi::models::Model, RST: embedded_hal::digital::OutputPin {
+//! use embedded_graphics::{pixelcolor::Rgb565, prelude::Point};
+//! use mipidsi::image::Bmp;
+//!
+//! let data: &[u8] = &[/* BMP file */];
+//! if let Ok(mut bmp) = Bmp::new(data).await {
+//!     display.show_image::<Rgb565, _>(&mut bmp, Point::new(10, 20)).await.ok();
+//! }
+//! # }
+//! ```
+
+use embedded_graphics::{
+    pixelcolor::Rgb888,
+    prelude::{Point, Size},
+    primitives::Rectangle,
+};
+use embedded_hal::digital::OutputPin;
+use embedded_io_async::Read;
+
+use crate::{
+    interface::{Interface, InterfacePixelFormat, PIXEL_CHUNK_WORDS},
+    models::Model,
+    Display,
+};
+
+mod bmp;
+pub use bmp::*;
+
+mod qoi;
+pub use qoi::*;
+
+/// Error which occurred while decoding an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageError<E> {
+    /// The reader returned an error.
+    Read(E),
+    /// The image data ended before the image was complete.
+    UnexpectedEof,
+    /// The image header is invalid.
+    InvalidHeader,
+    /// The image uses a format variant which isn't supported.
+    Unsupported,
+    /// The image data is invalid.
+    InvalidData,
+}
+
+/// Error returned by [`Display::show_image`].
+#[derive(Debug)]
+pub enum ShowImageError<DIError, ReadError> {
+    /// The display interface returned an error.
+    Interface(DIError),
+    /// The image couldn't be decoded.
+    Image(ImageError<ReadError>),
+}
+
+/// Order in which the rows of an image are decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowOrder {
+    /// The first row is the top row of the image.
+    TopDown,
+    /// The first row is the bottom row of the image.
+    BottomUp,
+}
+
+/// Image decoder which returns one pixel at a time.
+pub trait ImageDecoder {
+    /// Error type of the reader.
+    type Error;
+
+    /// Returns the size of the image.
+    fn size(&self) -> Size;
+
+    /// Returns the order in which rows are decoded.
+    fn row_order(&self) -> RowOrder;
+
+    /// Decodes the next pixel.
+    ///
+    /// Pixels are decoded from left to right, the rows are decoded in
+    /// [`row_order`](Self::row_order). `None` is returned for pixels which are
+    /// transparent or not defined by the image. Once all pixels were decoded
+    /// `None` is returned.
+    async fn next_pixel(&mut self) -> Result<Option<Rgb888>, ImageError<Self::Error>>;
+}
+
+/// Buffered reader for the image decoders.
+struct ByteReader<R> {
+    reader: R,
+    buffer: [u8; 64],
+    pos: usize,
+    len: usize,
+}
+
+impl<R: Read> ByteReader<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: [0; 64],
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    async fn read_u8(&mut self) -> Result<u8, ImageError<R::Error>> {
+        if self.pos == self.len {
+            self.len = self
+                .reader
+                .read(&mut self.buffer)
+                .await
+                .map_err(ImageError::Read)?;
+            self.pos = 0;
+            if self.len == 0 {
+                return Err(ImageError::UnexpectedEof);
+            }
+        }
+
+        let byte = self.buffer[self.pos];
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    async fn read_array<const N: usize>(&mut self) -> Result<[u8; N], ImageError<R::Error>> {
+        let mut bytes = [0; N];
+        for byte in &mut bytes {
+            *byte = self.read_u8().await?;
+        }
+        Ok(bytes)
+    }
+
+    async fn skip(&mut self, count: usize) -> Result<(), ImageError<R::Error>> {
+        for _ in 0..count {
+            self.read_u8().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Pixel data which is waiting to be sent to the display.
+struct ImageChunk<W> {
+    buffer: [W; PIXEL_CHUNK_WORDS],
+    len: usize,
+    /// `true` if an address window is open.
+    open: bool,
+    /// `true` if the open address window covers all remaining visible rows.
+    all_rows: bool,
+    /// `true` if no data was sent since the memory write was started.
+    started: bool,
+}
+
+impl<DI, M, RST> Display<DI, M, RST>
+where
+    DI: Interface,
+    M: Model,
+    RST: OutputPin,
+{
+    /// Decodes an image and shows it with its top left corner at `top_left`.
+    ///
+    /// The pixels are converted to `C` and encoded in the wire format of the
+    /// interface into a small buffer on the stack. The image is clipped to the
+    /// display bounds and pixels which aren't defined by the image, like
+    /// transparent pixels, aren't changed. Decoding stops after the last
+    /// visible row.
+    ///
+    /// Every buffer is sent with a separate transfer. Buffers which continue
+    /// a memory write are preceded by a write memory continue command, which
+    /// allows the image to be read from another device on the same bus.
+    pub async fn show_image<C, D>(
+        &mut self,
+        image: &mut D,
+        top_left: Point,
+    ) -> Result<(), ShowImageError<DI::Error, D::Error>>
+    where
+        C: InterfacePixelFormat<DI::Word> + From<Rgb888>,
+        DI::Word: Default,
+        D: ImageDecoder,
+    {
+        let (width, height) = self.options.display_size();
+        let bounds = Rectangle::new(
+            Point::zero(),
+            Size::new(u32::from(width), u32::from(height)),
+        );
+        let size = image.size();
+        let visible = Rectangle::new(top_left, size).intersection(&bounds);
+        let Some(bottom_right) = visible.bottom_right() else {
+            return Ok(());
+        };
+        let (first_x, last_x) = (visible.top_left.x, bottom_right.x);
+        let (first_y, last_y) = (visible.top_left.y, bottom_right.y);
+        let order = image.row_order();
+
+        let mut chunk = ImageChunk {
+            buffer: [DI::Word::default(); PIXEL_CHUNK_WORDS],
+            len: 0,
+            open: false,
+            all_rows: false,
+            started: false,
+        };
+
+        for row in 0..size.height {
+            let y = top_left.y
+                + match order {
+                    RowOrder::TopDown => row,
+                    RowOrder::BottomUp => size.height - 1 - row,
+                } as i32;
+
+            let done = match order {
+                RowOrder::TopDown => y > last_y,
+                RowOrder::BottomUp => y < first_y,
+            };
+            if done {
+                break;
+            }
+
+            if y < first_y || y > last_y {
+                for _ in 0..size.width {
+                    image.next_pixel().await.map_err(ShowImageError::Image)?;
+                }
+                continue;
+            }
+
+            for column in 0..size.width {
+                let pixel = image.next_pixel().await.map_err(ShowImageError::Image)?;
+                let x = top_left.x + column as i32;
+                if x < first_x || x > last_x {
+                    continue;
+                }
+
+                let Some(color) = pixel else {
+                    self.end_image_window(&mut chunk)
+                        .await
+                        .map_err(ShowImageError::Interface)?;
+                    continue;
+                };
+
+                if !chunk.open {
+                    // Consecutive top-down rows can share one window if the
+                    // window starts at the first visible column.
+                    chunk.all_rows = order == RowOrder::TopDown && x == first_x;
+                    let ey = if chunk.all_rows { last_y } else { y };
+                    self.set_address_window(x as u16, y as u16, last_x as u16, ey as u16)
+                        .await
+                        .map_err(ShowImageError::Interface)?;
+                    M::write_memory_start(&mut self.di)
+                        .await
+                        .map_err(ShowImageError::Interface)?;
+                    chunk.open = true;
+                    chunk.started = true;
+                }
+
+                if chunk.len + C::WORDS_PER_PIXEL > chunk.buffer.len() {
+                    self.send_image_chunk(&mut chunk)
+                        .await
+                        .map_err(ShowImageError::Interface)?;
+                }
+                C::from(color).write_words(&mut chunk.buffer[chunk.len..]);
+                chunk.len += C::WORDS_PER_PIXEL;
+            }
+
+            if !chunk.all_rows {
+                self.end_image_window(&mut chunk)
+                    .await
+                    .map_err(ShowImageError::Interface)?;
+            }
+        }
+
+        self.end_image_window(&mut chunk)
+            .await
+            .map_err(ShowImageError::Interface)
+    }
+
+    /// Sends the buffered image data.
+    async fn send_image_chunk(
+        &mut self,
+        chunk: &mut ImageChunk<DI::Word>,
+    ) -> Result<(), DI::Error> {
+        if chunk.len == 0 {
+            return Ok(());
+        }
+
+        if !chunk.started {
+            M::write_memory_continue(&mut self.di).await?;
+        }
+        self.di.send_data_slice(&chunk.buffer[..chunk.len]).await?;
+        chunk.len = 0;
+        chunk.started = false;
+        Ok(())
+    }
+
+    /// Sends the buffered image data and closes the address window.
+    async fn end_image_window(
+        &mut self,
+        chunk: &mut ImageChunk<DI::Word>,
+    ) -> Result<(), DI::Error> {
+        self.send_image_chunk(chunk).await?;
+        chunk.open = false;
+        chunk.all_rows = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use embassy_futures::block_on;
+    use embedded_graphics::{pixelcolor::Rgb565, prelude::RgbColor};
+    use std::{vec, vec::Vec};
+
+    use crate::{
+        models::ST7789,
+        raw_framebuf::IntoRawBytes,
+        testing::{mock_display, MockInterface, Transfer},
+    };
+
+    use super::*;
+
+    /// Decodes a 7x5 image into RGB bytes in top-down order.
+    fn decode<D: ImageDecoder>(image: &mut D) -> [Option<Rgb888>; 35]
+    where
+        D::Error: core::fmt::Debug,
+    {
+        assert_eq!(image.size(), Size::new(7, 5));
+        let mut pixels = [None; 35];
+        for row in 0..5 {
+            let y = match image.row_order() {
+                RowOrder::TopDown => row,
+                RowOrder::BottomUp => 4 - row,
+            };
+            for x in 0..7 {
+                pixels[y * 7 + x] = block_on(image.next_pixel()).unwrap();
+            }
+        }
+        assert_eq!(block_on(image.next_pixel()).unwrap(), None);
+        pixels
+    }
+
+    /// Returns the pixels of the golden image.
+    fn golden() -> [Option<Rgb888>; 35] {
+        let ppm = include_bytes!("image/testdata/pattern.ppm");
+        let (header, data) = ppm.split_at(ppm.len() - 35 * 3);
+        assert_eq!(header, b"P6\n7 5\n255\n");
+        core::array::from_fn(|i| Some(Rgb888::new(data[i * 3], data[i * 3 + 1], data[i * 3 + 2])))
+    }
+
+    fn decode_bmp(data: &[u8]) -> [Option<Rgb888>; 35] {
+        decode(&mut block_on(Bmp::new(data)).unwrap())
+    }
+
+    #[test]
+    fn bmp_matches_golden_image() {
+        let golden = golden();
+        for (name, data) in [
+            ("bgr24", &include_bytes!("image/testdata/bgr24.bmp")[..]),
+            (
+                "bgr24_top_down",
+                include_bytes!("image/testdata/bgr24_top_down.bmp"),
+            ),
+            ("xrgb32", include_bytes!("image/testdata/xrgb32.bmp")),
+            (
+                "xbgr32_bitfields",
+                include_bytes!("image/testdata/xbgr32_bitfields.bmp"),
+            ),
+            ("rgb565", include_bytes!("image/testdata/rgb565.bmp")),
+            ("rgb555", include_bytes!("image/testdata/rgb555.bmp")),
+            ("indexed8", include_bytes!("image/testdata/indexed8.bmp")),
+            ("indexed4", include_bytes!("image/testdata/indexed4.bmp")),
+            ("rle8", include_bytes!("image/testdata/rle8.bmp")),
+            ("rle4", include_bytes!("image/testdata/rle4.bmp")),
+        ] {
+            assert_eq!(decode_bmp(data), golden, "{}", name);
+        }
+    }
+
+    #[test]
+    fn qoi_matches_golden_image() {
+        let data = include_bytes!("image/testdata/pattern.qoi");
+        let mut qoi = block_on(Qoi::new(&data[..])).unwrap();
+        assert_eq!(decode(&mut qoi), golden());
+    }
+
+    #[test]
+    fn qoi_skips_transparent_pixels() {
+        let data = include_bytes!("image/testdata/gradient.qoi");
+        let mut qoi = block_on(Qoi::new(&data[..])).unwrap();
+        assert_eq!(qoi.size(), Size::new(8, 4));
+        for y in 0..4 {
+            for x in 0..8 {
+                let expected = (!matches!((x, y), (3, 1) | (4, 1)))
+                    .then(|| Rgb888::new(x * 20 + y, 100 + y * 30, 200 - x * 3));
+                assert_eq!(block_on(qoi.next_pixel()), Ok(expected), "{x},{y}");
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_images_are_rejected() {
+        let data = include_bytes!("image/testdata/bgr24.bmp");
+        assert_eq!(
+            block_on(Bmp::new(&data[..20])).err(),
+            Some(ImageError::UnexpectedEof)
+        );
+        // The padding of the last row is never read.
+        let mut bmp = block_on(Bmp::new(&data[..data.len() - 4])).unwrap();
+        let result = block_on(async {
+            for _ in 0..35 {
+                bmp.next_pixel().await?;
+            }
+            Ok(())
+        });
+        assert_eq!(result, Err(ImageError::UnexpectedEof));
+
+        assert_eq!(
+            block_on(Qoi::new(&b"qoif\0\0\0\x01"[..])).err(),
+            Some(ImageError::UnexpectedEof)
+        );
+        assert_eq!(
+            block_on(Bmp::new(&include_bytes!("image/testdata/pattern.qoi")[..])).err(),
+            Some(ImageError::InvalidHeader)
+        );
+    }
+
+    #[test]
+    fn rle_delta_leaves_pixels_undefined() {
+        // 4x2 RLE8 image: red run, delta (1, 1), white run, end of bitmap.
+        let mut data = [0u8; 54 + 8 + 10];
+        data[..2].copy_from_slice(b"BM");
+        data[10] = 54 + 8;
+        data[14] = 40;
+        data[18] = 4;
+        data[22] = 2;
+        data[26] = 1;
+        data[28] = 8;
+        data[30] = 1;
+        data[46] = 2;
+        data[54..62].copy_from_slice(&[0, 0, 0xFF, 0, 0xFF, 0xFF, 0xFF, 0]);
+        data[62..].copy_from_slice(&[2, 0, 0, 2, 1, 1, 1, 1, 0, 1]);
+
+        let mut bmp = block_on(Bmp::new(&data[..])).unwrap();
+        assert_eq!(bmp.row_order(), RowOrder::BottomUp);
+        let mut pixels = [None; 8];
+        for pixel in &mut pixels {
+            *pixel = block_on(bmp.next_pixel()).unwrap();
+        }
+        let (red, white) = (Some(Rgb888::RED), Some(Rgb888::WHITE));
+        assert_eq!(pixels, [red, red, None, None, None, None, None, white]);
+    }
+
+    #[test]
+    fn overflowing_sizes_are_rejected() {
+        let mut data = include_bytes!("image/testdata/xrgb32.bmp").to_vec();
+        data[18..22].copy_from_slice(&i32::MAX.to_le_bytes());
+        assert_eq!(
+            block_on(Bmp::new(&data[..])).err(),
+            Some(ImageError::InvalidHeader)
+        );
+
+        // RLE8 image with a delta which skips more pixels than fit into a u32.
+        let mut data = [0u8; 54 + 4 + 4];
+        data[..2].copy_from_slice(b"BM");
+        data[10] = 54 + 4;
+        data[14] = 40;
+        data[18..22].copy_from_slice(&(1i32 << 28).to_le_bytes());
+        data[22] = 255;
+        data[26] = 1;
+        data[28] = 8;
+        data[30] = 1;
+        data[46] = 1;
+        data[58..].copy_from_slice(&[0, 2, 0, 255]);
+
+        let mut bmp = block_on(Bmp::new(&data[..])).unwrap();
+        assert_eq!(block_on(bmp.next_pixel()), Err(ImageError::InvalidData));
+    }
+
+    /// Returns the Rgb565 data of the golden image pixels in the given area.
+    fn golden_data(columns: core::ops::Range<usize>, rows: core::ops::Range<usize>) -> Vec<u8> {
+        let golden = golden();
+        rows.flat_map(|y| columns.clone().map(move |x| (x, y)))
+            .flat_map(|(x, y)| Rgb565::from(golden[y * 7 + x].unwrap()).into_raw_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn shows_image_clipped_on_every_edge() {
+        let mut display = mock_display(ST7789, MockInterface::new(), (5, 3));
+        let data = include_bytes!("image/testdata/bgr24_top_down.bmp");
+        let mut bmp = block_on(Bmp::new(&data[..])).unwrap();
+        block_on(display.show_image::<Rgb565, _>(&mut bmp, Point::new(-1, -1))).unwrap();
+
+        display.di.assert_transfers(&[
+            Transfer::Command(0x2A, vec![0, 0, 0, 4]),
+            Transfer::Command(0x2B, vec![0, 0, 0, 2]),
+            Transfer::Command(0x2C, vec![]),
+            Transfer::Data(golden_data(1..6, 1..4)),
+        ]);
+    }
+
+    #[test]
+    fn shows_bottom_up_image_row_by_row() {
+        let mut display = mock_display(ST7789, MockInterface::new(), (6, 3));
+        let data = include_bytes!("image/testdata/bgr24.bmp");
+        let mut bmp = block_on(Bmp::new(&data[..])).unwrap();
+        block_on(display.show_image::<Rgb565, _>(&mut bmp, Point::new(1, -1))).unwrap();
+
+        display.di.assert_transfers(&[
+            Transfer::Command(0x2A, vec![0, 1, 0, 5]),
+            Transfer::Command(0x2B, vec![0, 2, 0, 2]),
+            Transfer::Command(0x2C, vec![]),
+            Transfer::Data(golden_data(0..5, 3..4)),
+            Transfer::Command(0x2B, vec![0, 1, 0, 1]),
+            Transfer::Command(0x2C, vec![]),
+            Transfer::Data(golden_data(0..5, 2..3)),
+            Transfer::Command(0x2B, vec![0, 0, 0, 0]),
+            Transfer::Command(0x2C, vec![]),
+            Transfer::Data(golden_data(0..5, 1..2)),
+        ]);
+    }
+
+    #[test]
+    fn shows_transparent_image_in_windows() {
+        const WIDTH: u32 = 16;
+        const HEIGHT: u32 = 10;
+        let color = |x: u32, y: u32| Rgb888::new(x as u8 * 16, y as u8 * 25, 128);
+
+        // Every pixel is encoded as an RGBA literal, (5, 1) is transparent.
+        let mut data = Vec::new();
+        data.extend_from_slice(b"qoif");
+        data.extend_from_slice(&WIDTH.to_be_bytes());
+        data.extend_from_slice(&HEIGHT.to_be_bytes());
+        data.extend_from_slice(&[4, 0]);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let c = color(x, y);
+                let alpha = if (x, y) == (5, 1) { 0 } else { 255 };
+                data.extend_from_slice(&[0xFF, c.r(), c.g(), c.b(), alpha]);
+            }
+        }
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+
+        let mut display = mock_display(
+            ST7789,
+            MockInterface::new(),
+            (WIDTH as usize, HEIGHT as usize),
+        );
+        let mut qoi = block_on(Qoi::new(&data[..])).unwrap();
+        block_on(display.show_image::<Rgb565, _>(&mut qoi, Point::zero())).unwrap();
+
+        let pixels = |points: &mut dyn Iterator<Item = (u32, u32)>, count: usize| -> Vec<u8> {
+            points
+                .take(count)
+                .flat_map(|(x, y)| Rgb565::from(color(x, y)).into_raw_bytes())
+                .collect()
+        };
+        let mut points = (0..HEIGHT).flat_map(|y| (0..WIDTH).map(move |x| (x, y)));
+        let first = pixels(&mut points, 21);
+        points.next();
+        let second = pixels(&mut points, 10);
+        // The last window is larger than the chunk buffer.
+        let third = pixels(&mut points, 96);
+        let fourth = pixels(&mut points, 32);
+
+        display.di.assert_transfers(&[
+            Transfer::Command(0x2A, vec![0, 0, 0, 15]),
+            Transfer::Command(0x2B, vec![0, 0, 0, 9]),
+            Transfer::Command(0x2C, vec![]),
+            Transfer::Data(first),
+            Transfer::Command(0x2A, vec![0, 6, 0, 15]),
+            Transfer::Command(0x2B, vec![0, 1, 0, 1]),
+            Transfer::Command(0x2C, vec![]),
+            Transfer::Data(second),
+            Transfer::Command(0x2A, vec![0, 0, 0, 15]),
+            Transfer::Command(0x2B, vec![0, 2, 0, 9]),
+            Transfer::Command(0x2C, vec![]),
+            Transfer::Data(third),
+            Transfer::Command(0x3C, vec![]),
+            Transfer::Data(fourth),
+        ]);
+    }
+}
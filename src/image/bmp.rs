@@ -0,0 +1,362 @@
+//! BMP decoder.
+
+use embedded_graphics::{pixelcolor::Rgb888, prelude::Size};
+use embedded_io_async::Read;
+
+use super::{ByteReader, ImageDecoder, ImageError, RowOrder};
+
+/// Bit masks of the red, green and blue channels.
+#[derive(Debug, Clone, Copy)]
+struct ChannelMasks([u32; 3]);
+
+impl ChannelMasks {
+    const RGB555: Self = Self([0x7C00, 0x03E0, 0x001F]);
+    const RGB888: Self = Self([0x00FF_0000, 0x0000_FF00, 0x0000_00FF]);
+
+    fn color(&self, value: u32) -> Rgb888 {
+        let [r, g, b] = self.0.map(|mask| {
+            if mask == 0 {
+                return 0;
+            }
+            let shift = mask.trailing_zeros();
+            let max = mask >> shift;
+            let channel = (value & mask) >> shift;
+            if max >= 0xFF {
+                (channel >> (32 - max.leading_zeros() - 8)) as u8
+            } else {
+                ((channel * 255 + max / 2) / max) as u8
+            }
+        });
+        Rgb888::new(r, g, b)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Indexed { bits: u8 },
+    Bgr24,
+    Masked { bytes: u8, masks: ChannelMasks },
+    Rle8,
+    Rle4,
+}
+
+/// State of the RLE decoder.
+#[derive(Debug, Default)]
+struct Rle {
+    /// Remaining pixels of an encoded run.
+    run: u8,
+    /// Remaining pixels of an absolute run.
+    absolute: u8,
+    /// Number of bytes in the current absolute run.
+    absolute_bytes: u8,
+    /// Value of the run or current byte of an absolute run.
+    value: u8,
+    /// `true` if the last 4 bit index was stored in the upper nibble.
+    high_nibble: bool,
+    /// Number of pixels which are skipped by a delta or end of line.
+    skip: u32,
+    /// `true` after the end of bitmap marker.
+    end: bool,
+    /// `true` if the current row was started because the previous row was full.
+    wrapped: bool,
+}
+
+/// BMP image decoder.
+///
+/// Supports uncompressed images with 1, 4, 8, 16, 24 and 32 bits per pixel,
+/// images with bit field color masks and RLE8 and RLE4 compressed images.
+/// Pixels which are skipped by the RLE delta and end of line codes are
+/// undefined and aren't drawn.
+pub struct Bmp<R: Read> {
+    reader: ByteReader<R>,
+    size: Size,
+    row_order: RowOrder,
+    format: Format,
+    palette: [Rgb888; 256],
+    palette_len: usize,
+    /// Number of bytes in a row including the padding.
+    stride: u32,
+    /// Bytes of the current row which were already read.
+    row_bytes: u32,
+    /// Current byte for images with less than 8 bits per pixel.
+    bits: u8,
+    bits_left: u8,
+    rle: Rle,
+    x: u32,
+    row: u32,
+}
+
+impl<R: Read> Bmp<R> {
+    /// Reads the BMP header from `reader`.
+    pub async fn new(reader: R) -> Result<Self, ImageError<R::Error>> {
+        let mut reader = ByteReader::new(reader);
+
+        let file_header: [u8; 14] = reader.read_array().await?;
+        if &file_header[..2] != b"BM" {
+            return Err(ImageError::InvalidHeader);
+        }
+        let data_offset = le_u32(&file_header[10..]);
+
+        let header: [u8; 40] = reader.read_array().await?;
+        let header_size = le_u32(&header);
+        if header_size < 40 {
+            return Err(ImageError::Unsupported);
+        }
+        let width = le_u32(&header[4..]) as i32;
+        let height = le_u32(&header[8..]) as i32;
+        let planes = le_u16(&header[12..]);
+        let bits = le_u16(&header[14..]);
+        let compression = le_u32(&header[16..]);
+        let colors_used = le_u32(&header[32..]);
+        if width < 0 || height == i32::MIN || planes != 1 {
+            return Err(ImageError::InvalidHeader);
+        }
+
+        let mut consumed = 14 + 40;
+        let mut header_left = header_size - 40;
+
+        let format = match (compression, bits) {
+            (0, 1 | 2 | 4 | 8) => Format::Indexed { bits: bits as u8 },
+            (0, 16) => Format::Masked {
+                bytes: 2,
+                masks: ChannelMasks::RGB555,
+            },
+            (0, 24) => Format::Bgr24,
+            (0, 32) => Format::Masked {
+                bytes: 4,
+                masks: ChannelMasks::RGB888,
+            },
+            (1, 8) => Format::Rle8,
+            (2, 4) => Format::Rle4,
+            (3 | 6, 16 | 32) => {
+                // The masks are part of larger headers, but follow a
+                // BITMAPINFOHEADER.
+                let masks: [u8; 12] = reader.read_array().await?;
+                consumed += 12;
+                header_left = header_left.saturating_sub(12);
+                Format::Masked {
+                    bytes: bits as u8 / 8,
+                    masks: ChannelMasks([le_u32(&masks), le_u32(&masks[4..]), le_u32(&masks[8..])]),
+                }
+            }
+            (0..=3 | 6, _) => return Err(ImageError::InvalidHeader),
+            _ => return Err(ImageError::Unsupported),
+        };
+
+        let row_order = if height < 0 {
+            if matches!(format, Format::Rle8 | Format::Rle4) {
+                return Err(ImageError::InvalidHeader);
+            }
+            RowOrder::TopDown
+        } else {
+            RowOrder::BottomUp
+        };
+
+        reader.skip(header_left as usize).await?;
+        consumed += header_left;
+
+        let mut palette = [Rgb888::new(0, 0, 0); 256];
+        let mut palette_len = 0;
+        if bits <= 8 {
+            palette_len = match colors_used {
+                0 => 1 << bits,
+                n if n <= 1 << bits => n as usize,
+                _ => return Err(ImageError::InvalidHeader),
+            };
+            for color in &mut palette[..palette_len] {
+                let [b, g, r, _] = reader.read_array().await?;
+                *color = Rgb888::new(r, g, b);
+            }
+            consumed += palette_len as u32 * 4;
+        }
+
+        let Some(gap) = data_offset.checked_sub(consumed) else {
+            return Err(ImageError::InvalidHeader);
+        };
+        reader.skip(gap as usize).await?;
+
+        let size = Size::new(width as u32, height.unsigned_abs());
+        let Some(row_bits) = size.width.checked_mul(u32::from(bits)) else {
+            return Err(ImageError::InvalidHeader);
+        };
+        Ok(Self {
+            reader,
+            size,
+            row_order,
+            format,
+            palette,
+            palette_len,
+            stride: row_bits.div_ceil(32) * 4,
+            row_bytes: 0,
+            bits: 0,
+            bits_left: 0,
+            rle: Rle::default(),
+            x: 0,
+            row: 0,
+        })
+    }
+
+    fn palette_color(&self, index: u8) -> Result<Rgb888, ImageError<R::Error>> {
+        self.palette[..self.palette_len]
+            .get(usize::from(index))
+            .copied()
+            .ok_or(ImageError::InvalidData)
+    }
+
+    /// Decodes a pixel of an uncompressed image.
+    async fn next_uncompressed(&mut self) -> Result<Rgb888, ImageError<R::Error>> {
+        match self.format {
+            Format::Indexed { bits } => {
+                if self.bits_left == 0 {
+                    self.bits = self.reader.read_u8().await?;
+                    self.row_bytes += 1;
+                    self.bits_left = 8;
+                }
+                self.bits_left -= bits;
+                let index = (self.bits >> self.bits_left) & (0xFF >> (8 - bits));
+                self.palette_color(index)
+            }
+            Format::Bgr24 => {
+                let [b, g, r] = self.reader.read_array().await?;
+                self.row_bytes += 3;
+                Ok(Rgb888::new(r, g, b))
+            }
+            Format::Masked { bytes, masks } => {
+                let value = if bytes == 2 {
+                    u32::from(le_u16(&self.reader.read_array::<2>().await?))
+                } else {
+                    le_u32(&self.reader.read_array::<4>().await?)
+                };
+                self.row_bytes += u32::from(bytes);
+                Ok(masks.color(value))
+            }
+            Format::Rle8 | Format::Rle4 => unreachable!(),
+        }
+    }
+
+    /// Decodes a pixel of an RLE compressed image.
+    ///
+    /// Returns the pixel and `true` if the pixel was part of the image data.
+    async fn next_rle(&mut self) -> Result<(Option<Rgb888>, bool), ImageError<R::Error>> {
+        let rle8 = matches!(self.format, Format::Rle8);
+        loop {
+            if self.rle.skip > 0 {
+                self.rle.skip -= 1;
+                return Ok((None, false));
+            }
+            if self.rle.end {
+                return Ok((None, false));
+            }
+
+            if self.rle.run > 0 {
+                self.rle.run -= 1;
+                let index = if rle8 {
+                    self.rle.value
+                } else {
+                    self.rle.high_nibble = !self.rle.high_nibble;
+                    if self.rle.high_nibble {
+                        self.rle.value >> 4
+                    } else {
+                        self.rle.value & 0x0F
+                    }
+                };
+                return Ok((Some(self.palette_color(index)?), true));
+            }
+
+            if self.rle.absolute > 0 {
+                self.rle.absolute -= 1;
+                let index = if rle8 {
+                    self.reader.read_u8().await?
+                } else {
+                    self.rle.high_nibble = !self.rle.high_nibble;
+                    if self.rle.high_nibble {
+                        self.rle.value = self.reader.read_u8().await?;
+                        self.rle.value >> 4
+                    } else {
+                        self.rle.value & 0x0F
+                    }
+                };
+                // Absolute runs are padded to 16 bits.
+                if self.rle.absolute == 0 && self.rle.absolute_bytes % 2 == 1 {
+                    self.reader.read_u8().await?;
+                }
+                return Ok((Some(self.palette_color(index)?), true));
+            }
+
+            let [count, value] = self.reader.read_array().await?;
+            match (count, value) {
+                (0, 0) => {
+                    // A row which was filled completely doesn't need an end of line.
+                    if !(self.x == 0 && self.rle.wrapped) {
+                        self.rle.skip = self.size.width - self.x;
+                    }
+                    self.rle.wrapped = false;
+                }
+                (0, 1) => self.rle.end = true,
+                (0, 2) => {
+                    let [dx, dy] = self.reader.read_array().await?;
+                    self.rle.skip = u32::from(dy)
+                        .checked_mul(self.size.width)
+                        .and_then(|skip| skip.checked_add(u32::from(dx)))
+                        .ok_or(ImageError::InvalidData)?;
+                }
+                (0, count) => {
+                    self.rle.absolute = count;
+                    self.rle.absolute_bytes = if rle8 { count } else { count.div_ceil(2) };
+                    self.rle.high_nibble = false;
+                }
+                (count, value) => {
+                    self.rle.run = count;
+                    self.rle.value = value;
+                    self.rle.high_nibble = false;
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> ImageDecoder for Bmp<R> {
+    type Error = R::Error;
+
+    fn size(&self) -> Size {
+        self.size
+    }
+
+    fn row_order(&self) -> RowOrder {
+        self.row_order
+    }
+
+    async fn next_pixel(&mut self) -> Result<Option<Rgb888>, ImageError<Self::Error>> {
+        if self.row >= self.size.height {
+            return Ok(None);
+        }
+
+        let (pixel, data) = match self.format {
+            Format::Rle8 | Format::Rle4 => self.next_rle().await?,
+            _ => (Some(self.next_uncompressed().await?), true),
+        };
+
+        self.x += 1;
+        if self.x == self.size.width {
+            self.x = 0;
+            self.row += 1;
+            self.rle.wrapped = data;
+            self.bits_left = 0;
+            if self.row < self.size.height && !matches!(self.format, Format::Rle8 | Format::Rle4) {
+                let padding = self.stride.saturating_sub(self.row_bytes);
+                self.reader.skip(padding as usize).await?;
+            }
+            self.row_bytes = 0;
+        }
+
+        Ok(pixel)
+    }
+}
+
+fn le_u16(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
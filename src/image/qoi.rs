@@ -0,0 +1,97 @@
+//! QOI decoder.
+
+use embedded_graphics::{pixelcolor::Rgb888, prelude::Size};
+use embedded_io_async::Read;
+
+use super::{ByteReader, ImageDecoder, ImageError, RowOrder};
+
+const OP_RGB: u8 = 0xFE;
+const OP_RGBA: u8 = 0xFF;
+
+/// QOI image decoder.
+///
+/// The alpha channel isn't blended, fully transparent pixels aren't drawn
+/// and all other pixels are drawn opaque.
+pub struct Qoi<R: Read> {
+    reader: ByteReader<R>,
+    size: Size,
+    index: [[u8; 4]; 64],
+    pixel: [u8; 4],
+    run: u8,
+    remaining: u64,
+}
+
+impl<R: Read> Qoi<R> {
+    /// Reads the QOI header from `reader`.
+    pub async fn new(reader: R) -> Result<Self, ImageError<R::Error>> {
+        let mut reader = ByteReader::new(reader);
+
+        let header: [u8; 14] = reader.read_array().await?;
+        let width = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        let height = u32::from_be_bytes([header[8], header[9], header[10], header[11]]);
+        let (channels, colorspace) = (header[12], header[13]);
+        if &header[..4] != b"qoif" || !matches!(channels, 3 | 4) || colorspace > 1 {
+            return Err(ImageError::InvalidHeader);
+        }
+
+        Ok(Self {
+            reader,
+            size: Size::new(width, height),
+            index: [[0; 4]; 64],
+            pixel: [0, 0, 0, 255],
+            run: 0,
+            remaining: u64::from(width) * u64::from(height),
+        })
+    }
+}
+
+impl<R: Read> ImageDecoder for Qoi<R> {
+    type Error = R::Error;
+
+    fn size(&self) -> Size {
+        self.size
+    }
+
+    fn row_order(&self) -> RowOrder {
+        RowOrder::TopDown
+    }
+
+    async fn next_pixel(&mut self) -> Result<Option<Rgb888>, ImageError<Self::Error>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+
+        if self.run > 0 {
+            self.run -= 1;
+        } else {
+            let [r, g, b, _] = &mut self.pixel;
+            match self.reader.read_u8().await? {
+                OP_RGB => [*r, *g, *b] = self.reader.read_array().await?,
+                OP_RGBA => self.pixel = self.reader.read_array().await?,
+                op => match op >> 6 {
+                    0 => self.pixel = self.index[usize::from(op)],
+                    1 => {
+                        *r = r.wrapping_add((op >> 4) & 0x03).wrapping_sub(2);
+                        *g = g.wrapping_add((op >> 2) & 0x03).wrapping_sub(2);
+                        *b = b.wrapping_add(op & 0x03).wrapping_sub(2);
+                    }
+                    2 => {
+                        let byte = self.reader.read_u8().await?;
+                        let dg = (op & 0x3F).wrapping_sub(32);
+                        *r = r.wrapping_add(dg).wrapping_sub(8).wrapping_add(byte >> 4);
+                        *g = g.wrapping_add(dg);
+                        *b = b.wrapping_add(dg).wrapping_sub(8).wrapping_add(byte & 0x0F);
+                    }
+                    _ => self.run = op & 0x3F,
+                },
+            }
+
+            let [r, g, b, a] = self.pixel.map(usize::from);
+            self.index[(r * 3 + g * 5 + b * 7 + a * 11) % 64] = self.pixel;
+        }
+
+        let [r, g, b, a] = self.pixel;
+        Ok((a != 0).then(|| Rgb888::new(r, g, b)))
+    }
+}
@@ -3,6 +3,9 @@ pub use spi::*;
 
 mod parallel;
 pub use parallel::*;
+
+mod pixel_format;
+pub use pixel_format::*;
 // Command and pixel interface
 pub trait Interface {
     /// The native width of the interface (e.g., u8 for SPI, u8/u16 for parallel).
@@ -25,6 +28,32 @@ pub trait Interface {
     /// For your goal of passing &[u8] directly, we'll aim for Self::Word = u8
     /// or handle the u8 slice appropriately in implementations.
     async fn send_data_slice(&mut self, data: &[Self::Word]) -> Result<(), Self::Error>;
+
+    /// Send data which is produced in chunks by `fill`, alternating between two buffers.
+    ///
+    /// `fill` is called with an empty buffer and returns the number of words it wrote
+    /// into the buffer, the transfer ends once `fill` returns `0`.
+    /// Interfaces which support asynchronous transfers can send one buffer while the
+    /// next chunk is filled into the other buffer. The default implementation sends
+    /// every chunk before filling the next one.
+    /// `WriteMemoryStart` (or equivalent) must be sent before calling this function.
+    async fn send_data_pipelined<F>(
+        &mut self,
+        buffers: [&mut [Self::Word]; 2],
+        mut fill: F,
+    ) -> Result<(), Self::Error>
+    where
+        F: FnMut(&mut [Self::Word]) -> usize,
+    {
+        let [buffer, _] = buffers;
+        loop {
+            let len = fill(buffer);
+            if len == 0 {
+                return Ok(());
+            }
+            self.send_data_slice(&buffer[..len]).await?;
+        }
+    }
 }
 
 // Update the blanket impl for &mut T
@@ -40,6 +69,17 @@ impl<T: Interface + ?Sized> Interface for &mut T {
     async fn send_data_slice(&mut self, data: &[Self::Word]) -> Result<(), Self::Error> {
         T::send_data_slice(self, data).await
     }
+
+    async fn send_data_pipelined<F>(
+        &mut self,
+        buffers: [&mut [Self::Word]; 2],
+        fill: F,
+    ) -> Result<(), Self::Error>
+    where
+        F: FnMut(&mut [Self::Word]) -> usize,
+    {
+        T::send_data_pipelined(self, buffers, fill).await
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -0,0 +1,139 @@
+use embedded_graphics::pixelcolor::{
+    raw::{RawData, RawU16},
+    Bgr565, Bgr888, PixelColor, Rgb565, Rgb666, Rgb888,
+};
+
+use crate::raw_framebuf::IntoRawBytes;
+
+/// Pixel color which can be sent to a display using `Word` sized data words.
+///
+/// Interfaces with 8 bit words use the same byte order as
+/// [`IntoRawBytes`]. On 16 bit parallel interfaces every 16 bit color is
+/// sent as a single word.
+pub trait InterfacePixelFormat<Word: Copy>: PixelColor {
+    /// Number of words used to encode one pixel.
+    const WORDS_PER_PIXEL: usize;
+
+    /// Writes the encoded pixel to the start of `buffer`.
+    ///
+    /// `buffer` must hold at least [`WORDS_PER_PIXEL`](Self::WORDS_PER_PIXEL) words.
+    fn write_words(self, buffer: &mut [Word]);
+}
+
+/// Number of words in the stack buffers used to encode pixels.
+///
+/// The size is divisible by every supported number of words per pixel.
+pub(crate) const PIXEL_CHUNK_WORDS: usize = 192;
+
+/// Encodes pixels from `pixels` into `buffer` and returns the number of words written.
+///
+/// Only whole pixels are written. Returns `0` once the iterator is exhausted.
+pub(crate) fn encode_words<C, DW>(pixels: &mut impl Iterator<Item = C>, buffer: &mut [DW]) -> usize
+where
+    C: InterfacePixelFormat<DW>,
+    DW: Copy,
+{
+    let words = C::WORDS_PER_PIXEL;
+    let mut len = 0;
+    while len + words <= buffer.len() {
+        let Some(color) = pixels.next() else {
+            break;
+        };
+        color.write_words(&mut buffer[len..]);
+        len += words;
+    }
+    len
+}
+
+/// Interface word which can be created from raw framebuffer bytes.
+///
+/// Raw bytes are combined into words in big endian byte order, which matches
+/// the 16 bit colors of [`InterfacePixelFormat`] for 16 bit interfaces.
+pub trait RawDataWord: Copy + Default {
+    /// Number of bytes per word.
+    const BYTES: usize;
+
+    /// Creates a word from [`BYTES`](Self::BYTES) raw bytes.
+    fn from_be_bytes(bytes: &[u8]) -> Self;
+}
+
+impl RawDataWord for u8 {
+    const BYTES: usize = 1;
+
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        bytes[0]
+    }
+}
+
+impl RawDataWord for u16 {
+    const BYTES: usize = 2;
+
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        u16::from_be_bytes([bytes[0], bytes[1]])
+    }
+}
+
+macro_rules! impl_u8_pixel_format {
+    ($($color:ty => $n:literal),*) => {
+        $(
+            impl InterfacePixelFormat<u8> for $color {
+                const WORDS_PER_PIXEL: usize = $n;
+
+                fn write_words(self, buffer: &mut [u8]) {
+                    buffer[..$n].copy_from_slice(&IntoRawBytes::<$n>::into_raw_bytes(self));
+                }
+            }
+        )*
+    };
+}
+
+impl_u8_pixel_format!(Rgb565 => 2, Bgr565 => 2, Rgb666 => 3, Rgb888 => 3, Bgr888 => 3);
+
+impl InterfacePixelFormat<u16> for Rgb565 {
+    const WORDS_PER_PIXEL: usize = 1;
+
+    fn write_words(self, buffer: &mut [u16]) {
+        buffer[0] = RawU16::from(self).into_inner();
+    }
+}
+
+impl InterfacePixelFormat<u16> for Bgr565 {
+    const WORDS_PER_PIXEL: usize = 1;
+
+    fn write_words(self, buffer: &mut [u16]) {
+        buffer[0] = RawU16::from(self).into_inner();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::prelude::RgbColor;
+
+    use super::*;
+
+    #[test]
+    fn encodes_words() {
+        let mut bytes = [0u8; 3];
+        Rgb565::RED.write_words(&mut bytes);
+        assert_eq!(bytes, [0xF8, 0x00, 0x00]);
+        Rgb666::GREEN.write_words(&mut bytes);
+        assert_eq!(bytes, [0x00, 0xFC, 0x00]);
+
+        let mut words = [0u16; 1];
+        Rgb565::BLUE.write_words(&mut words);
+        assert_eq!(words, [0x001F]);
+        Bgr565::BLUE.write_words(&mut words);
+        assert_eq!(words, [0xF800]);
+    }
+
+    #[test]
+    fn encodes_whole_pixels() {
+        let mut pixels = [Rgb565::RED, Rgb565::GREEN, Rgb565::BLUE].into_iter();
+        let mut buffer = [0u8; 5];
+        assert_eq!(encode_words(&mut pixels, &mut buffer), 4);
+        assert_eq!(&buffer[..4], &[0xF8, 0x00, 0x07, 0xE0]);
+        assert_eq!(encode_words(&mut pixels, &mut buffer), 2);
+        assert_eq!(&buffer[..2], &[0x00, 0x1F]);
+        assert_eq!(encode_words(&mut pixels, &mut buffer), 0);
+    }
+}
@@ -1,5 +1,6 @@
+use embassy_futures::join::join;
 use embedded_hal::digital::OutputPin;
-use embedded_hal_async::spi::SpiDevice;
+use embedded_hal_async::spi::{SpiBus, SpiDevice};
 
 use super::{Interface, InterfaceKind};
 
@@ -32,6 +33,10 @@ where
     }
 }
 
+/// Pipelined transfers send every chunk in a separate [`SpiDevice`]
+/// transaction, which releases CS between the chunks. Another device on a
+/// shared bus can use the bus in between, which doesn't affect the display.
+/// Use a [`SpiBusInterface`] if CS must stay asserted for the whole transfer.
 impl<SPI, DC> Interface for SpiInterface<SPI, DC>
 where
     SPI: SpiDevice, // Assuming async
@@ -57,4 +62,324 @@ where
         self.spi.write(data).await.map_err(SpiError::Spi)?;
         Ok(())
     }
+
+    async fn send_data_pipelined<F>(
+        &mut self,
+        buffers: [&mut [Self::Word]; 2],
+        mut fill: F,
+    ) -> Result<(), Self::Error>
+    where
+        F: FnMut(&mut [Self::Word]) -> usize,
+    {
+        // `SpiDevice::transaction` needs all operations up front, which means that
+        // every chunk is sent as a separate transaction. DC stays high between the
+        // chunks, so the display continues the memory write. Use `SpiBusInterface`
+        // to keep CS asserted for the whole transfer.
+        let [mut front, mut back] = buffers;
+        let mut len = fill(front);
+
+        while len > 0 {
+            // The write is polled first and starts the transfer before the next
+            // chunk is filled.
+            let (result, next_len) =
+                join(self.spi.write(&front[..len]), async { fill(back) }).await;
+            result.map_err(SpiError::Spi)?;
+
+            core::mem::swap(&mut front, &mut back);
+            len = next_len;
+        }
+
+        Ok(())
+    }
+}
+
+/// SPI bus interface error
+#[derive(Clone, Copy, Debug)]
+pub enum SpiBusError<BUS, DC, CS> {
+    /// SPI bus error.
+    Spi(BUS),
+    /// Data/command pin error.
+    Dc(DC),
+    /// Chip select pin error.
+    Cs(CS),
+}
+
+/// SPI interface which owns the bus and drives the chip select pin.
+///
+/// Unlike [`SpiInterface`], this interface keeps CS asserted for the whole
+/// pipelined transfer, while the next chunk is filled during the write of the
+/// previous one. The bus must not be shared with other devices.
+pub struct SpiBusInterface<BUS, DC, CS> {
+    bus: BUS,
+    dc: DC,
+    cs: CS,
+}
+
+impl<BUS, DC, CS> SpiBusInterface<BUS, DC, CS>
+where
+    BUS: SpiBus,
+    DC: OutputPin,
+    CS: OutputPin,
+{
+    /// Create new interface
+    pub fn new(bus: BUS, dc: DC, cs: CS) -> Self {
+        Self { bus, dc, cs }
+    }
+
+    /// Release the bus and the pins, deconstructing the interface
+    pub fn release(self) -> (BUS, DC, CS) {
+        (self.bus, self.dc, self.cs)
+    }
+
+    async fn write_command(
+        &mut self,
+        command: u8,
+        args: &[u8],
+    ) -> Result<(), <Self as Interface>::Error> {
+        self.dc.set_low().map_err(SpiBusError::Dc)?;
+        self.bus.write(&[command]).await.map_err(SpiBusError::Spi)?;
+        // DC must not change before the command byte was shifted out.
+        self.bus.flush().await.map_err(SpiBusError::Spi)?;
+        self.dc.set_high().map_err(SpiBusError::Dc)?;
+        self.bus.write(args).await.map_err(SpiBusError::Spi)?;
+        self.bus.flush().await.map_err(SpiBusError::Spi)
+    }
+
+    async fn write_pipelined<F>(
+        &mut self,
+        buffers: [&mut [u8]; 2],
+        mut fill: F,
+    ) -> Result<(), <Self as Interface>::Error>
+    where
+        F: FnMut(&mut [u8]) -> usize,
+    {
+        let [mut front, mut back] = buffers;
+        let mut len = fill(front);
+
+        while len > 0 {
+            let (result, next_len) =
+                join(self.bus.write(&front[..len]), async { fill(back) }).await;
+            result.map_err(SpiBusError::Spi)?;
+
+            core::mem::swap(&mut front, &mut back);
+            len = next_len;
+        }
+
+        self.bus.flush().await.map_err(SpiBusError::Spi)
+    }
+
+    /// Releases CS and returns the first error.
+    fn end_transfer(
+        &mut self,
+        result: Result<(), <Self as Interface>::Error>,
+    ) -> Result<(), <Self as Interface>::Error> {
+        let released = self.cs.set_high().map_err(SpiBusError::Cs);
+        result.and(released)
+    }
+}
+
+impl<BUS, DC, CS> Interface for SpiBusInterface<BUS, DC, CS>
+where
+    BUS: SpiBus,
+    DC: OutputPin,
+    CS: OutputPin,
+{
+    type Word = u8;
+    type Error = SpiBusError<BUS::Error, DC::Error, CS::Error>;
+
+    const KIND: InterfaceKind = InterfaceKind::Serial4Line;
+
+    async fn send_command(&mut self, command: u8, args: &[u8]) -> Result<(), Self::Error> {
+        self.cs.set_low().map_err(SpiBusError::Cs)?;
+        let result = self.write_command(command, args).await;
+        self.end_transfer(result)
+    }
+
+    async fn send_data_slice(&mut self, data: &[Self::Word]) -> Result<(), Self::Error> {
+        self.cs.set_low().map_err(SpiBusError::Cs)?;
+        let result = match self.bus.write(data).await {
+            Ok(()) => self.bus.flush().await,
+            Err(e) => Err(e),
+        };
+        self.end_transfer(result.map_err(SpiBusError::Spi))
+    }
+
+    async fn send_data_pipelined<F>(
+        &mut self,
+        buffers: [&mut [Self::Word]; 2],
+        fill: F,
+    ) -> Result<(), Self::Error>
+    where
+        F: FnMut(&mut [Self::Word]) -> usize,
+    {
+        self.cs.set_low().map_err(SpiBusError::Cs)?;
+        let result = self.write_pipelined(buffers, fill).await;
+        self.end_transfer(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use core::{cell::RefCell, convert::Infallible};
+    use std::{rc::Rc, vec, vec::Vec};
+
+    use embassy_futures::block_on;
+    use embedded_hal::{digital, spi};
+    use embedded_hal_async::spi::Operation;
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum Event {
+        Dc(bool),
+        Cs(bool),
+        Transaction(Vec<Vec<u8>>),
+        Write(Vec<u8>),
+        Flush,
+    }
+
+    type Log = Rc<RefCell<Vec<Event>>>;
+
+    struct MockSpi(Log);
+
+    impl spi::ErrorType for MockSpi {
+        type Error = Infallible;
+    }
+
+    impl SpiDevice for MockSpi {
+        async fn transaction(
+            &mut self,
+            operations: &mut [Operation<'_, u8>],
+        ) -> Result<(), Infallible> {
+            let writes = operations
+                .iter()
+                .map(|operation| match operation {
+                    Operation::Write(data) => data.to_vec(),
+                    _ => panic!("unexpected operation"),
+                })
+                .collect();
+            self.0.borrow_mut().push(Event::Transaction(writes));
+            Ok(())
+        }
+    }
+
+    impl SpiBus for MockSpi {
+        async fn read(&mut self, _words: &mut [u8]) -> Result<(), Infallible> {
+            unimplemented!()
+        }
+
+        async fn write(&mut self, words: &[u8]) -> Result<(), Infallible> {
+            self.0.borrow_mut().push(Event::Write(words.to_vec()));
+            Ok(())
+        }
+
+        async fn transfer(&mut self, _read: &mut [u8], _write: &[u8]) -> Result<(), Infallible> {
+            unimplemented!()
+        }
+
+        async fn transfer_in_place(&mut self, _words: &mut [u8]) -> Result<(), Infallible> {
+            unimplemented!()
+        }
+
+        async fn flush(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push(Event::Flush);
+            Ok(())
+        }
+    }
+
+    struct MockPin(Log, fn(bool) -> Event);
+
+    impl digital::ErrorType for MockPin {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for MockPin {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push((self.1)(false));
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push((self.1)(true));
+            Ok(())
+        }
+    }
+
+    /// Sends `[1, 2, 3]`, `[4, 5]` and `[6]` through 3 byte buffers.
+    async fn send_chunks(di: &mut impl Interface<Word = u8>) {
+        let mut chunks = [&[1, 2, 3][..], &[4, 5], &[6]].into_iter();
+        di.send_data_pipelined([&mut [0; 3], &mut [0; 3]], |buffer| {
+            let chunk = chunks.next().unwrap_or_default();
+            buffer[..chunk.len()].copy_from_slice(chunk);
+            chunk.len()
+        })
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn device_sends_chunks_in_order() {
+        let log = Log::default();
+        let mut di = SpiInterface::new(MockSpi(log.clone()), MockPin(log.clone(), Event::Dc));
+
+        block_on(async {
+            di.send_command(0x2C, &[]).await.unwrap();
+            send_chunks(&mut di).await;
+        });
+
+        assert_eq!(
+            *log.borrow(),
+            [
+                Event::Dc(false),
+                Event::Transaction(vec![vec![0x2C]]),
+                Event::Dc(true),
+                Event::Transaction(vec![vec![]]),
+                Event::Transaction(vec![vec![1, 2, 3]]),
+                Event::Transaction(vec![vec![4, 5]]),
+                Event::Transaction(vec![vec![6]]),
+            ]
+        );
+    }
+
+    #[test]
+    fn bus_keeps_cs_asserted() {
+        let log = Log::default();
+        let mut di = SpiBusInterface::new(
+            MockSpi(log.clone()),
+            MockPin(log.clone(), Event::Dc),
+            MockPin(log.clone(), Event::Cs),
+        );
+
+        block_on(async {
+            di.send_command(0x2C, &[0x01]).await.unwrap();
+            send_chunks(&mut di).await;
+            di.send_data_slice(&[7, 8]).await.unwrap();
+        });
+
+        assert_eq!(
+            *log.borrow(),
+            [
+                Event::Cs(false),
+                Event::Dc(false),
+                Event::Write(vec![0x2C]),
+                Event::Flush,
+                Event::Dc(true),
+                Event::Write(vec![0x01]),
+                Event::Flush,
+                Event::Cs(true),
+                Event::Cs(false),
+                Event::Write(vec![1, 2, 3]),
+                Event::Write(vec![4, 5]),
+                Event::Write(vec![6]),
+                Event::Flush,
+                Event::Cs(true),
+                Event::Cs(false),
+                Event::Write(vec![7, 8]),
+                Event::Flush,
+                Event::Cs(true),
+            ]
+        );
+    }
 }
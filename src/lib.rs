@@ -5,9 +5,16 @@
 //! that implement the MIPI Display Command Set.
 // ... (rest of your crate-level docs) ...
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 use crate::dcs::SetAddressMode; // Assuming dcs module is at crate root
 pub mod interface;
 
+use embedded_graphics::{
+    prelude::{Point, Size},
+    primitives::{PointsIter, Rectangle},
+};
 use embedded_hal::digital::OutputPin as BlockingOutputPin;
 use embedded_hal_async::delay::DelayNs as AsyncDelayNs;
 
@@ -17,10 +24,21 @@ use crate::options::MemoryMapping; // Assuming options module is at crate root
 mod builder;
 pub use builder::*; // Uses the corrected builder
 
+pub mod console;
 pub mod dcs;
+pub mod dither;
+mod fill;
+pub mod image;
 pub mod models;
 pub mod raw_framebuf;
-use models::Model; // Uses the corrected Model trait
+use models::Model;
+use raw_framebuf::{IntoRawBytes, RawBufferBackendMut, RawFrameBuf};
+
+mod scroll;
+pub use scroll::*;
+
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 // pub mod _troubleshooting; // Optional
 
@@ -43,6 +61,15 @@ where
     madctl: SetAddressMode,
     /// Sleep state.
     sleeping: bool,
+    /// Last address window sent to the display, `None` if unknown.
+    address_window: Option<AddressWindow>,
+}
+
+/// Physical address window in framebuffer coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct AddressWindow {
+    columns: (u16, u16),
+    pages: (u16, u16),
 }
 
 impl<DI, M, RST> Display<DI, M, RST>
@@ -62,6 +89,7 @@ where
         orientation: options::Orientation,
     ) -> Result<(), DI::Error> {
         self.options.orientation = orientation;
+        self.address_window = None;
         // `self.model` is an instance of M.
         // `update_options` is an async method on the Model trait that takes `&self` (model instance).
         let new_madctl = self
@@ -73,6 +101,9 @@ where
     }
 
     /// Sends a raw pixel data slice to the specified rectangular region of the display.
+    ///
+    /// `pixel_data` doesn't need to cover the whole region. The remaining
+    /// pixels can be sent later using [`continue_raw_data`](Self::continue_raw_data).
     pub async fn show_raw_data<DW>(
         &mut self,
         x: usize,
@@ -96,6 +127,190 @@ where
         self.di.send_data_slice(pixel_data).await
     }
 
+    /// Appends raw pixel data to the last memory write.
+    ///
+    /// The data is written at the position where the previous call to
+    /// [`show_raw_data`](Self::show_raw_data) or `continue_raw_data` stopped,
+    /// which allows a region to be filled from several buffers. Other commands
+    /// may be sent in between, but the address window must not be changed.
+    pub async fn continue_raw_data<DW>(&mut self, pixel_data: &[DW]) -> Result<(), DI::Error>
+    where
+        DI: interface::Interface<Word = DW>,
+        DW: Copy,
+    {
+        M::write_memory_continue(&mut self.di).await?;
+        self.di.send_data_slice(pixel_data).await
+    }
+
+    /// Sends pixels from an iterator to a rectangular area of the display.
+    ///
+    /// The pixels are encoded in the wire format of the interface into a small
+    /// buffer on the stack, which is sent every time it is full. At most
+    /// `width * height` pixels of the area are taken from `pixels`, in row major order.
+    ///
+    /// The area is clipped to the display. Pixels outside of the display are
+    /// taken from `pixels`, but aren't sent.
+    pub async fn set_pixels<C, I>(&mut self, area: &Rectangle, pixels: I) -> Result<(), DI::Error>
+    where
+        C: interface::InterfacePixelFormat<DI::Word>,
+        DI::Word: Default,
+        I: IntoIterator<Item = C>,
+    {
+        let (width, height) = self.options.display_size();
+        let bounds = Rectangle::new(
+            Point::zero(),
+            Size::new(u32::from(width), u32::from(height)),
+        );
+        let visible = area.intersection(&bounds);
+        let Some(bottom_right) = visible.bottom_right() else {
+            return Ok(());
+        };
+
+        self.set_address_window(
+            visible.top_left.x as u16,
+            visible.top_left.y as u16,
+            bottom_right.x as u16,
+            bottom_right.y as u16,
+        )
+        .await?;
+        M::write_memory_start(&mut self.di).await?;
+
+        let mut pixels = area
+            .points()
+            .zip(pixels)
+            .filter(|(point, _)| visible.contains(*point))
+            .map(|(_, pixel)| pixel);
+        let mut buffer = [DI::Word::default(); interface::PIXEL_CHUNK_WORDS];
+        loop {
+            let len = interface::encode_words(&mut pixels, &mut buffer);
+            if len == 0 {
+                return Ok(());
+            }
+            self.di.send_data_slice(&buffer[..len]).await?;
+        }
+    }
+
+    /// Encodes pixels and sends them to the specified rectangular region of the display.
+    ///
+    /// The pixels are encoded in chunks, alternating between the two `buffers`.
+    /// Interfaces with asynchronous transfers, like [`SpiInterface`](interface::SpiInterface)
+    /// and [`SpiBusInterface`](interface::SpiBusInterface), send one buffer while
+    /// the next chunk is encoded into the other one. At most `width * height`
+    /// pixels are taken from `pixels`, nothing is sent for an empty area.
+    ///
+    /// This method only supports interfaces with `u8` words, like SPI and 8 bit
+    /// parallel interfaces. Use [`set_pixels`](Self::set_pixels) for 16 bit
+    /// parallel interfaces.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a buffer can't hold at least one pixel.
+    pub async fn show_pixels_pipelined<C, const N: usize, I>(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        pixels: I,
+        buffers: [&mut [u8]; 2],
+    ) -> Result<(), DI::Error>
+    where
+        DI: interface::Interface<Word = u8>,
+        C: IntoRawBytes<N>,
+        I: IntoIterator<Item = C>,
+    {
+        assert!(
+            buffers.iter().all(|buffer| buffer.len() >= N),
+            "Pipeline buffers must hold at least one pixel."
+        );
+
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        let sx = x as u16;
+        let sy = y as u16;
+        let ex = sx + (width as u16) - 1;
+        let ey = sy + (height as u16) - 1;
+
+        let mut pixels = pixels.into_iter().take(width * height);
+
+        self.set_address_window(sx, sy, ex, ey).await?;
+        M::write_memory_start(&mut self.di).await?;
+        self.di
+            .send_data_pipelined(buffers, |buffer| {
+                raw_framebuf::encode_pixels(&mut pixels, buffer)
+            })
+            .await
+    }
+
+    /// Sends the areas of a framebuffer which were changed since the last flush.
+    ///
+    /// The framebuffer is assumed to be located at the top left corner of the
+    /// display. Every dirty rectangle is sent using a single address window.
+    /// The dirty state of the framebuffer is cleared afterwards.
+    pub async fn flush_dirty<C, BUF, const N: usize>(
+        &mut self,
+        framebuffer: &mut RawFrameBuf<C, BUF, N>,
+    ) -> Result<(), DI::Error>
+    where
+        DI: interface::Interface<Word = u8>,
+        C: IntoRawBytes<N>,
+        BUF: RawBufferBackendMut,
+    {
+        let dirty = *framebuffer.dirty_region();
+
+        for area in dirty.as_slice() {
+            if area.size.width as usize == framebuffer.width() {
+                // Complete rows are contiguous in the framebuffer.
+                let start = area.top_left.y as usize * framebuffer.width() * N;
+                let len = area.size.height as usize * framebuffer.width() * N;
+                self.show_raw_data(
+                    0,
+                    area.top_left.y as usize,
+                    framebuffer.width(),
+                    area.size.height as usize,
+                    &framebuffer.as_bytes()[start..start + len],
+                )
+                .await?;
+            } else {
+                self.show_raw_rows(area, framebuffer.area_rows(area))
+                    .await?;
+            }
+        }
+
+        framebuffer.clear_dirty();
+        Ok(())
+    }
+
+    /// (Internal) Sends rows of pixel data to the given area using a single address window.
+    async fn show_raw_rows<'a, DW>(
+        &mut self,
+        area: &Rectangle,
+        rows: impl IntoIterator<Item = &'a [DW]>,
+    ) -> Result<(), DI::Error>
+    where
+        DI: interface::Interface<Word = DW>,
+        DW: Copy + 'a,
+    {
+        let Some(bottom_right) = area.bottom_right() else {
+            return Ok(());
+        };
+
+        self.set_address_window(
+            area.top_left.x as u16,
+            area.top_left.y as u16,
+            bottom_right.x as u16,
+            bottom_right.y as u16,
+        )
+        .await?;
+        M::write_memory_start(&mut self.di).await?;
+        for row in rows {
+            self.di.send_data_slice(row).await?;
+        }
+        Ok(())
+    }
+
     /// Sets the vertical scroll region of the display.
     pub async fn set_vertical_scroll_region(
         &mut self,
@@ -145,16 +360,50 @@ where
             ey.saturating_add(offset.1),
         );
 
-        // M::update_address_window is an associated function on the Model trait
-        M::update_address_window(
-            &mut self.di,
-            self.options.orientation.rotation,
-            final_sx,
-            final_sy,
-            final_ex,
-            final_ey,
-        )
-        .await
+        let rotation = self.options.orientation.rotation;
+        if !M::CACHE_ADDRESS_WINDOW {
+            return M::update_address_window(
+                &mut self.di,
+                rotation,
+                final_sx,
+                final_sy,
+                final_ex,
+                final_ey,
+            )
+            .await;
+        }
+
+        let window = AddressWindow {
+            columns: (final_sx, final_ex),
+            pages: (final_sy, final_ey),
+        };
+        let cached = self.address_window.take();
+
+        // RAMWR always restarts at the window origin, so unchanged CASET and
+        // RASET commands can be skipped.
+        match cached {
+            Some(cached) if cached == window => {}
+            Some(cached) if cached.columns == window.columns => {
+                M::update_page_address(&mut self.di, rotation, final_sy, final_ey).await?
+            }
+            Some(cached) if cached.pages == window.pages => {
+                M::update_column_address(&mut self.di, rotation, final_sx, final_ex).await?
+            }
+            _ => {
+                M::update_address_window(
+                    &mut self.di,
+                    rotation,
+                    final_sx,
+                    final_sy,
+                    final_ex,
+                    final_ey,
+                )
+                .await?
+            }
+        }
+
+        self.address_window = Some(window);
+        Ok(())
     }
 
     /// Configures the tearing effect output signal.
@@ -172,6 +421,7 @@ where
 
     /// Puts the display into sleep mode.
     pub async fn sleep<DLY: AsyncDelayNs>(&mut self, delay: &mut DLY) -> Result<(), DI::Error> {
+        self.address_window = None;
         M::sleep(&mut self.di, delay).await?;
         self.sleeping = true;
         Ok(())
@@ -179,6 +429,7 @@ where
 
     /// Wakes the display from sleep mode.
     pub async fn wake<DLY: AsyncDelayNs>(&mut self, delay: &mut DLY) -> Result<(), DI::Error> {
+        self.address_window = None;
         M::wake(&mut self.di, delay).await?;
         self.sleeping = false;
         Ok(())
@@ -187,7 +438,259 @@ where
     /// Returns a mutable reference to the underlying display interface for sending raw commands.
     /// # Safety
     /// (User responsible for not desynchronizing state)
+    ///
+    /// The cached address window is discarded, because raw commands might change it.
     pub unsafe fn raw_interface_mut(&mut self) -> &mut DI {
+        self.address_window = None;
         &mut self.di
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use embassy_futures::block_on;
+
+    use crate::{
+        models::{ST7735s, GC9107, ST7789},
+        options::{Orientation, Rotation},
+        testing::{mock_display, mock_display_with, MockDelay, MockInterface},
+    };
+
+    use super::*;
+
+    /// Returns the CASET and RASET parameters for a 10x20 window at (0, 0)
+    /// in every rotation.
+    fn address_windows<M: Model>(
+        model: impl Fn() -> M,
+        size: (usize, usize),
+        offset: (u16, u16),
+    ) -> Vec<(Rotation, [u8; 4], [u8; 4])> {
+        [
+            Rotation::Deg0,
+            Rotation::Deg90,
+            Rotation::Deg180,
+            Rotation::Deg270,
+        ]
+        .into_iter()
+        .map(|rotation| {
+            let mut display = mock_display_with(model(), MockInterface::new(), size, |builder| {
+                builder
+                    .display_offset(offset.0, offset.1)
+                    .orientation(Orientation::new().rotate(rotation))
+            });
+            display.di.clear();
+            block_on(display.set_address_window(0, 0, 9, 19)).unwrap();
+
+            let commands = display.di.commands().collect::<Vec<_>>();
+            match commands[..] {
+                [(0x2A, caset), (0x2B, raset)] => (
+                    rotation,
+                    caset.try_into().unwrap(),
+                    raset.try_into().unwrap(),
+                ),
+                _ => panic!("unexpected commands: {commands:?}"),
+            }
+        })
+        .collect()
+    }
+
+    #[test]
+    fn init_sends_software_reset_without_reset_pin() {
+        let mut di = MockInterface::new();
+        let mut delay = MockDelay::new();
+        block_on(Builder::new(ST7789, &mut di).init(&mut delay)).unwrap();
+
+        assert_eq!(di.commands().next(), Some((0x01, &[][..])));
+        assert_eq!(di.commands().count(), 7);
+    }
+
+    #[test]
+    fn init_rejects_invalid_size() {
+        let result = block_on(
+            Builder::new(ST7789, MockInterface::new())
+                .display_size(240, 240)
+                .display_offset(0, 81)
+                .init(&mut MockDelay::new()),
+        );
+        assert!(matches!(
+            result,
+            Err(InitError::InvalidConfiguration(
+                ConfigurationError::InvalidDisplayOffset
+            ))
+        ));
+    }
+
+    #[test]
+    fn st7789_address_window() {
+        assert_eq!(
+            address_windows(|| ST7789, (240, 240), (0, 0)),
+            [
+                (Rotation::Deg0, [0, 0, 0, 9], [0, 0, 0, 19]),
+                (Rotation::Deg90, [0, 0, 0, 9], [0, 0, 0, 19]),
+                (Rotation::Deg180, [0, 0, 0, 9], [0, 80, 0, 99]),
+                (Rotation::Deg270, [0, 80, 0, 89], [0, 0, 0, 19]),
+            ]
+        );
+    }
+
+    #[test]
+    fn st7735s_address_window() {
+        assert_eq!(
+            address_windows(|| ST7735s, (128, 160), (2, 1)),
+            [
+                (Rotation::Deg0, [0, 2, 0, 11], [0, 1, 0, 20]),
+                (Rotation::Deg90, [0, 1, 0, 10], [0, 2, 0, 21]),
+                (Rotation::Deg180, [0, 2, 0, 11], [0, 1, 0, 20]),
+                (Rotation::Deg270, [0, 1, 0, 10], [0, 2, 0, 21]),
+            ]
+        );
+    }
+
+    #[test]
+    fn gc9107_address_window() {
+        assert_eq!(
+            address_windows(|| GC9107, (128, 128), (0, 32)),
+            [
+                (Rotation::Deg0, [0, 0, 0, 9], [0, 32, 0, 51]),
+                (Rotation::Deg90, [0, 32, 0, 41], [0, 0, 0, 19]),
+                (Rotation::Deg180, [0, 0, 0, 9], [0, 0, 0, 19]),
+                (Rotation::Deg270, [0, 0, 0, 9], [0, 0, 0, 19]),
+            ]
+        );
+    }
+
+    #[test]
+    fn address_window_is_cached() {
+        let mut display = mock_display(ST7789, MockInterface::new(), (240, 320));
+        block_on(async {
+            display.set_address_window(0, 0, 9, 19).await.unwrap();
+            display.set_address_window(0, 0, 9, 19).await.unwrap();
+            display.set_address_window(0, 20, 9, 39).await.unwrap();
+            display.set_address_window(10, 20, 19, 39).await.unwrap();
+            display.set_orientation(Orientation::new()).await.unwrap();
+            display.set_address_window(10, 20, 19, 39).await.unwrap();
+        });
+
+        display.di.assert_commands(&[
+            (0x2A, &[0, 0, 0, 9]),
+            (0x2B, &[0, 0, 0, 19]),
+            (0x2B, &[0, 20, 0, 39]),
+            (0x2A, &[0, 10, 0, 19]),
+            (0x36, &[0x00]),
+            (0x2A, &[0, 10, 0, 19]),
+            (0x2B, &[0, 20, 0, 39]),
+        ]);
+    }
+
+    #[test]
+    fn address_window_is_not_cached_without_opt_in() {
+        struct Uncached;
+
+        impl Model for Uncached {
+            const FRAMEBUFFER_SIZE: (u16, u16) = (240, 320);
+
+            async fn init<DELAY, DI>(
+                &mut self,
+                _di: &mut DI,
+                _delay: &mut DELAY,
+                options: &options::ModelOptions,
+            ) -> Result<dcs::SetAddressMode, models::ModelInitError<DI::Error>>
+            where
+                DELAY: AsyncDelayNs,
+                DI: interface::Interface,
+            {
+                Ok(dcs::SetAddressMode::from(options))
+            }
+        }
+
+        let mut display = mock_display(Uncached, MockInterface::new(), (240, 320));
+        block_on(async {
+            display.set_address_window(0, 0, 9, 19).await.unwrap();
+            display.set_address_window(0, 0, 9, 19).await.unwrap();
+            display.set_address_window(0, 20, 9, 39).await.unwrap();
+        });
+
+        display.di.assert_commands(&[
+            (0x2A, &[0, 0, 0, 9]),
+            (0x2B, &[0, 0, 0, 19]),
+            (0x2A, &[0, 0, 0, 9]),
+            (0x2B, &[0, 0, 0, 19]),
+            (0x2A, &[0, 0, 0, 9]),
+            (0x2B, &[0, 20, 0, 39]),
+        ]);
+    }
+
+    #[test]
+    fn set_pixels_clips_to_display() {
+        use embedded_graphics::pixelcolor::Rgb565;
+
+        use crate::testing::Transfer;
+
+        let mut display = mock_display(ST7789, MockInterface::new(), (4, 3));
+        let pixels = || (0..9).map(|i| Rgb565::new(0, 0, i));
+
+        let area = Rectangle::new(Point::new(-1, -1), Size::new(3, 3));
+        block_on(display.set_pixels(&area, pixels())).unwrap();
+        display.di.assert_transfers(&[
+            Transfer::Command(0x2A, std::vec![0, 0, 0, 1]),
+            Transfer::Command(0x2B, std::vec![0, 0, 0, 1]),
+            Transfer::Command(0x2C, std::vec![]),
+            Transfer::Data(std::vec![0, 4, 0, 5, 0, 7, 0, 8]),
+        ]);
+
+        display.di.clear();
+        let area = Rectangle::new(Point::new(3, 2), Size::new(3, 3));
+        block_on(display.set_pixels(&area, pixels())).unwrap();
+        display.di.assert_transfers(&[
+            Transfer::Command(0x2A, std::vec![0, 3, 0, 3]),
+            Transfer::Command(0x2B, std::vec![0, 2, 0, 2]),
+            Transfer::Command(0x2C, std::vec![]),
+            Transfer::Data(std::vec![0, 0]),
+        ]);
+
+        display.di.clear();
+        let area = Rectangle::new(Point::new(4, 0), Size::new(3, 3));
+        block_on(display.set_pixels(&area, pixels())).unwrap();
+        assert!(display.di.transfers().is_empty());
+    }
+
+    #[test]
+    fn pipelined_pixels() {
+        use embedded_graphics::pixelcolor::Rgb565;
+
+        use crate::testing::Transfer;
+
+        let mut display = mock_display(ST7789, MockInterface::new(), (240, 320));
+        let pixels = (0..4).map(|i| Rgb565::new(0, 0, i));
+        block_on(display.show_pixels_pipelined(1, 2, 2, 2, pixels, [&mut [0; 4], &mut [0; 4]]))
+            .unwrap();
+
+        display.di.assert_transfers(&[
+            Transfer::Command(0x2A, std::vec![0, 1, 0, 2]),
+            Transfer::Command(0x2B, std::vec![0, 2, 0, 3]),
+            Transfer::Command(0x2C, std::vec![]),
+            Transfer::Data(std::vec![0, 0, 0, 1]),
+            Transfer::Data(std::vec![0, 2, 0, 3]),
+        ]);
+
+        display.di.clear();
+        block_on(async {
+            let pixels = core::iter::repeat(Rgb565::new(0, 0, 0));
+            let buffers = [&mut [0; 4][..], &mut [0; 4]];
+            display
+                .show_pixels_pipelined(0, 0, 0, 2, pixels.clone(), buffers)
+                .await
+                .unwrap();
+            let buffers = [&mut [0; 4][..], &mut [0; 4]];
+            display
+                .show_pixels_pipelined(0, 0, 2, 0, pixels, buffers)
+                .await
+                .unwrap();
+        });
+        assert!(display.di.transfers().is_empty());
+    }
+}
@@ -9,6 +9,11 @@ use embedded_hal_async::delay::DelayNs;
 
 pub use crate::builder::ConfigurationError;
 
+mod generic_dcs;
+mod init_script;
+pub use generic_dcs::*;
+pub use init_script::*;
+
 mod gc9107;
 mod gc9a01;
 // mod ili9225;
@@ -40,6 +45,18 @@ pub trait Model: Sized {
     const FRAMEBUFFER_SIZE: (u16, u16);
     const RESET_DURATION: u32 = 10;
 
+    /// Allows [`Display`](crate::Display) to skip unchanged parts of the address window.
+    ///
+    /// If this is `true`, the display caches the last address window. An
+    /// unchanged window isn't sent again and a window which only changed in
+    /// one direction is sent using [`update_column_address`](Self::update_column_address)
+    /// or [`update_page_address`](Self::update_page_address). This requires
+    /// that the memory write restarts at the window origin and that both
+    /// methods match [`update_address_window`](Self::update_address_window).
+    ///
+    /// If this is `false`, every address window is sent by `update_address_window`.
+    const CACHE_ADDRESS_WINDOW: bool = false;
+
     async fn init<DELAY, DI>(
         &mut self,
         di: &mut DI,
@@ -63,9 +80,15 @@ pub trait Model: Sized {
         Ok(madctl_cmd) // Return the struct
     }
 
+    /// Sets the address window for display RAM access.
+    ///
+    /// Models which override this method and enable
+    /// [`CACHE_ADDRESS_WINDOW`](Self::CACHE_ADDRESS_WINDOW) must override
+    /// [`update_column_address`](Self::update_column_address) and
+    /// [`update_page_address`](Self::update_page_address) as well.
     async fn update_address_window<DI>(
         di: &mut DI,
-        _rotation: Rotation,
+        rotation: Rotation,
         sx: u16,
         sy: u16,
         ex: u16,
@@ -74,7 +97,33 @@ pub trait Model: Sized {
     where
         DI: Interface, // DI will also impl InterfaceExt
     {
-        di.write_command(dcs::SetColumnAddress::new(sx, ex)).await?;
+        Self::update_column_address(di, rotation, sx, ex).await?;
+        Self::update_page_address(di, rotation, sy, ey).await
+    }
+
+    /// Sets the column range of the address window.
+    async fn update_column_address<DI>(
+        di: &mut DI,
+        _rotation: Rotation,
+        sx: u16,
+        ex: u16,
+    ) -> Result<(), DI::Error>
+    where
+        DI: Interface, // DI will also impl InterfaceExt
+    {
+        di.write_command(dcs::SetColumnAddress::new(sx, ex)).await
+    }
+
+    /// Sets the page (row) range of the address window.
+    async fn update_page_address<DI>(
+        di: &mut DI,
+        _rotation: Rotation,
+        sy: u16,
+        ey: u16,
+    ) -> Result<(), DI::Error>
+    where
+        DI: Interface, // DI will also impl InterfaceExt
+    {
         di.write_command(dcs::SetPageAddress::new(sy, ey)).await
     }
 
@@ -105,6 +154,18 @@ pub trait Model: Sized {
         di.write_command(dcs::WriteMemoryStart).await
     }
 
+    /// Resumes a memory write at the position where the previous write stopped.
+    ///
+    /// The default sends the DCS Write Memory Continue command. Models which
+    /// override [`write_memory_start`](Self::write_memory_start) with a non
+    /// DCS command must override this method as well.
+    async fn write_memory_continue<DI>(di: &mut DI) -> Result<(), DI::Error>
+    where
+        DI: Interface, // DI will also impl InterfaceExt
+    {
+        di.write_command(dcs::WriteMemoryContinue).await
+    }
+
     async fn software_reset<DI>(di: &mut DI) -> Result<(), DI::Error>
     where
         DI: Interface, // DI will also impl InterfaceExt
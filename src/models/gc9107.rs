@@ -1,21 +1,57 @@
 use embedded_hal_async::delay::DelayNs;
 
 use crate::{
-    dcs::{
-        BitsPerPixel, ExitSleepMode, InterfaceExt, PixelFormat, SetAddressMode, SetDisplayOn,
-        SetInvertMode, SetPixelFormat,
-    },
+    dcs::SetAddressMode,
     interface::{Interface, InterfaceKind},
-    models::{Model, ModelInitError},
+    models::{run_init_script, InitStep, Model, ModelInitError},
     options::ModelOptions,
     ConfigurationError,
 };
 
+const INIT_SCRIPT: &[InitStep] = &[
+    InitStep::delay_ms(200),
+    InitStep::command(0xFE, &[]).with_delay_ms(5),
+    InitStep::command(0xEF, &[]).with_delay_ms(5),
+    InitStep::command(0xB0, &[0xC0]),
+    InitStep::command(0xB2, &[0x2F]),
+    InitStep::command(0xB3, &[0x03]),
+    InitStep::command(0xB6, &[0x19]),
+    InitStep::command(0xB7, &[0x01]),
+    InitStep::address_mode(),
+    InitStep::command(0xAC, &[0xCB]),
+    InitStep::command(0xAB, &[0x0E]),
+    InitStep::command(0xB4, &[0x04]),
+    InitStep::command(0xA8, &[0x19]),
+    InitStep::command(0x3A, &[0x55]), // 16 bits per pixel
+    InitStep::command(0xB8, &[0x08]),
+    InitStep::command(0xE8, &[0x24]),
+    InitStep::command(0xE9, &[0x48]),
+    InitStep::command(0xEA, &[0x22]),
+    InitStep::command(0xC6, &[0x30]),
+    InitStep::command(0xC7, &[0x18]),
+    InitStep::command(
+        0xF0,
+        &[
+            0x01, 0x2b, 0x23, 0x3c, 0xb7, 0x12, 0x17, 0x60, 0x00, 0x06, 0x0c, 0x17, 0x12, 0x1f,
+        ],
+    ),
+    InitStep::command(
+        0xF1,
+        &[
+            0x05, 0x2e, 0x2d, 0x44, 0xd6, 0x15, 0x17, 0xa0, 0x02, 0x0d, 0x0d, 0x1a, 0x18, 0x1f,
+        ],
+    ),
+    InitStep::invert_mode(),
+    InitStep::command(0x11, &[]).with_delay_ms(120), // exit sleep mode
+    InitStep::command(0x29, &[]),                    // display on
+];
+
 /// GC9107 display in Rgb565 color mode.
 pub struct GC9107;
 
 impl Model for GC9107 {
     const FRAMEBUFFER_SIZE: (u16, u16) = (128, 160);
+    const CACHE_ADDRESS_WINDOW: bool = true;
 
     async fn init<DELAY, DI>(
         &mut self,
@@ -36,67 +72,87 @@ impl Model for GC9107 {
             ));
         }
 
-        delay.delay_ms(200).await;
-
-        di.write_raw(0xFE, &[]).await?;
-        delay.delay_ms(5).await;
-        di.write_raw(0xEF, &[]).await?;
-        delay.delay_ms(5).await;
-
-        di.write_raw(0xB0, &[0xC0]).await?;
-        di.write_raw(0xB2, &[0x2F]).await?;
-        di.write_raw(0xB3, &[0x03]).await?;
-        di.write_raw(0xB6, &[0x19]).await?;
-        di.write_raw(0xB7, &[0x01]).await?;
-
-        let madctl = SetAddressMode::from(options);
-        di.write_command(madctl).await?;
-
-        di.write_raw(0xAC, &[0xCB]).await?;
-        di.write_raw(0xAB, &[0x0E]).await?;
-
-        di.write_raw(0xB4, &[0x04]).await?;
-
-        di.write_raw(0xA8, &[0x19]).await?;
-
-        let pf_cmd = SetPixelFormat::new(PixelFormat::with_all(BitsPerPixel::Sixteen));
-        di.write_command(pf_cmd).await?;
-
-        di.write_raw(0xB8, &[0x08]).await?;
-
-        di.write_raw(0xE8, &[0x24]).await?;
-
-        di.write_raw(0xE9, &[0x48]).await?;
-
-        di.write_raw(0xEA, &[0x22]).await?;
-
-        di.write_raw(0xC6, &[0x30]).await?;
-        di.write_raw(0xC7, &[0x18]).await?;
-
-        di.write_raw(
-            0xF0,
-            &[
-                0x01, 0x2b, 0x23, 0x3c, 0xb7, 0x12, 0x17, 0x60, 0x00, 0x06, 0x0c, 0x17, 0x12, 0x1f,
-            ],
-        )
-        .await?;
-
-        di.write_raw(
-            0xF1,
-            &[
-                0x05, 0x2e, 0x2d, 0x44, 0xd6, 0x15, 0x17, 0xa0, 0x02, 0x0d, 0x0d, 0x1a, 0x18, 0x1f,
-            ],
-        )
-        .await?;
-
-        di.write_command(SetInvertMode::new(options.invert_colors))
-            .await?;
+        run_init_script(di, delay, options, INIT_SCRIPT)
+            .await
+            .map_err(Into::into)
+    }
+}
 
-        di.write_command(ExitSleepMode).await?; // turn off sleep
-        delay.delay_ms(120).await;
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+
+    use crate::testing::{
+        assert_display_timing, MockDelay, MockInterface, Parallel16Bit, Serial4Line, TimingRules,
+    };
+
+    use super::*;
+
+    #[test]
+    fn init_sequence() {
+        let mut di = MockInterface::new();
+        let mut delay = MockDelay::new();
+        let options = ModelOptions::full_size::<GC9107>();
+        block_on(GC9107.init(&mut di, &mut delay, &options)).unwrap();
+
+        di.assert_commands(&[
+            (0xFE, &[]),
+            (0xEF, &[]),
+            (0xB0, &[0xC0]),
+            (0xB2, &[0x2F]),
+            (0xB3, &[0x03]),
+            (0xB6, &[0x19]),
+            (0xB7, &[0x01]),
+            (0x36, &[0x00]),
+            (0xAC, &[0xCB]),
+            (0xAB, &[0x0E]),
+            (0xB4, &[0x04]),
+            (0xA8, &[0x19]),
+            (0x3A, &[0x55]),
+            (0xB8, &[0x08]),
+            (0xE8, &[0x24]),
+            (0xE9, &[0x48]),
+            (0xEA, &[0x22]),
+            (0xC6, &[0x30]),
+            (0xC7, &[0x18]),
+            (
+                0xF0,
+                &[
+                    0x01, 0x2B, 0x23, 0x3C, 0xB7, 0x12, 0x17, 0x60, 0x00, 0x06, 0x0C, 0x17, 0x12,
+                    0x1F,
+                ],
+            ),
+            (
+                0xF1,
+                &[
+                    0x05, 0x2E, 0x2D, 0x44, 0xD6, 0x15, 0x17, 0xA0, 0x02, 0x0D, 0x0D, 0x1A, 0x18,
+                    0x1F,
+                ],
+            ),
+            (0x20, &[]),
+            (0x11, &[]),
+            (0x29, &[]),
+        ]);
+        delay.assert_delays_us(&[200_000, 5_000, 5_000, 120_000]);
+    }
 
-        di.write_command(SetDisplayOn).await?; // turn on display
+    #[test]
+    fn rejects_16_bit_interface() {
+        let mut di = MockInterface::<Parallel16Bit>::default();
+        let options = ModelOptions::full_size::<GC9107>();
+        let result = block_on(GC9107.init(&mut di, &mut MockDelay::new(), &options));
+
+        assert!(matches!(
+            result,
+            Err(ModelInitError::InvalidConfiguration(
+                ConfigurationError::UnsupportedInterface
+            ))
+        ));
+        di.assert_commands(&[]);
+    }
 
-        Ok(madctl)
+    #[test]
+    fn timing() {
+        assert_display_timing::<Serial4Line, _>(|| GC9107, &TimingRules::MIPI_DCS);
     }
 }
@@ -1,21 +1,91 @@
 use embedded_hal_async::delay::DelayNs;
 
 use crate::{
-    dcs::{
-        BitsPerPixel, ExitSleepMode, InterfaceExt, PixelFormat, SetAddressMode, SetDisplayOn,
-        SetInvertMode, SetPixelFormat,
-    },
+    dcs::SetAddressMode,
     interface::{Interface, InterfaceKind},
-    models::{Model, ModelInitError},
+    models::{run_init_script, InitStep, Model, ModelInitError},
     options::ModelOptions,
     ConfigurationError,
 };
 
+const INIT_SCRIPT: &[InitStep] = &[
+    InitStep::delay_ms(200),
+    InitStep::command(0xEF, &[]), // inter register enable 2
+    InitStep::command(0xEB, &[0x14]),
+    InitStep::command(0xFE, &[]), // inter register enable 1
+    InitStep::command(0xEF, &[]), // inter register enable 2
+    InitStep::command(0xEB, &[0x14]),
+    InitStep::command(0x84, &[0x40]),
+    InitStep::command(0x85, &[0xFF]),
+    InitStep::command(0x86, &[0xFF]),
+    InitStep::command(0x87, &[0xFF]),
+    InitStep::command(0x88, &[0x0A]),
+    InitStep::command(0x89, &[0x21]),
+    InitStep::command(0x8A, &[0x00]),
+    InitStep::command(0x8B, &[0x80]),
+    InitStep::command(0x8C, &[0x01]),
+    InitStep::command(0x8D, &[0x01]),
+    InitStep::command(0x8E, &[0xFF]),
+    InitStep::command(0x8F, &[0xFF]),
+    InitStep::command(0xB6, &[0x00, 0x20]), // display function control
+    InitStep::address_mode(),
+    InitStep::command(0x3A, &[0x55]), // 16 bits per pixel
+    InitStep::command(0x90, &[0x08, 0x08, 0x08, 0x08]),
+    InitStep::command(0xBD, &[0x06]),
+    InitStep::command(0xBC, &[0x00]),
+    InitStep::command(0xFF, &[0x60, 0x01, 0x04]),
+    InitStep::command(0xC3, &[0x13]), // power control 2
+    InitStep::command(0xC4, &[0x13]), // power control 3
+    InitStep::command(0xC9, &[0x22]), // power control 4
+    InitStep::command(0xBE, &[0x11]),
+    InitStep::command(0xE1, &[0x10, 0x0E]),
+    InitStep::command(0xDF, &[0x20, 0x0c, 0x02]),
+    InitStep::command(0xF0, &[0x45, 0x09, 0x08, 0x08, 0x26, 0x2A]), // gamma 1
+    InitStep::command(0xF1, &[0x43, 0x70, 0x72, 0x36, 0x37, 0x6f]), // gamma 2
+    InitStep::command(0xF2, &[0x45, 0x09, 0x08, 0x08, 0x26, 0x2A]), // gamma 3
+    InitStep::command(0xF3, &[0x43, 0x70, 0x72, 0x36, 0x37, 0x6f]), // gamma 4
+    InitStep::command(0xED, &[0x18, 0x0B]),
+    InitStep::command(0xAE, &[0x77]),
+    InitStep::command(0xCD, &[0x63]),
+    InitStep::command(
+        0x70,
+        &[0x07, 0x07, 0x04, 0x0E, 0x0F, 0x09, 0x07, 0x08, 0x03],
+    ),
+    InitStep::command(0xE8, &[0x34]), // framerate
+    InitStep::command(
+        0x62,
+        &[
+            0x18, 0x0D, 0x71, 0xED, 0x70, 0x70, 0x18, 0x0F, 0x71, 0xEF, 0x70, 0x70,
+        ],
+    ),
+    InitStep::command(
+        0x63,
+        &[
+            0x18, 0x11, 0x71, 0xF1, 0x70, 0x70, 0x18, 0x13, 0x71, 0xF3, 0x70, 0x70,
+        ],
+    ),
+    InitStep::command(0x64, &[0x28, 0x29, 0xF1, 0x01, 0xF1, 0x00, 0x07]),
+    InitStep::command(
+        0x66,
+        &[0x3C, 0x00, 0xCD, 0x67, 0x45, 0x45, 0x10, 0x00, 0x00, 0x00],
+    ),
+    InitStep::command(
+        0x67,
+        &[0x00, 0x3C, 0x00, 0x00, 0x00, 0x01, 0x54, 0x10, 0x32, 0x98],
+    ),
+    InitStep::command(0x74, &[0x10, 0x85, 0x80, 0x00, 0x00, 0x4E, 0x00]),
+    InitStep::command(0x98, &[0x3e, 0x07]),
+    InitStep::invert_mode(),
+    InitStep::command(0x11, &[]).with_delay_ms(120), // exit sleep mode
+    InitStep::command(0x29, &[]),                    // display on
+];
+
 /// GC9A01 display in Rgb565 color mode.
 pub struct GC9A01;
 
 impl Model for GC9A01 {
     const FRAMEBUFFER_SIZE: (u16, u16) = (240, 240);
+    const CACHE_ADDRESS_WINDOW: bool = true;
 
     async fn init<DELAY, DI>(
         &mut self,
@@ -36,109 +106,104 @@ impl Model for GC9A01 {
             ));
         }
 
-        let madctl = SetAddressMode::from(options);
-
-        delay.delay_us(200_000).await;
-
-        di.write_raw(0xEF, &[]).await?; // inter register enable 2
-        di.write_raw(0xEB, &[0x14]).await?;
-        di.write_raw(0xFE, &[]).await?; // inter register enable 1
-        di.write_raw(0xEF, &[]).await?; // inter register enable 2
-        di.write_raw(0xEB, &[0x14]).await?;
-
-        di.write_raw(0x84, &[0x40]).await?;
-        di.write_raw(0x85, &[0xFF]).await?;
-        di.write_raw(0x86, &[0xFF]).await?;
-        di.write_raw(0x87, &[0xFF]).await?;
-        di.write_raw(0x88, &[0x0A]).await?;
-        di.write_raw(0x89, &[0x21]).await?;
-        di.write_raw(0x8A, &[0x00]).await?;
-        di.write_raw(0x8B, &[0x80]).await?;
-        di.write_raw(0x8C, &[0x01]).await?;
-        di.write_raw(0x8D, &[0x01]).await?;
-        di.write_raw(0x8E, &[0xFF]).await?;
-        di.write_raw(0x8F, &[0xFF]).await?;
-
-        di.write_raw(0xB6, &[0x00, 0x20]).await?; // display function control
-
-        di.write_command(madctl).await?; // set memory data access control, Top -> Bottom, RGB, Left -> Right
-
-        let pf_cmd = SetPixelFormat::new(PixelFormat::with_all(BitsPerPixel::Sixteen));
-        di.write_command(pf_cmd).await?;
-
-        di.write_raw(0x90, &[0x08, 0x08, 0x08, 0x08]).await?;
-        di.write_raw(0xBD, &[0x06]).await?;
-        di.write_raw(0xBC, &[0x00]).await?;
-        di.write_raw(0xFF, &[0x60, 0x01, 0x04]).await?;
-
-        di.write_raw(0xC3, &[0x13]).await?; // power control 2
-        di.write_raw(0xC4, &[0x13]).await?; // power control 3
-        di.write_raw(0xC9, &[0x22]).await?; // power control 4
-
-        di.write_raw(0xBE, &[0x11]).await?;
-        di.write_raw(0xE1, &[0x10, 0x0E]).await?;
-        di.write_raw(0xDF, &[0x20, 0x0c, 0x02]).await?;
-
-        di.write_raw(0xF0, &[0x45, 0x09, 0x08, 0x08, 0x26, 0x2A])
-            .await?; // gamma 1
-        di.write_raw(0xF1, &[0x43, 0x70, 0x72, 0x36, 0x37, 0x6f])
-            .await?; // gamma 2
-        di.write_raw(0xF2, &[0x45, 0x09, 0x08, 0x08, 0x26, 0x2A])
-            .await?; // gamma 3
-        di.write_raw(0xF3, &[0x43, 0x70, 0x72, 0x36, 0x37, 0x6f])
-            .await?; // gamma 4
-
-        di.write_raw(0xED, &[0x18, 0x0B]).await?;
-        di.write_raw(0xAE, &[0x77]).await?;
-        di.write_raw(0xCD, &[0x63]).await?;
-
-        di.write_raw(
-            0x70,
-            &[0x07, 0x07, 0x04, 0x0E, 0x0F, 0x09, 0x07, 0x08, 0x03],
-        )
-        .await?;
-
-        di.write_raw(0xE8, &[0x34]).await?; // framerate
-
-        di.write_raw(
-            0x62,
-            &[
-                0x18, 0x0D, 0x71, 0xED, 0x70, 0x70, 0x18, 0x0F, 0x71, 0xEF, 0x70, 0x70,
-            ],
-        )
-        .await?;
-        di.write_raw(
-            0x63,
-            &[
-                0x18, 0x11, 0x71, 0xF1, 0x70, 0x70, 0x18, 0x13, 0x71, 0xF3, 0x70, 0x70,
-            ],
-        )
-        .await?;
-        di.write_raw(0x64, &[0x28, 0x29, 0xF1, 0x01, 0xF1, 0x00, 0x07])
-            .await?;
-        di.write_raw(
-            0x66,
-            &[0x3C, 0x00, 0xCD, 0x67, 0x45, 0x45, 0x10, 0x00, 0x00, 0x00],
-        )
-        .await?;
-        di.write_raw(
-            0x67,
-            &[0x00, 0x3C, 0x00, 0x00, 0x00, 0x01, 0x54, 0x10, 0x32, 0x98],
-        )
-        .await?;
-
-        di.write_raw(0x74, &[0x10, 0x85, 0x80, 0x00, 0x00, 0x4E, 0x00])
-            .await?;
-        di.write_raw(0x98, &[0x3e, 0x07]).await?;
-
-        di.write_command(SetInvertMode::new(options.invert_colors))
-            .await?; // set color inversion
-
-        di.write_command(ExitSleepMode).await?; // turn off sleep
-        delay.delay_us(120_000).await;
+        run_init_script(di, delay, options, INIT_SCRIPT)
+            .await
+            .map_err(Into::into)
+    }
+}
 
-        di.write_command(SetDisplayOn).await?; // turn on display
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+
+    use crate::testing::{
+        assert_display_timing, MockDelay, MockInterface, Serial4Line, TimingRules,
+    };
+
+    use super::*;
+
+    #[test]
+    fn init_sequence() {
+        let mut di = MockInterface::new();
+        let mut delay = MockDelay::new();
+        let options = ModelOptions::full_size::<GC9A01>();
+        block_on(GC9A01.init(&mut di, &mut delay, &options)).unwrap();
+
+        di.assert_commands(&[
+            (0xEF, &[]),
+            (0xEB, &[0x14]),
+            (0xFE, &[]),
+            (0xEF, &[]),
+            (0xEB, &[0x14]),
+            (0x84, &[0x40]),
+            (0x85, &[0xFF]),
+            (0x86, &[0xFF]),
+            (0x87, &[0xFF]),
+            (0x88, &[0x0A]),
+            (0x89, &[0x21]),
+            (0x8A, &[0x00]),
+            (0x8B, &[0x80]),
+            (0x8C, &[0x01]),
+            (0x8D, &[0x01]),
+            (0x8E, &[0xFF]),
+            (0x8F, &[0xFF]),
+            (0xB6, &[0x00, 0x20]),
+            (0x36, &[0x00]),
+            (0x3A, &[0x55]),
+            (0x90, &[0x08, 0x08, 0x08, 0x08]),
+            (0xBD, &[0x06]),
+            (0xBC, &[0x00]),
+            (0xFF, &[0x60, 0x01, 0x04]),
+            (0xC3, &[0x13]),
+            (0xC4, &[0x13]),
+            (0xC9, &[0x22]),
+            (0xBE, &[0x11]),
+            (0xE1, &[0x10, 0x0E]),
+            (0xDF, &[0x20, 0x0C, 0x02]),
+            (0xF0, &[0x45, 0x09, 0x08, 0x08, 0x26, 0x2A]),
+            (0xF1, &[0x43, 0x70, 0x72, 0x36, 0x37, 0x6F]),
+            (0xF2, &[0x45, 0x09, 0x08, 0x08, 0x26, 0x2A]),
+            (0xF3, &[0x43, 0x70, 0x72, 0x36, 0x37, 0x6F]),
+            (0xED, &[0x18, 0x0B]),
+            (0xAE, &[0x77]),
+            (0xCD, &[0x63]),
+            (
+                0x70,
+                &[0x07, 0x07, 0x04, 0x0E, 0x0F, 0x09, 0x07, 0x08, 0x03],
+            ),
+            (0xE8, &[0x34]),
+            (
+                0x62,
+                &[
+                    0x18, 0x0D, 0x71, 0xED, 0x70, 0x70, 0x18, 0x0F, 0x71, 0xEF, 0x70, 0x70,
+                ],
+            ),
+            (
+                0x63,
+                &[
+                    0x18, 0x11, 0x71, 0xF1, 0x70, 0x70, 0x18, 0x13, 0x71, 0xF3, 0x70, 0x70,
+                ],
+            ),
+            (0x64, &[0x28, 0x29, 0xF1, 0x01, 0xF1, 0x00, 0x07]),
+            (
+                0x66,
+                &[0x3C, 0x00, 0xCD, 0x67, 0x45, 0x45, 0x10, 0x00, 0x00, 0x00],
+            ),
+            (
+                0x67,
+                &[0x00, 0x3C, 0x00, 0x00, 0x00, 0x01, 0x54, 0x10, 0x32, 0x98],
+            ),
+            (0x74, &[0x10, 0x85, 0x80, 0x00, 0x00, 0x4E, 0x00]),
+            (0x98, &[0x3E, 0x07]),
+            (0x20, &[]),
+            (0x11, &[]),
+            (0x29, &[]),
+        ]);
+        delay.assert_delays_us(&[200_000, 120_000]);
+    }
 
-        Ok(madctl)
+    #[test]
+    fn timing() {
+        assert_display_timing::<Serial4Line, _>(|| GC9A01, &TimingRules::MIPI_DCS);
     }
 }
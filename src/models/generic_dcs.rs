@@ -0,0 +1,264 @@
+use core::marker::PhantomData;
+
+use embedded_hal_async::delay::DelayNs;
+
+use crate::{
+    dcs::{BitsPerPixel, InterfaceExt, PixelFormat, SetAddressMode, SetPixelFormat},
+    interface::{Interface, InterfaceKind},
+    models::{run_init_script, InitStep, Model, ModelInitError},
+    options::ModelOptions,
+    ConfigurationError,
+};
+
+const INIT_START: &[InitStep] = &[
+    // Sleep out can only be sent 120 ms after a software reset.
+    InitStep::delay_ms(120),
+    InitStep::command(0x11, &[]).with_delay_ms(120), // exit sleep mode
+    InitStep::address_mode(),
+    InitStep::invert_mode(),
+];
+
+/// Configuration of a [`GenericDcsModel`].
+///
+/// The configuration is created by a `const` builder, which starts with
+/// [`DcsModelConfig::new`]. Unset values default to a 16 bit pixel format,
+/// a 10 µs reset pulse, all interface kinds, no extra init commands and a
+/// 120 ms delay after the display is turned on.
+#[derive(Debug, Clone, Copy)]
+pub struct DcsModelConfig {
+    framebuffer_size: (u16, u16),
+    reset_duration: u32,
+    pixel_format: PixelFormat,
+    interfaces: &'static [InterfaceKind],
+    init_steps: &'static [InitStep],
+    display_on_delay_us: u32,
+}
+
+impl DcsModelConfig {
+    /// Creates a new configuration for a controller with a framebuffer of
+    /// `width` x `height` pixels.
+    pub const fn new(width: u16, height: u16) -> Self {
+        Self {
+            framebuffer_size: (width, height),
+            reset_duration: 10,
+            pixel_format: PixelFormat::with_all(BitsPerPixel::Sixteen),
+            interfaces: &[
+                InterfaceKind::Serial4Line,
+                InterfaceKind::Parallel8Bit,
+                InterfaceKind::Parallel16Bit,
+            ],
+            init_steps: &[],
+            display_on_delay_us: 120_000,
+        }
+    }
+
+    /// Sets the duration of the hardware reset pulse in microseconds.
+    #[must_use]
+    pub const fn reset_duration(self, reset_duration: u32) -> Self {
+        Self {
+            reset_duration,
+            ..self
+        }
+    }
+
+    /// Sets the pixel format which is sent with the COLMOD command.
+    #[must_use]
+    pub const fn pixel_format(self, pixel_format: PixelFormat) -> Self {
+        Self {
+            pixel_format,
+            ..self
+        }
+    }
+
+    /// Sets the supported interface kinds.
+    ///
+    /// The initialization fails with [`ConfigurationError::UnsupportedInterface`]
+    /// for all other interfaces.
+    #[must_use]
+    pub const fn interfaces(self, interfaces: &'static [InterfaceKind]) -> Self {
+        Self { interfaces, ..self }
+    }
+
+    /// Sets extra init commands, for example vendor specific power and gamma
+    /// settings.
+    ///
+    /// The steps are executed after the pixel format is set and before the
+    /// display is turned on.
+    #[must_use]
+    pub const fn init_steps(self, init_steps: &'static [InitStep]) -> Self {
+        Self { init_steps, ..self }
+    }
+
+    /// Sets the delay after the display on command in milliseconds.
+    #[must_use]
+    pub const fn display_on_delay_ms(self, delay_ms: u32) -> Self {
+        Self {
+            display_on_delay_us: delay_ms * 1000,
+            ..self
+        }
+    }
+}
+
+/// Panel which is driven by a [`GenericDcsModel`].
+pub trait DcsPanel {
+    /// Configuration of the panel.
+    const CONFIG: DcsModelConfig;
+}
+
+/// Generic MIPI DCS display model.
+///
+/// This model supports controllers which only need a few vendor specific
+/// commands in addition to the standard MIPI DCS init sequence, without a
+/// new [`Model`] implementation. The panel is described by a type which
+/// implements [`DcsPanel`]. All other [`Model`] methods use the default MIPI
+/// DCS implementations.
+///
+/// The init sequence exits the sleep mode, sets MADCTL, the color inversion
+/// and the pixel format, executes the extra init commands and turns the
+/// display on.
+///
+/// ```
+/// use mipidsi::{
+///     interface::InterfaceKind,
+///     models::{DcsModelConfig, DcsPanel, GenericDcsModel, InitStep},
+/// };
+///
+/// struct Nv3030b;
+///
+/// impl DcsPanel for Nv3030b {
+///     const CONFIG: DcsModelConfig = DcsModelConfig::new(240, 280)
+///         .interfaces(&[InterfaceKind::Serial4Line])
+///         .init_steps(&[
+///             InitStep::command(0xFD, &[0x06, 0x08]), // enable extension commands
+///             InitStep::command(0xFD, &[0xFA, 0xFC]), // disable extension commands
+///         ])
+///         .display_on_delay_ms(20);
+/// }
+///
+/// let model = GenericDcsModel::<Nv3030b>::new();
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct GenericDcsModel<P: DcsPanel> {
+    panel: PhantomData<P>,
+}
+
+impl<P: DcsPanel> GenericDcsModel<P> {
+    /// Creates a new model.
+    pub const fn new() -> Self {
+        Self { panel: PhantomData }
+    }
+}
+
+impl<P: DcsPanel> Default for GenericDcsModel<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: DcsPanel> Model for GenericDcsModel<P> {
+    const FRAMEBUFFER_SIZE: (u16, u16) = P::CONFIG.framebuffer_size;
+    const CACHE_ADDRESS_WINDOW: bool = true;
+    const RESET_DURATION: u32 = P::CONFIG.reset_duration;
+
+    async fn init<DELAY, DI>(
+        &mut self,
+        di: &mut DI,
+        delay: &mut DELAY,
+        options: &ModelOptions,
+    ) -> Result<SetAddressMode, ModelInitError<DI::Error>>
+    where
+        DELAY: DelayNs,
+        DI: Interface,
+    {
+        let config = P::CONFIG;
+        if !config.interfaces.contains(&DI::KIND) {
+            return Err(ModelInitError::InvalidConfiguration(
+                ConfigurationError::UnsupportedInterface,
+            ));
+        }
+
+        let madctl = run_init_script(di, delay, options, INIT_START).await?;
+        di.write_command(SetPixelFormat::new(config.pixel_format))
+            .await?;
+        run_init_script(di, delay, options, config.init_steps).await?;
+
+        let init_end = [
+            InitStep::command(0x13, &[]), // enter normal mode
+            InitStep::command(0x29, &[]).with_delay_us(config.display_on_delay_us), // display on
+        ];
+        run_init_script(di, delay, options, &init_end).await?;
+
+        Ok(madctl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+
+    use crate::testing::{
+        assert_display_timing, MockDelay, MockInterface, Parallel8Bit, Serial4Line, TimingRules,
+    };
+
+    use super::*;
+
+    struct Panel;
+
+    impl DcsPanel for Panel {
+        const CONFIG: DcsModelConfig = DcsModelConfig::new(240, 280)
+            .reset_duration(20)
+            .pixel_format(PixelFormat::with_all(BitsPerPixel::Eighteen))
+            .interfaces(&[InterfaceKind::Serial4Line])
+            .init_steps(&[InitStep::command(0xB2, &[0x0C, 0x0C]).with_delay_ms(1)])
+            .display_on_delay_ms(20);
+    }
+
+    #[test]
+    fn init_sequence() {
+        assert_eq!(GenericDcsModel::<Panel>::FRAMEBUFFER_SIZE, (240, 280));
+        assert_eq!(GenericDcsModel::<Panel>::RESET_DURATION, 20);
+
+        let options = ModelOptions::with_all((240, 280), (0, 0));
+        let mut di = MockInterface::new();
+        let mut delay = MockDelay::new();
+        block_on(GenericDcsModel::<Panel>::new().init(&mut di, &mut delay, &options)).unwrap();
+
+        di.assert_commands(&[
+            (0x11, &[]),
+            (0x36, &[0x00]),
+            (0x20, &[]),
+            (0x3A, &[0x66]),
+            (0xB2, &[0x0C, 0x0C]),
+            (0x13, &[]),
+            (0x29, &[]),
+        ]);
+        delay.assert_delays_us(&[120_000, 120_000, 1_000, 20_000]);
+    }
+
+    #[test]
+    fn unsupported_interface() {
+        let options = ModelOptions::with_all((240, 280), (0, 0));
+        let mut di = MockInterface::<Parallel8Bit>::default();
+        let result = block_on(GenericDcsModel::<Panel>::new().init(
+            &mut di,
+            &mut MockDelay::new(),
+            &options,
+        ));
+
+        assert!(matches!(
+            result,
+            Err(ModelInitError::InvalidConfiguration(
+                ConfigurationError::UnsupportedInterface
+            ))
+        ));
+        di.assert_commands(&[]);
+    }
+
+    #[test]
+    fn timing() {
+        assert_display_timing::<Serial4Line, _>(
+            GenericDcsModel::<Panel>::new,
+            &TimingRules::MIPI_DCS,
+        );
+    }
+}
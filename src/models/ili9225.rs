@@ -250,6 +250,16 @@ impl Model for ILI9225Rgb565 {
         di.write_command(WriteMemoryStartILI9225).await
     }
 
+    /// The ILI9225 has no separate continue command. Selecting the GRAM
+    /// register again doesn't reset the address counter, so the write
+    /// resumes where the previous write stopped.
+    async fn write_memory_continue<DI>(di: &mut DI) -> Result<(), DI::Error>
+    where
+        DI: Interface,
+    {
+        di.write_command(WriteMemoryStartILI9225).await
+    }
+
     async fn update_options<DI>(&self, di: &mut DI, options: &ModelOptions) -> Result<(), DI::Error>
     where
         DI: Interface,
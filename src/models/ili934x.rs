@@ -1,14 +1,32 @@
 use embedded_hal_async::delay::DelayNs;
 
 use crate::{
-    dcs::{
-        EnterNormalMode, ExitSleepMode, InterfaceExt, PixelFormat, SetAddressMode, SetDisplayOn,
-        SetInvertMode, SetPixelFormat,
-    },
+    dcs::{InterfaceExt, PixelFormat, SetAddressMode, SetPixelFormat},
     interface::Interface,
+    models::{run_init_script, InitStep},
     options::ModelOptions,
 };
 
+// 15.4:  It is necessary to wait 5msec after releasing RESX before sending commands.
+// 8.2.2: It will be necessary to wait 5msec before sending new command following software reset.
+const INIT_START: &[InitStep] = &[
+    InitStep::delay_ms(5),
+    InitStep::address_mode(),
+    InitStep::command(0xB4, &[0x0]),
+    InitStep::invert_mode(),
+];
+
+const INIT_END: &[InitStep] = &[
+    // 8.2.12: It will be necessary to wait 120msec after sending Sleep In command (when in Sleep Out mode)
+    //          before Sleep Out command can be sent.
+    // The reset might have implicitly called the Sleep In command if the controller is reinitialized.
+    InitStep::command(0x13, &[]).with_delay_ms(120), // enter normal mode
+    // 8.2.12: It takes 120msec to become Sleep Out mode after SLPOUT command issued.
+    // 13.2 Power ON Sequence: Delay should be 60ms + 80ms
+    InitStep::command(0x11, &[]).with_delay_ms(140), // exit sleep mode
+    InitStep::command(0x29, &[]),                    // display on
+];
+
 /// Common init for all ILI934x controllers and color formats.
 #[allow(dead_code)]
 pub async fn init_common<DELAY, DI>(
@@ -21,32 +39,56 @@ where
     DELAY: DelayNs,
     DI: Interface,
 {
-    let madctl = SetAddressMode::from(options);
+    let madctl = run_init_script(di, delay, options, INIT_START).await?;
+    di.write_command(SetPixelFormat::new(pixel_format)).await?;
+    run_init_script(di, delay, options, INIT_END).await?;
+
+    Ok(madctl)
+}
 
-    // 15.4:  It is necessary to wait 5msec after releasing RESX before sending commands.
-    // 8.2.2: It will be necessary to wait 5msec before sending new command following software reset.
-    delay.delay_us(5_000).await;
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
 
-    di.write_command(madctl).await?;
-    di.write_raw(0xB4, &[0x0]).await?;
-    di.write_command(SetInvertMode::new(options.invert_colors))
-        .await?;
-    di.write_command(SetPixelFormat::new(pixel_format)).await?;
+    use crate::{
+        dcs::BitsPerPixel,
+        testing::{
+            assert_display_timing, rgb666_model, MockDelay, MockInterface, Serial4Line, TimingRules,
+        },
+    };
 
-    di.write_command(EnterNormalMode).await?;
+    use super::*;
 
-    // 8.2.12: It will be necessary to wait 120msec after sending Sleep In command (when in Sleep Out mode)
-    //          before Sleep Out command can be sent.
-    // The reset might have implicitly called the Sleep In command if the controller is reinitialized.
-    delay.delay_us(120_000).await;
+    rgb666_model!(Rgb666Model, (240, 320), init_common);
 
-    di.write_command(ExitSleepMode).await?;
+    #[test]
+    fn init_sequence() {
+        let mut di = MockInterface::new();
+        let mut delay = MockDelay::new();
+        let options = ModelOptions::with_all((320, 480), (0, 0));
+        let pixel_format = PixelFormat::with_all(BitsPerPixel::Eighteen);
+        block_on(init_common(&mut di, &mut delay, &options, pixel_format)).unwrap();
 
-    // 8.2.12: It takes 120msec to become Sleep Out mode after SLPOUT command issued.
-    // 13.2 Power ON Sequence: Delay should be 60ms + 80ms
-    delay.delay_us(140_000).await;
+        di.assert_commands(&[
+            (0x36, &[0x00]),
+            (0xB4, &[0x00]),
+            (0x20, &[]),
+            (0x3A, &[0x66]),
+            (0x13, &[]),
+            (0x11, &[]),
+            (0x29, &[]),
+        ]);
+        delay.assert_delays_us(&[5_000, 120_000, 140_000]);
+    }
 
-    di.write_command(SetDisplayOn).await?;
+    #[test]
+    fn timing() {
+        const RULES: TimingRules = TimingRules {
+            // 13.2 Power ON Sequence: Delay should be 60ms + 80ms
+            sleep_out_to_display_on_us: 140_000,
+            ..TimingRules::MIPI_DCS
+        };
 
-    Ok(madctl)
+        assert_display_timing::<Serial4Line, _>(|| Rgb666Model, &RULES);
+    }
 }
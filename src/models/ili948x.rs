@@ -1,15 +1,32 @@
 use embedded_hal_async::delay::DelayNs;
 
 use crate::{
-    dcs::{
-        EnterNormalMode, ExitSleepMode, InterfaceExt, PixelFormat, SetAddressMode, SetDisplayOn,
-        SetInvertMode, SetPixelFormat,
-    },
+    dcs::{InterfaceExt, PixelFormat, SetAddressMode, SetPixelFormat},
     interface::Interface,
-    models::ModelInitError,
+    models::{run_init_script, InitStep, ModelInitError},
     options::ModelOptions,
 };
 
+const INIT_START: &[InitStep] = &[
+    // Sleep out can only be sent 120 ms after a software reset.
+    InitStep::delay_ms(120),
+    InitStep::command(0x11, &[]).with_delay_ms(120), // exit sleep mode
+];
+
+const INIT_END: &[InitStep] = &[
+    InitStep::address_mode(), // left -> right, bottom -> top RGB
+    // InitStep::command(0xC5, &[0x00, 0x48, 0x00, 0x48]), // VCOM Control 1 [00 40 00 40]
+    // InitStep::command(0xB4, &[0x0]), // Inversion Control [00]
+    InitStep::invert_mode(),
+    // optional gamma setup
+    // InitStep::command(0xE0, &[0x00, 0x2C, 0x2C, 0x0B, 0x0C, 0x04, 0x4C, 0x64, 0x36, 0x03, 0x0E, 0x01, 0x10, 0x01, 0x00]), // Positive Gamma Control
+    // InitStep::command(0xE1, &[0x0F, 0x37, 0x37, 0x0C, 0x0F, 0x05, 0x50, 0x32, 0x36, 0x04, 0x0B, 0x00, 0x19, 0x14, 0x0F]), // Negative Gamma Control
+    InitStep::command(0xB6, &[0b0000_0010, 0x02, 0x3B]), // DFC
+    InitStep::command(0x13, &[]),                        // turn to normal mode
+    // DISPON requires some time otherwise we risk SPI data issues
+    InitStep::command(0x29, &[]).with_delay_ms(120), // turn on display
+];
+
 /// Common init for all ILI948x models and color formats.
 #[allow(dead_code)]
 pub async fn init_common<DELAY, DI>(
@@ -22,25 +39,55 @@ where
     DELAY: DelayNs,
     DI: Interface,
 {
-    let madctl = SetAddressMode::from(options);
-    di.write_command(ExitSleepMode).await?; // turn off sleep
+    let madctl = run_init_script(di, delay, options, INIT_START).await?;
     di.write_command(SetPixelFormat::new(pixel_format)).await?; // pixel format
-    di.write_command(madctl).await?; // left -> right, bottom -> top RGB
-                                     // dcs.write_command(Instruction::VCMOFSET, &[0x00, 0x48, 0x00, 0x48]).await?; //VCOM  Control 1 [00 40 00 40]
-                                     // dcs.write_command(Instruction::INVCO, &[0x0]).await?; //Inversion Control [00]
-    di.write_command(SetInvertMode::new(options.invert_colors))
-        .await?;
+    run_init_script(di, delay, options, INIT_END).await?;
 
-    // optional gamma setup
-    // dcs.write_raw(Instruction::PGC, &[0x00, 0x2C, 0x2C, 0x0B, 0x0C, 0x04, 0x4C, 0x64, 0x36, 0x03, 0x0E, 0x01, 0x10, 0x01, 0x00]).await?; // Positive Gamma Control
-    // dcs.write_raw(Instruction::NGC, &[0x0F, 0x37, 0x37, 0x0C, 0x0F, 0x05, 0x50, 0x32, 0x36, 0x04, 0x0B, 0x00, 0x19, 0x14, 0x0F]).await?; // Negative Gamma Control
+    Ok(madctl)
+}
 
-    di.write_raw(0xB6, &[0b0000_0010, 0x02, 0x3B]).await?; // DFC
-    di.write_command(EnterNormalMode).await?; // turn to normal mode
-    di.write_command(SetDisplayOn).await?; // turn on display
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
 
-    // DISPON requires some time otherwise we risk SPI data issues
-    delay.delay_us(120_000).await;
+    use crate::{
+        dcs::BitsPerPixel,
+        testing::{
+            assert_display_timing, rgb666_model, MockDelay, MockInterface, Serial4Line, TimingRules,
+        },
+    };
 
-    Ok(madctl)
+    use super::*;
+
+    rgb666_model!(Rgb666Model, (320, 480), init_common);
+
+    #[test]
+    fn init_sequence() {
+        let mut di = MockInterface::new();
+        let mut delay = MockDelay::new();
+        let options = ModelOptions::with_all((320, 480), (0, 0));
+        let pixel_format = PixelFormat::with_all(BitsPerPixel::Eighteen);
+        block_on(init_common(&mut di, &mut delay, &options, pixel_format)).unwrap();
+
+        di.assert_commands(&[
+            (0x11, &[]),
+            (0x3A, &[0x66]),
+            (0x36, &[0x00]),
+            (0x20, &[]),
+            (0xB6, &[0x02, 0x02, 0x3B]),
+            (0x13, &[]),
+            (0x29, &[]),
+        ]);
+        delay.assert_delays_us(&[120_000, 120_000, 120_000]);
+    }
+
+    #[test]
+    fn timing() {
+        const RULES: TimingRules = TimingRules {
+            display_on_to_command_us: 120_000,
+            ..TimingRules::MIPI_DCS
+        };
+
+        assert_display_timing::<Serial4Line, _>(|| Rgb666Model, &RULES);
+    }
 }
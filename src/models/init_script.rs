@@ -0,0 +1,257 @@
+use embedded_hal_async::delay::DelayNs;
+
+use crate::{
+    dcs::{InterfaceExt, SetAddressMode, SetInvertMode},
+    interface::Interface,
+    models::{Model, ModelInitError},
+    options::ModelOptions,
+};
+
+mod blob;
+pub use blob::*;
+
+/// Instruction of an [`InitStep`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// Sends a command with the parameters of the step.
+    Command(u8),
+    /// Sends the MADCTL command for the display options.
+    AddressMode,
+    /// Sends the INVON or INVOFF command for the display options.
+    InvertMode,
+    /// Doesn't send anything and only waits for the delay of the step.
+    Delay,
+}
+
+/// Single step of an init script.
+///
+/// An init script is a slice of steps, which is executed by [`run_init_script`].
+/// Every step sends an instruction and waits for an optional delay afterwards.
+///
+/// ```
+/// use mipidsi::models::InitStep;
+///
+/// const INIT_SCRIPT: &[InitStep] = &[
+///     InitStep::delay_ms(120),
+///     InitStep::command(0x11, &[]).with_delay_ms(120), // exit sleep mode
+///     InitStep::address_mode(),
+///     InitStep::invert_mode(),
+///     InitStep::command(0x3A, &[0x55]), // 16 bits per pixel
+///     InitStep::command(0x29, &[]), // display on
+/// ];
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitStep {
+    /// Instruction which is sent.
+    pub instruction: Instruction,
+    /// Parameters of a [`Instruction::Command`].
+    pub params: &'static [u8],
+    /// Delay after the instruction in microseconds.
+    pub delay_us: u32,
+}
+
+impl InitStep {
+    /// Creates a step which sends a command with parameters.
+    pub const fn command(instruction: u8, params: &'static [u8]) -> Self {
+        Self {
+            instruction: Instruction::Command(instruction),
+            params,
+            delay_us: 0,
+        }
+    }
+
+    /// Creates a step which sends the MADCTL command for the display options.
+    pub const fn address_mode() -> Self {
+        Self {
+            instruction: Instruction::AddressMode,
+            params: &[],
+            delay_us: 0,
+        }
+    }
+
+    /// Creates a step which sends the color inversion command for the display options.
+    pub const fn invert_mode() -> Self {
+        Self {
+            instruction: Instruction::InvertMode,
+            params: &[],
+            delay_us: 0,
+        }
+    }
+
+    /// Creates a step which only waits for `delay_us` microseconds.
+    pub const fn delay_us(delay_us: u32) -> Self {
+        Self {
+            instruction: Instruction::Delay,
+            params: &[],
+            delay_us,
+        }
+    }
+
+    /// Creates a step which only waits for `delay_ms` milliseconds.
+    pub const fn delay_ms(delay_ms: u32) -> Self {
+        Self::delay_us(delay_ms * 1000)
+    }
+
+    /// Sets the delay after the instruction in microseconds.
+    #[must_use]
+    pub const fn with_delay_us(self, delay_us: u32) -> Self {
+        Self { delay_us, ..self }
+    }
+
+    /// Sets the delay after the instruction in milliseconds.
+    #[must_use]
+    pub const fn with_delay_ms(self, delay_ms: u32) -> Self {
+        self.with_delay_us(delay_ms * 1000)
+    }
+}
+
+/// Runs an init script.
+///
+/// Returns the MADCTL value for the display options, regardless of whether
+/// the script contains an [`Instruction::AddressMode`] step.
+pub async fn run_init_script<DI, DELAY>(
+    di: &mut DI,
+    delay: &mut DELAY,
+    options: &ModelOptions,
+    script: &[InitStep],
+) -> Result<SetAddressMode, DI::Error>
+where
+    DI: Interface,
+    DELAY: DelayNs,
+{
+    let madctl = SetAddressMode::from(options);
+
+    for step in script {
+        execute_step(
+            di,
+            delay,
+            options,
+            step.instruction,
+            step.params,
+            step.delay_us,
+        )
+        .await?;
+    }
+
+    Ok(madctl)
+}
+
+/// Sends a single instruction and waits for the delay.
+async fn execute_step<DI, DELAY>(
+    di: &mut DI,
+    delay: &mut DELAY,
+    options: &ModelOptions,
+    instruction: Instruction,
+    params: &[u8],
+    delay_us: u32,
+) -> Result<(), DI::Error>
+where
+    DI: Interface,
+    DELAY: DelayNs,
+{
+    match instruction {
+        Instruction::Command(instruction) => di.write_raw(instruction, params).await?,
+        Instruction::AddressMode => di.write_command(SetAddressMode::from(options)).await?,
+        Instruction::InvertMode => {
+            di.write_command(SetInvertMode::new(options.invert_colors))
+                .await?
+        }
+        Instruction::Delay => {}
+    }
+
+    if delay_us > 0 {
+        delay.delay_us(delay_us).await;
+    }
+    Ok(())
+}
+
+/// Display model which is initialized by a user supplied init script.
+///
+/// The framebuffer size is set by the `WIDTH` and `HEIGHT` parameters. All
+/// other [`Model`] methods use the default MIPI DCS implementations. The
+/// model accepts every interface kind.
+///
+/// ```
+/// use mipidsi::models::{GenericModel, InitStep};
+///
+/// const INIT_SCRIPT: &[InitStep] = &[
+///     InitStep::delay_ms(120),
+///     InitStep::command(0x11, &[]).with_delay_ms(120), // exit sleep mode
+///     InitStep::address_mode(),
+///     InitStep::invert_mode(),
+///     InitStep::command(0x3A, &[0x55]), // 16 bits per pixel
+///     InitStep::command(0x29, &[]).with_delay_ms(120), // display on
+/// ];
+///
+/// let model = GenericModel::<240, 320>::new(INIT_SCRIPT);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct GenericModel<const WIDTH: u16, const HEIGHT: u16> {
+    script: &'static [InitStep],
+}
+
+impl<const WIDTH: u16, const HEIGHT: u16> GenericModel<WIDTH, HEIGHT> {
+    /// Creates a new model which runs `script` during the initialization.
+    pub const fn new(script: &'static [InitStep]) -> Self {
+        Self { script }
+    }
+
+    /// Returns the init script.
+    pub const fn script(&self) -> &'static [InitStep] {
+        self.script
+    }
+}
+
+impl<const WIDTH: u16, const HEIGHT: u16> Model for GenericModel<WIDTH, HEIGHT> {
+    const FRAMEBUFFER_SIZE: (u16, u16) = (WIDTH, HEIGHT);
+    const CACHE_ADDRESS_WINDOW: bool = true;
+
+    async fn init<DELAY, DI>(
+        &mut self,
+        di: &mut DI,
+        delay: &mut DELAY,
+        options: &ModelOptions,
+    ) -> Result<SetAddressMode, ModelInitError<DI::Error>>
+    where
+        DELAY: DelayNs,
+        DI: Interface,
+    {
+        run_init_script(di, delay, options, self.script)
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+
+    use crate::{
+        options::{ColorInversion, Orientation, Rotation},
+        testing::{MockDelay, MockInterface},
+    };
+
+    use super::*;
+
+    #[test]
+    fn runs_steps_with_delays() {
+        const SCRIPT: &[InitStep] = &[
+            InitStep::delay_ms(5),
+            InitStep::command(0xB1, &[0x05, 0x3A]).with_delay_us(10),
+            InitStep::address_mode(),
+            InitStep::invert_mode(),
+        ];
+
+        let mut options = ModelOptions::with_all((10, 10), (0, 0));
+        options.orientation = Orientation::new().rotate(Rotation::Deg90);
+        options.invert_colors = ColorInversion::Inverted;
+
+        let mut di = MockInterface::new();
+        let mut delay = MockDelay::new();
+        let madctl = block_on(run_init_script(&mut di, &mut delay, &options, SCRIPT)).unwrap();
+
+        assert_eq!(madctl, SetAddressMode::from(&options));
+        di.assert_commands(&[(0xB1, &[0x05, 0x3A]), (0x36, &[0x60]), (0x21, &[])]);
+        delay.assert_delays_us(&[5_000, 10]);
+    }
+}
@@ -0,0 +1,669 @@
+//! Init scripts which are loaded at runtime.
+//!
+//! Scripts are stored in a text or a binary format. Both formats are parsed
+//! without allocations and can be executed instead of a model specific init
+//! sequence, see [`ScriptModel`].
+//!
+//! # Text format
+//!
+//! The text format follows the C arrays which are commonly supplied by panel
+//! vendors. Values are separated by commas, semicolons or whitespace and are
+//! either decimal, hexadecimal with a `0x` prefix or binary with a `0b` prefix.
+//! `//` and `#` start a comment which continues to the end of the line.
+//!
+//! | Entry                                    | Step                                         |
+//! |------------------------------------------|----------------------------------------------|
+//! | `instruction, count, param1, …, paramN`  | Command with `count` parameters              |
+//! | `delay ms`                               | Delay in milliseconds                        |
+//! | `madctl`                                 | MADCTL for the display options               |
+//! | `invert`                                 | INVON or INVOFF for the display options      |
+//!
+//! ```text
+//! 0x11, 0, delay 120   // exit sleep mode
+//! 0xB1, 3, 0x05, 0x3A, 0x3A
+//! madctl, invert
+//! 0x3A, 1, 0x55
+//! 0x29, 0, delay 120
+//! ```
+//!
+//! # Binary format
+//!
+//! Every step starts with two bytes, an instruction and a header. Bits 0 to 6
+//! of the header contain the number of parameters which follow the header. If
+//! bit 7 is set, the step ends with a delay in milliseconds, stored as a
+//! little endian `u16`.
+//!
+//! If the parameter count is `0x7F` the instruction byte selects a special
+//! step without parameters: `0x00` only waits for the delay, `0x01` sends
+//! MADCTL and `0x02` sends INVON or INVOFF for the display options.
+//!
+//! ```text
+//! 0x11 0x80 0x78 0x00        exit sleep mode, delay 120 ms
+//! 0xB1 0x03 0x05 0x3A 0x3A
+//! 0x01 0x7F 0x02 0x7F        MADCTL, color inversion
+//! ```
+//!
+//! Text scripts can be converted to the binary format with [`Script::to_binary`].
+
+use embedded_hal_async::delay::DelayNs;
+
+use crate::{
+    dcs::SetAddressMode,
+    interface::Interface,
+    models::{Model, ModelInitError},
+    options::ModelOptions,
+    ConfigurationError,
+};
+
+use super::{execute_step, Instruction};
+
+/// Maximum number of parameters of a command in a script.
+pub const MAX_SCRIPT_PARAMS: usize = 126;
+
+/// Parameter count which marks a special step in the binary format.
+const SPECIAL: u8 = 0x7F;
+
+/// Header flag of steps with a delay in the binary format.
+const DELAY_FLAG: u8 = 0x80;
+
+/// Error which occurred while parsing or encoding a script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptError {
+    /// Kind of the error.
+    pub kind: ScriptErrorKind,
+    /// Byte offset in the script at which the error occurred.
+    pub offset: usize,
+}
+
+/// Kind of a [`ScriptError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptErrorKind {
+    /// A text token isn't a number or keyword.
+    InvalidToken,
+    /// A number is too large.
+    NumberOutOfRange,
+    /// A command has more than [`MAX_SCRIPT_PARAMS`] parameters.
+    TooManyParams,
+    /// The script ended in the middle of a step.
+    UnexpectedEnd,
+    /// A binary special step uses an unknown instruction.
+    UnknownSpecialStep,
+    /// A delay can't be stored in the binary format.
+    DelayOutOfRange,
+    /// The output buffer is too small.
+    BufferTooSmall,
+}
+
+impl ScriptError {
+    const fn new(kind: ScriptErrorKind, offset: usize) -> Self {
+        Self { kind, offset }
+    }
+}
+
+/// Init script which is loaded at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Script<'a> {
+    /// Script in the text format.
+    Text(&'a str),
+    /// Script in the binary format.
+    Binary(&'a [u8]),
+}
+
+impl<'a> Script<'a> {
+    /// Returns an iterator over the steps of the script.
+    ///
+    /// The iteration stops after the first error.
+    pub fn steps(&self) -> ScriptSteps<'a> {
+        let (data, text) = match *self {
+            Script::Text(text) => (text.as_bytes(), true),
+            Script::Binary(data) => (data, false),
+        };
+        ScriptSteps { data, text, pos: 0 }
+    }
+
+    /// Checks the entire script for errors.
+    pub fn validate(&self) -> Result<(), ScriptError> {
+        self.steps().try_for_each(|step| step.map(drop))
+    }
+
+    /// Encodes the script in the binary format.
+    ///
+    /// Delays which follow a command are merged into the command. Returns the
+    /// number of bytes written to `buffer`.
+    pub fn to_binary(&self, buffer: &mut [u8]) -> Result<usize, ScriptError> {
+        let mut len = 0;
+        // Position of the last header, if the last step can take a delay.
+        let mut last_header = None;
+
+        for (step, offset) in self.steps().with_offsets() {
+            let step = step?;
+            let error = |kind| ScriptError::new(kind, offset);
+            let mut push = |len: &mut usize, bytes: &[u8]| -> Result<(), ScriptError> {
+                let end = *len + bytes.len();
+                buffer
+                    .get_mut(*len..end)
+                    .ok_or(error(ScriptErrorKind::BufferTooSmall))?
+                    .copy_from_slice(bytes);
+                *len = end;
+                Ok(())
+            };
+
+            let delay_ms = match step.delay_us {
+                us if us % 1000 != 0 => return Err(error(ScriptErrorKind::DelayOutOfRange)),
+                us => {
+                    u16::try_from(us / 1000).map_err(|_| error(ScriptErrorKind::DelayOutOfRange))?
+                }
+            };
+
+            if let (Instruction::Delay, Some(header)) = (step.instruction, last_header) {
+                push(&mut len, &delay_ms.to_le_bytes())?;
+                buffer[header] |= DELAY_FLAG;
+                last_header = None;
+                continue;
+            }
+
+            let header = len + 1;
+            match step.instruction {
+                Instruction::Command(instruction) => {
+                    push(&mut len, &[instruction, step.params().len() as u8])?;
+                    push(&mut len, step.params())?;
+                }
+                Instruction::Delay => push(&mut len, &[0x00, SPECIAL])?,
+                Instruction::AddressMode => push(&mut len, &[0x01, SPECIAL])?,
+                Instruction::InvertMode => push(&mut len, &[0x02, SPECIAL])?,
+            }
+
+            if delay_ms > 0 {
+                push(&mut len, &delay_ms.to_le_bytes())?;
+                buffer[header] |= DELAY_FLAG;
+                last_header = None;
+            } else {
+                last_header = Some(header);
+            }
+        }
+
+        Ok(len)
+    }
+}
+
+/// Single step of a [`Script`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptStep {
+    /// Instruction which is sent.
+    pub instruction: Instruction,
+    params: [u8; MAX_SCRIPT_PARAMS],
+    params_len: usize,
+    /// Delay after the instruction in microseconds.
+    pub delay_us: u32,
+}
+
+impl ScriptStep {
+    fn new(instruction: Instruction) -> Self {
+        Self {
+            instruction,
+            params: [0; MAX_SCRIPT_PARAMS],
+            params_len: 0,
+            delay_us: 0,
+        }
+    }
+
+    /// Returns the parameters of a command.
+    pub fn params(&self) -> &[u8] {
+        &self.params[..self.params_len]
+    }
+}
+
+/// Iterator over the steps of a [`Script`].
+#[derive(Debug, Clone)]
+pub struct ScriptSteps<'a> {
+    data: &'a [u8],
+    text: bool,
+    pos: usize,
+}
+
+impl<'a> ScriptSteps<'a> {
+    /// Returns the steps together with their byte offsets.
+    fn with_offsets(self) -> impl Iterator<Item = (Result<ScriptStep, ScriptError>, usize)> + 'a {
+        let mut steps = self;
+        core::iter::from_fn(move || {
+            if steps.text {
+                steps.skip_ignored();
+            }
+            let offset = steps.pos;
+            steps.next().map(|step| (step, offset))
+        })
+    }
+
+    /// Skips separators and comments in a text script.
+    fn skip_ignored(&mut self) {
+        let data = self.data;
+        loop {
+            match &data[self.pos..] {
+                [b'/', b'/', ..] | [b'#', ..] => {
+                    while self.pos < data.len() && data[self.pos] != b'\n' {
+                        self.pos += 1;
+                    }
+                }
+                [byte, ..] if is_separator(*byte) => self.pos += 1,
+                _ => break,
+            }
+        }
+    }
+
+    /// Returns the next text token and its offset.
+    fn token(&mut self) -> Option<(&'a [u8], usize)> {
+        self.skip_ignored();
+        let data = self.data;
+        if self.pos == data.len() {
+            return None;
+        }
+
+        let start = self.pos;
+        while self.pos < data.len() && !is_separator(data[self.pos]) && data[self.pos] != b'#' {
+            self.pos += 1;
+        }
+        Some((&data[start..self.pos], start))
+    }
+
+    /// Returns the next text token as a number and its offset.
+    fn number(&mut self, max: u32) -> Result<(u32, usize), ScriptError> {
+        let end = self.data.len();
+        let (token, offset) = self
+            .token()
+            .ok_or(ScriptError::new(ScriptErrorKind::UnexpectedEnd, end))?;
+        let value = parse_number(token).map_err(|kind| ScriptError::new(kind, offset))?;
+        if value > max {
+            return Err(ScriptError::new(ScriptErrorKind::NumberOutOfRange, offset));
+        }
+        Ok((value, offset))
+    }
+
+    fn next_text(&mut self) -> Option<Result<ScriptStep, ScriptError>> {
+        let (token, offset) = self.token()?;
+        let step = if token.eq_ignore_ascii_case(b"delay") {
+            self.number(u32::MAX / 1000).map(|(ms, _)| ScriptStep {
+                delay_us: ms * 1000,
+                ..ScriptStep::new(Instruction::Delay)
+            })
+        } else if token.eq_ignore_ascii_case(b"madctl") {
+            Ok(ScriptStep::new(Instruction::AddressMode))
+        } else if token.eq_ignore_ascii_case(b"invert") {
+            Ok(ScriptStep::new(Instruction::InvertMode))
+        } else {
+            self.pos = offset;
+            self.next_text_command()
+        };
+        Some(step)
+    }
+
+    fn next_text_command(&mut self) -> Result<ScriptStep, ScriptError> {
+        let (instruction, _) = self.number(0xFF)?;
+        let (count, count_offset) = self.number(0xFF)?;
+        let count = count as usize;
+        if count > MAX_SCRIPT_PARAMS {
+            return Err(ScriptError::new(
+                ScriptErrorKind::TooManyParams,
+                count_offset,
+            ));
+        }
+
+        let mut step = ScriptStep::new(Instruction::Command(instruction as u8));
+        for param in &mut step.params[..count] {
+            *param = self.number(0xFF)?.0 as u8;
+        }
+        step.params_len = count;
+        Ok(step)
+    }
+
+    fn next_binary(&mut self) -> Option<Result<ScriptStep, ScriptError>> {
+        let offset = self.pos;
+        let rest = self.data.get(offset..).filter(|rest| !rest.is_empty())?;
+        let end = ScriptError::new(ScriptErrorKind::UnexpectedEnd, self.data.len());
+
+        let [instruction, header, ..] = *rest else {
+            return Some(Err(end));
+        };
+        let count = header & !DELAY_FLAG;
+        let mut len = 2;
+
+        let mut step = if count == SPECIAL {
+            let instruction = match instruction {
+                0x00 => Instruction::Delay,
+                0x01 => Instruction::AddressMode,
+                0x02 => Instruction::InvertMode,
+                _ => {
+                    return Some(Err(ScriptError::new(
+                        ScriptErrorKind::UnknownSpecialStep,
+                        offset,
+                    )))
+                }
+            };
+            ScriptStep::new(instruction)
+        } else {
+            let count = usize::from(count);
+            let Some(params) = rest.get(len..len + count) else {
+                return Some(Err(end));
+            };
+            let mut step = ScriptStep::new(Instruction::Command(instruction));
+            step.params[..count].copy_from_slice(params);
+            step.params_len = count;
+            len += count;
+            step
+        };
+
+        if header & DELAY_FLAG != 0 {
+            let Some(&[low, high]) = rest.get(len..len + 2) else {
+                return Some(Err(end));
+            };
+            step.delay_us = u32::from(u16::from_le_bytes([low, high])) * 1000;
+            len += 2;
+        }
+
+        self.pos += len;
+        Some(Ok(step))
+    }
+}
+
+impl Iterator for ScriptSteps<'_> {
+    type Item = Result<ScriptStep, ScriptError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let step = if self.text {
+            self.next_text()
+        } else {
+            self.next_binary()
+        };
+        if let Some(Err(_)) = step {
+            self.pos = self.data.len();
+        }
+        step
+    }
+}
+
+fn is_separator(byte: u8) -> bool {
+    byte.is_ascii_whitespace() || byte == b',' || byte == b';'
+}
+
+fn parse_number(token: &[u8]) -> Result<u32, ScriptErrorKind> {
+    let (digits, radix) = match token {
+        [b'0', b'x' | b'X', digits @ ..] => (digits, 16),
+        [b'0', b'b' | b'B', digits @ ..] => (digits, 2),
+        digits => (digits, 10),
+    };
+    if !digits
+        .iter()
+        .all(|digit| char::from(*digit).is_digit(radix))
+    {
+        return Err(ScriptErrorKind::InvalidToken);
+    }
+    let digits = core::str::from_utf8(digits).map_err(|_| ScriptErrorKind::InvalidToken)?;
+    u32::from_str_radix(digits, radix).map_err(|error| match error.kind() {
+        core::num::IntErrorKind::PosOverflow => ScriptErrorKind::NumberOutOfRange,
+        _ => ScriptErrorKind::InvalidToken,
+    })
+}
+
+/// Runs a script which was loaded at runtime.
+///
+/// The script is validated before any command is sent. Invalid scripts
+/// return [`ConfigurationError::InvalidInitScript`]. Returns the MADCTL value
+/// for the display options.
+pub async fn run_script<DI, DELAY>(
+    di: &mut DI,
+    delay: &mut DELAY,
+    options: &ModelOptions,
+    script: Script<'_>,
+) -> Result<SetAddressMode, ModelInitError<DI::Error>>
+where
+    DI: Interface,
+    DELAY: DelayNs,
+{
+    let invalid = |_| ModelInitError::InvalidConfiguration(ConfigurationError::InvalidInitScript);
+    script.validate().map_err(invalid)?;
+
+    for step in script.steps() {
+        let step = step.map_err(invalid)?;
+        execute_step(
+            di,
+            delay,
+            options,
+            step.instruction,
+            step.params(),
+            step.delay_us,
+        )
+        .await?;
+    }
+
+    Ok(SetAddressMode::from(options))
+}
+
+/// Display model which is initialized by a script loaded at runtime.
+///
+/// This allows the init sequence of a panel to be stored in a configuration,
+/// for example in flash, and to be updated without rebuilding the firmware.
+/// The framebuffer size is set by the `WIDTH` and `HEIGHT` parameters. All
+/// other [`Model`] methods use the default MIPI DCS implementations. The model
+/// accepts every interface kind.
+///
+/// ```
+/// use mipidsi::models::{Script, ScriptModel};
+///
+/// let text = "0x11, 0, delay 120; madctl; invert; 0x3A, 1, 0x55; 0x29, 0, delay 120";
+/// let model = ScriptModel::<240, 320>::new(Script::Text(text)).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ScriptModel<'a, const WIDTH: u16, const HEIGHT: u16> {
+    script: Script<'a>,
+}
+
+impl<'a, const WIDTH: u16, const HEIGHT: u16> ScriptModel<'a, WIDTH, HEIGHT> {
+    /// Creates a new model, after checking the script for errors.
+    pub fn new(script: Script<'a>) -> Result<Self, ScriptError> {
+        script.validate()?;
+        Ok(Self { script })
+    }
+
+    /// Returns the init script.
+    pub fn script(&self) -> Script<'a> {
+        self.script
+    }
+}
+
+impl<const WIDTH: u16, const HEIGHT: u16> Model for ScriptModel<'_, WIDTH, HEIGHT> {
+    const FRAMEBUFFER_SIZE: (u16, u16) = (WIDTH, HEIGHT);
+    const CACHE_ADDRESS_WINDOW: bool = true;
+
+    async fn init<DELAY, DI>(
+        &mut self,
+        di: &mut DI,
+        delay: &mut DELAY,
+        options: &ModelOptions,
+    ) -> Result<SetAddressMode, ModelInitError<DI::Error>>
+    where
+        DELAY: DelayNs,
+        DI: Interface,
+    {
+        run_script(di, delay, options, self.script).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use embassy_futures::block_on;
+
+    use crate::{
+        options::ColorInversion,
+        testing::{MockDelay, MockInterface},
+    };
+
+    use super::*;
+
+    const TEXT: &str = "
+        // vendor init code
+        0x11, 0, delay 120
+        0xB1,3,0x05,0x3A,0x3A   # frame rate
+        MADCTL; invert
+        58, 1, 0b0101_0101
+        delay 5 delay 7
+        0x29 0
+    ";
+
+    fn steps(script: Script<'_>) -> Vec<(Instruction, Vec<u8>, u32)> {
+        script
+            .steps()
+            .map(|step| {
+                let step = step.unwrap();
+                (step.instruction, step.params().to_vec(), step.delay_us)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_text() {
+        assert_eq!(
+            parse_number(b"0b0101_0101"),
+            Err(ScriptErrorKind::InvalidToken)
+        );
+        let text = TEXT.replace("0b0101_0101", "0b01010101");
+        assert_eq!(
+            steps(Script::Text(&text)),
+            [
+                (Instruction::Command(0x11), std::vec![], 0),
+                (Instruction::Delay, std::vec![], 120_000),
+                (Instruction::Command(0xB1), std::vec![0x05, 0x3A, 0x3A], 0),
+                (Instruction::AddressMode, std::vec![], 0),
+                (Instruction::InvertMode, std::vec![], 0),
+                (Instruction::Command(0x3A), std::vec![0x55], 0),
+                (Instruction::Delay, std::vec![], 5_000),
+                (Instruction::Delay, std::vec![], 7_000),
+                (Instruction::Command(0x29), std::vec![], 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_text_errors() {
+        let error = |text, kind, offset| {
+            assert_eq!(
+                Script::Text(text).validate(),
+                Err(ScriptError { kind, offset }),
+                "{}",
+                text
+            );
+        };
+        error("0x11, 0, 0xB1, 2, 1", ScriptErrorKind::UnexpectedEnd, 19);
+        error("0x11, 0, sleep 5", ScriptErrorKind::InvalidToken, 9);
+        error("0x100, 0", ScriptErrorKind::NumberOutOfRange, 0);
+        error("0x11, 0x1G", ScriptErrorKind::InvalidToken, 6);
+        error("0x11, 127", ScriptErrorKind::TooManyParams, 6);
+        error("delay 99999999999", ScriptErrorKind::NumberOutOfRange, 6);
+        error("delay", ScriptErrorKind::UnexpectedEnd, 5);
+        assert_eq!(Script::Text(" // empty\n").validate(), Ok(()));
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let text = TEXT.replace("0b0101_0101", "0x55");
+        let mut buffer = [0; 64];
+        let len = Script::Text(&text).to_binary(&mut buffer).unwrap();
+        assert_eq!(
+            &buffer[..len],
+            &[
+                0x11, 0x80, 120, 0, // exit sleep mode with delay
+                0xB1, 3, 0x05, 0x3A, 0x3A, // frame rate
+                0x01, 0x7F, 0x02, 0x7F, // MADCTL, inversion
+                0x3A, 0x81, 0x55, 5, 0, // pixel format with delay
+                0x00, 0xFF, 7, 0, // delay
+                0x29, 0x00, // display on
+            ]
+        );
+
+        // Merged delays produce the same commands.
+        let merged = steps(Script::Binary(&buffer[..len]));
+        let commands = merged
+            .iter()
+            .map(|(instruction, params, _)| (*instruction, params.clone()))
+            .filter(|(instruction, _)| *instruction != Instruction::Delay)
+            .collect::<Vec<_>>();
+        assert_eq!(commands.len(), 6);
+        assert_eq!(merged[0].2, 120_000);
+        assert_eq!(
+            merged[4],
+            (Instruction::Command(0x3A), std::vec![0x55], 5_000)
+        );
+
+        assert_eq!(
+            Script::Text(&text).to_binary(&mut buffer[..10]),
+            Err(ScriptError {
+                kind: ScriptErrorKind::BufferTooSmall,
+                offset: text.find("MADCTL").unwrap(),
+            })
+        );
+        assert_eq!(
+            Script::Text("delay 70000").to_binary(&mut buffer),
+            Err(ScriptError {
+                kind: ScriptErrorKind::DelayOutOfRange,
+                offset: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn reports_binary_errors() {
+        let error = |data: &[u8], kind, offset| {
+            assert_eq!(
+                Script::Binary(data).validate(),
+                Err(ScriptError { kind, offset })
+            );
+        };
+        error(&[0x11], ScriptErrorKind::UnexpectedEnd, 1);
+        error(
+            &[0x11, 0x00, 0xB1, 0x02, 0x05],
+            ScriptErrorKind::UnexpectedEnd,
+            5,
+        );
+        error(&[0x11, 0x80, 0x05], ScriptErrorKind::UnexpectedEnd, 3);
+        error(
+            &[0x11, 0x00, 0x03, 0x7F],
+            ScriptErrorKind::UnknownSpecialStep,
+            2,
+        );
+    }
+
+    #[test]
+    fn invalid_scripts_send_nothing() {
+        let mut options = ModelOptions::with_all((10, 10), (0, 0));
+        let mut di = MockInterface::new();
+        let mut delay = MockDelay::new();
+
+        let result = block_on(run_script(
+            &mut di,
+            &mut delay,
+            &options,
+            Script::Text("0x11, 0, delay 120, 0x29"),
+        ));
+        assert!(matches!(
+            result,
+            Err(ModelInitError::InvalidConfiguration(
+                ConfigurationError::InvalidInitScript
+            ))
+        ));
+        di.assert_commands(&[]);
+        delay.assert_delays_us(&[]);
+
+        options.invert_colors = ColorInversion::Inverted;
+        block_on(run_script(
+            &mut di,
+            &mut delay,
+            &options,
+            Script::Binary(&[0x11, 0x80, 120, 0, 0x02, 0x7F]),
+        ))
+        .unwrap();
+        di.assert_commands(&[(0x11, &[]), (0x21, &[])]);
+        delay.assert_delays_us(&[120_000]);
+    }
+}
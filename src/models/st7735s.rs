@@ -1,21 +1,52 @@
 use embedded_hal_async::delay::DelayNs;
 
 use crate::{
-    dcs::{
-        BitsPerPixel, ExitSleepMode, InterfaceExt, PixelFormat, SetAddressMode, SetDisplayOn,
-        SetInvertMode, SetPixelFormat,
-    },
+    dcs::SetAddressMode,
     interface::{Interface, InterfaceKind},
-    models::{Model, ModelInitError},
+    models::{run_init_script, InitStep, Model, ModelInitError},
     options::ModelOptions,
     ConfigurationError,
 };
 
+const INIT_SCRIPT: &[InitStep] = &[
+    InitStep::delay_ms(200),
+    InitStep::command(0x11, &[]).with_delay_ms(120), // exit sleep mode
+    InitStep::invert_mode(),
+    InitStep::command(0xB1, &[0x05, 0x3A, 0x3A]), // set frame rate
+    InitStep::command(0xB2, &[0x05, 0x3A, 0x3A]), // set frame rate
+    InitStep::command(0xB3, &[0x05, 0x3A, 0x3A, 0x05, 0x3A, 0x3A]), // set frame rate
+    InitStep::command(0xB4, &[0b0000_0011]),      // set inversion control
+    InitStep::command(0xC0, &[0x62, 0x02, 0x04]), // set power control 1
+    InitStep::command(0xC1, &[0xC0]),             // set power control 2
+    InitStep::command(0xC2, &[0x0D, 0x00]),       // set power control 3
+    InitStep::command(0xC3, &[0x8D, 0x6A]),       // set power control 4
+    InitStep::command(0xC4, &[0x8D, 0xEE]),       // set power control 5
+    InitStep::command(0xC5, &[0x0E]),             // set VCOM control 1
+    InitStep::command(
+        0xE0,
+        &[
+            0x10, 0x0E, 0x02, 0x03, 0x0E, 0x07, 0x02, 0x07, 0x0A, 0x12, 0x27, 0x37, 0x00, 0x0D,
+            0x0E, 0x10,
+        ],
+    ), // set GAMMA +Polarity characteristics
+    InitStep::command(
+        0xE1,
+        &[
+            0x10, 0x0E, 0x03, 0x03, 0x0F, 0x06, 0x02, 0x08, 0x0A, 0x13, 0x26, 0x36, 0x00, 0x0D,
+            0x0E, 0x10,
+        ],
+    ), // set GAMMA -Polarity characteristics
+    InitStep::command(0x3A, &[0x55]),             // 16 bits per pixel
+    InitStep::address_mode(),
+    InitStep::command(0x29, &[]), // display on
+];
+
 /// ST7735s display in Rgb565 color mode.
 pub struct ST7735s;
 
 impl Model for ST7735s {
     const FRAMEBUFFER_SIZE: (u16, u16) = (132, 162);
+    const CACHE_ADDRESS_WINDOW: bool = true;
 
     async fn init<DELAY, DI>(
         &mut self,
@@ -36,49 +67,65 @@ impl Model for ST7735s {
             ));
         }
 
-        let madctl = SetAddressMode::from(options);
+        run_init_script(di, delay, options, INIT_SCRIPT)
+            .await
+            .map_err(Into::into)
+    }
+}
 
-        delay.delay_us(200_000).await;
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
 
-        di.write_command(ExitSleepMode).await?; // turn off sleep
-        delay.delay_us(120_000).await;
+    use crate::testing::{
+        assert_display_timing, MockDelay, MockInterface, Serial4Line, TimingRules,
+    };
 
-        di.write_command(SetInvertMode::new(options.invert_colors))
-            .await?; // set color inversion
-        di.write_raw(0xB1, &[0x05, 0x3A, 0x3A]).await?; // set frame rate
-        di.write_raw(0xB2, &[0x05, 0x3A, 0x3A]).await?; // set frame rate
-        di.write_raw(0xB3, &[0x05, 0x3A, 0x3A, 0x05, 0x3A, 0x3A])
-            .await?; // set frame rate
-        di.write_raw(0xB4, &[0b0000_0011]).await?; // set inversion control
-        di.write_raw(0xC0, &[0x62, 0x02, 0x04]).await?; // set power control 1
-        di.write_raw(0xC1, &[0xC0]).await?; // set power control 2
-        di.write_raw(0xC2, &[0x0D, 0x00]).await?; // set power control 3
-        di.write_raw(0xC3, &[0x8D, 0x6A]).await?; // set power control 4
-        di.write_raw(0xC4, &[0x8D, 0xEE]).await?; // set power control 5
-        di.write_raw(0xC5, &[0x0E]).await?; // set VCOM control 1
-        di.write_raw(
-            0xE0,
-            &[
-                0x10, 0x0E, 0x02, 0x03, 0x0E, 0x07, 0x02, 0x07, 0x0A, 0x12, 0x27, 0x37, 0x00, 0x0D,
-                0x0E, 0x10,
-            ],
-        )
-        .await?; // set GAMMA +Polarity characteristics
-        di.write_raw(
-            0xE1,
-            &[
-                0x10, 0x0E, 0x03, 0x03, 0x0F, 0x06, 0x02, 0x08, 0x0A, 0x13, 0x26, 0x36, 0x00, 0x0D,
-                0x0E, 0x10,
-            ],
-        )
-        .await?; // set GAMMA -Polarity characteristics
+    use super::*;
 
-        let pf_cmd = SetPixelFormat::new(PixelFormat::with_all(BitsPerPixel::Sixteen));
-        di.write_command(pf_cmd).await?;
+    #[test]
+    fn init_sequence() {
+        let mut di = MockInterface::new();
+        let mut delay = MockDelay::new();
+        let options = ModelOptions::full_size::<ST7735s>();
+        block_on(ST7735s.init(&mut di, &mut delay, &options)).unwrap();
 
-        di.write_command(madctl).await?; // set memory data access control, Top -> Bottom, RGB, Left -> Right
-        di.write_command(SetDisplayOn).await?; // turn on display
+        di.assert_commands(&[
+            (0x11, &[]),
+            (0x20, &[]),
+            (0xB1, &[0x05, 0x3A, 0x3A]),
+            (0xB2, &[0x05, 0x3A, 0x3A]),
+            (0xB3, &[0x05, 0x3A, 0x3A, 0x05, 0x3A, 0x3A]),
+            (0xB4, &[0x03]),
+            (0xC0, &[0x62, 0x02, 0x04]),
+            (0xC1, &[0xC0]),
+            (0xC2, &[0x0D, 0x00]),
+            (0xC3, &[0x8D, 0x6A]),
+            (0xC4, &[0x8D, 0xEE]),
+            (0xC5, &[0x0E]),
+            (
+                0xE0,
+                &[
+                    0x10, 0x0E, 0x02, 0x03, 0x0E, 0x07, 0x02, 0x07, 0x0A, 0x12, 0x27, 0x37, 0x00,
+                    0x0D, 0x0E, 0x10,
+                ],
+            ),
+            (
+                0xE1,
+                &[
+                    0x10, 0x0E, 0x03, 0x03, 0x0F, 0x06, 0x02, 0x08, 0x0A, 0x13, 0x26, 0x36, 0x00,
+                    0x0D, 0x0E, 0x10,
+                ],
+            ),
+            (0x3A, &[0x55]),
+            (0x36, &[0x00]),
+            (0x29, &[]),
+        ]);
+        delay.assert_delays_us(&[200_000, 120_000]);
+    }
 
-        Ok(madctl)
+    #[test]
+    fn timing() {
+        assert_display_timing::<Serial4Line, _>(|| ST7735s, &TimingRules::MIPI_DCS);
     }
 }
@@ -1,21 +1,30 @@
 use embedded_hal_async::delay::DelayNs;
 
 use crate::{
-    dcs::{
-        BitsPerPixel, EnterNormalMode, ExitSleepMode, InterfaceExt, PixelFormat, SetAddressMode,
-        SetDisplayOn, SetInvertMode, SetPixelFormat,
-    },
+    dcs::SetAddressMode,
     interface::{Interface, InterfaceKind},
-    models::{Model, ModelInitError},
+    models::{run_init_script, InitStep, Model, ModelInitError},
     options::ModelOptions,
     ConfigurationError,
 };
 
+const INIT_SCRIPT: &[InitStep] = &[
+    InitStep::delay_ms(150),
+    InitStep::command(0x11, &[]).with_delay_ms(10), // exit sleep mode
+    InitStep::address_mode(),
+    InitStep::invert_mode(),
+    InitStep::command(0x3A, &[0x55]).with_delay_ms(10), // 16 bits per pixel
+    InitStep::command(0x13, &[]).with_delay_ms(10),     // enter normal mode
+    // DISPON requires some time otherwise we risk SPI data issues
+    InitStep::command(0x29, &[]).with_delay_ms(120), // display on
+];
+
 /// ST7789 display in Rgb565 color mode.
 pub struct ST7789;
 
 impl Model for ST7789 {
     const FRAMEBUFFER_SIZE: (u16, u16) = (240, 320);
+    const CACHE_ADDRESS_WINDOW: bool = true;
 
     async fn init<DELAY, DI>(
         &mut self,
@@ -36,29 +45,42 @@ impl Model for ST7789 {
             ));
         }
 
-        let madctl = SetAddressMode::from(options);
-
-        delay.delay_us(150_000).await;
+        run_init_script(di, delay, options, INIT_SCRIPT)
+            .await
+            .map_err(Into::into)
+    }
+}
 
-        di.write_command(ExitSleepMode).await?;
-        delay.delay_us(10_000).await;
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
 
-        // set hw scroll area based on framebuffer size
-        di.write_command(madctl).await?;
+    use crate::testing::{
+        assert_display_timing, MockDelay, MockInterface, Serial4Line, TimingRules,
+    };
 
-        di.write_command(SetInvertMode::new(options.invert_colors))
-            .await?;
+    use super::*;
 
-        let pf_cmd = SetPixelFormat::new(PixelFormat::with_all(BitsPerPixel::Sixteen));
-        di.write_command(pf_cmd).await?;
-        delay.delay_us(10_000).await;
-        di.write_command(EnterNormalMode).await?;
-        delay.delay_us(10_000).await;
-        di.write_command(SetDisplayOn).await?;
+    #[test]
+    fn init_sequence() {
+        let mut di = MockInterface::new();
+        let mut delay = MockDelay::new();
+        let options = ModelOptions::full_size::<ST7789>();
+        block_on(ST7789.init(&mut di, &mut delay, &options)).unwrap();
 
-        // DISPON requires some time otherwise we risk SPI data issues
-        delay.delay_us(120_000).await;
+        di.assert_commands(&[
+            (0x11, &[]),
+            (0x36, &[0x00]),
+            (0x20, &[]),
+            (0x3A, &[0x55]),
+            (0x13, &[]),
+            (0x29, &[]),
+        ]);
+        delay.assert_delays_us(&[150_000, 10_000, 10_000, 10_000, 120_000]);
+    }
 
-        Ok(madctl)
+    #[test]
+    fn timing() {
+        assert_display_timing::<Serial4Line, _>(|| ST7789, &TimingRules::ST7789);
     }
 }
@@ -13,6 +13,7 @@ pub struct ST7796;
 
 impl Model for ST7796 {
     const FRAMEBUFFER_SIZE: (u16, u16) = (320, 480);
+    const CACHE_ADDRESS_WINDOW: bool = true;
 
     async fn init<DELAY, DI>(
         &mut self,
@@ -36,3 +37,37 @@ impl Model for ST7796 {
         super::ST7789.init(di, delay, options).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+
+    use crate::testing::{
+        assert_display_timing, MockDelay, MockInterface, Serial4Line, TimingRules,
+    };
+
+    use super::*;
+
+    #[test]
+    fn init_sequence() {
+        let mut di = MockInterface::new();
+        let mut delay = MockDelay::new();
+        let options = ModelOptions::full_size::<ST7796>();
+        block_on(ST7796.init(&mut di, &mut delay, &options)).unwrap();
+
+        di.assert_commands(&[
+            (0x11, &[]),
+            (0x36, &[0x00]),
+            (0x20, &[]),
+            (0x3A, &[0x55]),
+            (0x13, &[]),
+            (0x29, &[]),
+        ]);
+        delay.assert_delays_us(&[150_000, 10_000, 10_000, 10_000, 120_000]);
+    }
+
+    #[test]
+    fn timing() {
+        assert_display_timing::<Serial4Line, _>(|| ST7796, &TimingRules::ST7789);
+    }
+}
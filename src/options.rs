@@ -64,35 +64,25 @@ impl ModelOptions {
 }
 
 /// Color inversion.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
 pub enum ColorInversion {
     /// Normal colors.
+    #[default]
     Normal,
     /// Inverted colors.
     Inverted,
 }
 
-impl Default for ColorInversion {
-    fn default() -> Self {
-        Self::Normal
-    }
-}
-
 /// Vertical refresh order.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
 pub enum VerticalRefreshOrder {
     /// Refresh from top to bottom.
+    #[default]
     TopToBottom,
     /// Refresh from bottom to top.
     BottomToTop,
 }
 
-impl Default for VerticalRefreshOrder {
-    fn default() -> Self {
-        Self::TopToBottom
-    }
-}
-
 impl VerticalRefreshOrder {
     /// Returns the opposite refresh order.
     #[must_use]
@@ -105,20 +95,15 @@ impl VerticalRefreshOrder {
 }
 
 /// Horizontal refresh order.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
 pub enum HorizontalRefreshOrder {
     /// Refresh from left to right.
+    #[default]
     LeftToRight,
     /// Refresh from right to left.
     RightToLeft,
 }
 
-impl Default for HorizontalRefreshOrder {
-    fn default() -> Self {
-        Self::LeftToRight
-    }
-}
-
 impl HorizontalRefreshOrder {
     /// Returns the opposite refresh order.
     #[must_use]
@@ -181,16 +166,11 @@ pub enum TearingEffect {
 }
 
 /// Subpixel order.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ColorOrder {
     /// RGB subpixel order.
+    #[default]
     Rgb,
     /// BGR subpixel order.
     Bgr,
 }
-
-impl Default for ColorOrder {
-    fn default() -> Self {
-        Self::Rgb
-    }
-}
@@ -6,12 +6,29 @@ use embedded_graphics::{
     geometry::{Dimensions, OriginDimensions},
     pixelcolor::raw::RawData,
     pixelcolor::PixelColor,
+    pixelcolor::Rgb888,
     pixelcolor::RgbColor,
-    prelude::Size,
+    prelude::{Point, Size},
     primitives::Rectangle,
     Pixel,
 };
 
+use crate::options::{MemoryMapping, Orientation};
+
+mod band;
+pub use band::*;
+mod blend;
+pub use blend::*;
+mod diff;
+pub use diff::*;
+mod dirty;
+pub use dirty::*;
+mod indexed;
+pub use indexed::*;
+mod scaled;
+mod view;
+pub use view::*;
+
 // --- Helper Trait for Color to Raw Byte Conversion ---
 pub trait IntoRawBytes<const N: usize>: PixelColor {
     fn into_raw_bytes(self) -> [u8; N];
@@ -31,6 +48,135 @@ impl IntoRawBytes<3> for embedded_graphics::pixelcolor::Rgb888 {
     }
 }
 
+impl IntoRawBytes<2> for embedded_graphics::pixelcolor::Bgr565 {
+    fn into_raw_bytes(self) -> [u8; 2] {
+        use embedded_graphics::pixelcolor::raw::RawU16;
+        RawU16::from(self).into_inner().to_be_bytes()
+    }
+}
+
+impl IntoRawBytes<3> for embedded_graphics::pixelcolor::Bgr888 {
+    fn into_raw_bytes(self) -> [u8; 3] {
+        [self.b(), self.g(), self.r()]
+    }
+}
+
+/// 18 bit colors are sent as 3 bytes with the 6 color bits left aligned.
+impl IntoRawBytes<3> for embedded_graphics::pixelcolor::Rgb666 {
+    fn into_raw_bytes(self) -> [u8; 3] {
+        [self.r() << 2, self.g() << 2, self.b() << 2]
+    }
+}
+
+/// Expanded to the 16 bit Rgb565 format.
+impl IntoRawBytes<2> for embedded_graphics::pixelcolor::Gray8 {
+    fn into_raw_bytes(self) -> [u8; 2] {
+        embedded_graphics::pixelcolor::Rgb565::from(self).into_raw_bytes()
+    }
+}
+
+/// Expanded to 3 bytes, which are valid for the 18 bit and the 24 bit format.
+impl IntoRawBytes<3> for embedded_graphics::pixelcolor::Gray8 {
+    fn into_raw_bytes(self) -> [u8; 3] {
+        embedded_graphics::pixelcolor::Rgb888::from(self).into_raw_bytes()
+    }
+}
+
+/// Expanded to black and white in the 16 bit Rgb565 format.
+impl IntoRawBytes<2> for embedded_graphics::pixelcolor::BinaryColor {
+    fn into_raw_bytes(self) -> [u8; 2] {
+        embedded_graphics::pixelcolor::Rgb565::from(self).into_raw_bytes()
+    }
+}
+
+/// Expanded to black and white, which is valid for the 18 bit and the 24 bit format.
+impl IntoRawBytes<3> for embedded_graphics::pixelcolor::BinaryColor {
+    fn into_raw_bytes(self) -> [u8; 3] {
+        embedded_graphics::pixelcolor::Rgb888::from(self).into_raw_bytes()
+    }
+}
+
+/// Conversion from the raw bytes created by [`IntoRawBytes`] back to a color.
+///
+/// Used to read back pixels from a [`RawFrameBuf`], for example for blending.
+pub trait FromRawBytes<const N: usize>: IntoRawBytes<N> {
+    fn from_raw_bytes(bytes: [u8; N]) -> Self;
+}
+
+impl FromRawBytes<2> for embedded_graphics::pixelcolor::Rgb565 {
+    fn from_raw_bytes(bytes: [u8; 2]) -> Self {
+        use embedded_graphics::pixelcolor::raw::RawU16;
+        RawU16::new(u16::from_be_bytes(bytes)).into()
+    }
+}
+
+impl FromRawBytes<3> for embedded_graphics::pixelcolor::Rgb888 {
+    fn from_raw_bytes([r, g, b]: [u8; 3]) -> Self {
+        Self::new(r, g, b)
+    }
+}
+
+impl FromRawBytes<2> for embedded_graphics::pixelcolor::Bgr565 {
+    fn from_raw_bytes(bytes: [u8; 2]) -> Self {
+        use embedded_graphics::pixelcolor::raw::RawU16;
+        RawU16::new(u16::from_be_bytes(bytes)).into()
+    }
+}
+
+impl FromRawBytes<3> for embedded_graphics::pixelcolor::Bgr888 {
+    fn from_raw_bytes([b, g, r]: [u8; 3]) -> Self {
+        Self::new(r, g, b)
+    }
+}
+
+impl FromRawBytes<3> for embedded_graphics::pixelcolor::Rgb666 {
+    fn from_raw_bytes([r, g, b]: [u8; 3]) -> Self {
+        Self::new(r >> 2, g >> 2, b >> 2)
+    }
+}
+
+impl FromRawBytes<2> for embedded_graphics::pixelcolor::Gray8 {
+    fn from_raw_bytes(bytes: [u8; 2]) -> Self {
+        embedded_graphics::pixelcolor::Rgb565::from_raw_bytes(bytes).into()
+    }
+}
+
+impl FromRawBytes<3> for embedded_graphics::pixelcolor::Gray8 {
+    fn from_raw_bytes(bytes: [u8; 3]) -> Self {
+        Rgb888::from_raw_bytes(bytes).into()
+    }
+}
+
+impl FromRawBytes<2> for embedded_graphics::pixelcolor::BinaryColor {
+    fn from_raw_bytes(bytes: [u8; 2]) -> Self {
+        embedded_graphics::pixelcolor::Rgb565::from_raw_bytes(bytes).into()
+    }
+}
+
+impl FromRawBytes<3> for embedded_graphics::pixelcolor::BinaryColor {
+    fn from_raw_bytes(bytes: [u8; 3]) -> Self {
+        Rgb888::from_raw_bytes(bytes).into()
+    }
+}
+
+/// Encodes pixels from `pixels` into `buffer` and returns the number of bytes written.
+///
+/// Only complete pixels are written, the remaining pixels are left in the iterator.
+pub(crate) fn encode_pixels<C, const N: usize>(
+    pixels: &mut impl Iterator<Item = C>,
+    buffer: &mut [u8],
+) -> usize
+where
+    C: IntoRawBytes<N>,
+{
+    let mut len = 0;
+    for (chunk, color) in buffer.chunks_exact_mut(N).zip(pixels) {
+        chunk.copy_from_slice(&color.into_raw_bytes());
+        len += N;
+    }
+    len
+}
+
 // --- Backend Trait for Buffer Flexibility ---
 pub trait RawBufferBackendMut {
     fn as_mut_u8_slice(&mut self) -> &mut [u8];
@@ -38,7 +184,65 @@ pub trait RawBufferBackendMut {
     fn u8_len(&self) -> usize;
 }
 
-impl<'a> RawBufferBackendMut for &'a mut [u8] {
+impl RawBufferBackendMut for &mut [u8] {
+    fn as_mut_u8_slice(&mut self) -> &mut [u8] {
+        self
+    }
+    fn as_u8_slice(&self) -> &[u8] {
+        self
+    }
+    fn u8_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<const S: usize> RawBufferBackendMut for [u8; S] {
+    fn as_mut_u8_slice(&mut self) -> &mut [u8] {
+        self
+    }
+    fn as_u8_slice(&self) -> &[u8] {
+        self
+    }
+    fn u8_len(&self) -> usize {
+        S
+    }
+}
+
+/// The framebuffer uses the current length of the vector, not its capacity.
+/// The vector must be resized to the size of the framebuffer before it is
+/// passed to [`RawFrameBuf::new`], for example with [`heapless::Vec::resize`].
+#[cfg(feature = "heapless")]
+impl<const S: usize> RawBufferBackendMut for heapless::Vec<u8, S> {
+    fn as_mut_u8_slice(&mut self) -> &mut [u8] {
+        self
+    }
+    fn as_u8_slice(&self) -> &[u8] {
+        self
+    }
+    fn u8_len(&self) -> usize {
+        self.len()
+    }
+}
+
+/// The framebuffer uses the current length of the vector, not its capacity.
+/// The vector must be resized to the size of the framebuffer before it is
+/// passed to [`RawFrameBuf::new`], for example with `vec![0; size]` or
+/// [`Vec::resize`](alloc::vec::Vec::resize).
+#[cfg(feature = "alloc")]
+impl RawBufferBackendMut for alloc::vec::Vec<u8> {
+    fn as_mut_u8_slice(&mut self) -> &mut [u8] {
+        self
+    }
+    fn as_u8_slice(&self) -> &[u8] {
+        self
+    }
+    fn u8_len(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl RawBufferBackendMut for alloc::boxed::Box<[u8]> {
     fn as_mut_u8_slice(&mut self) -> &mut [u8] {
         self
     }
@@ -50,6 +254,16 @@ impl<'a> RawBufferBackendMut for &'a mut [u8] {
     }
 }
 
+/// Compile time check for the size of array backed framebuffers.
+struct ArraySize<const S: usize, const W: usize, const H: usize, const N: usize>;
+
+impl<const S: usize, const W: usize, const H: usize, const N: usize> ArraySize<S, W, H, N> {
+    const CHECK: () = assert!(
+        S >= W * H * N,
+        "RawFrameBuf array is too small for the given width and height."
+    );
+}
+
 pub struct RawFrameBuf<C, BUF, const N: usize>
 where
     C: IntoRawBytes<N>,
@@ -58,6 +272,8 @@ where
     buffer: BUF,
     width: usize,
     height: usize,
+    dirty: DirtyRegion,
+    orientation: Orientation,
     _phantom_color: core::marker::PhantomData<C>,
 }
 
@@ -66,6 +282,13 @@ where
     C: IntoRawBytes<N>,
     BUF: RawBufferBackendMut,
 {
+    /// Creates a new framebuffer with `width` x `height` pixels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` is smaller than `width * height * N` bytes. Vector
+    /// backends report their length, not their capacity, which means that an
+    /// empty vector must be resized first.
     pub fn new(buffer: BUF, width: usize, height: usize) -> Self {
         let expected_len = width * height * N;
         assert!(
@@ -78,10 +301,73 @@ where
             buffer,
             width,
             height,
+            dirty: DirtyRegion::new(),
+            orientation: Orientation::new(),
             _phantom_color: core::marker::PhantomData,
         }
     }
 
+    /// Sets the orientation and returns the framebuffer.
+    ///
+    /// See [`set_orientation`](Self::set_orientation).
+    #[must_use]
+    pub fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.set_orientation(orientation);
+        self
+    }
+
+    /// Returns the orientation used to map drawing coordinates to the buffer.
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Sets the orientation used to map drawing coordinates to the buffer.
+    ///
+    /// The buffer always stores pixels in the native scan order of the panel,
+    /// which allows the display to be used without changing its memory access
+    /// order through MADCTL. Drawing operations use logical coordinates,
+    /// which are rotated and mirrored at draw time just like the display
+    /// controller would for [`Display::set_orientation`](crate::Display::set_orientation).
+    ///
+    /// [`width`](Self::width), [`height`](Self::height) and the
+    /// [`dirty_region`](Self::dirty_region) refer to the buffer, the size of
+    /// the draw target is swapped for 90° and 270° rotations. Existing
+    /// content isn't changed.
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    /// Converts a point in drawing coordinates to buffer coordinates.
+    fn buffer_point(&self, point: Point) -> Point {
+        let mapping = MemoryMapping::from_orientation(self.orientation);
+        let mut point = if mapping.swap_rows_and_columns {
+            Point::new(point.y, point.x)
+        } else {
+            point
+        };
+        if mapping.reverse_columns {
+            point.x = self.width as i32 - 1 - point.x;
+        }
+        if mapping.reverse_rows {
+            point.y = self.height as i32 - 1 - point.y;
+        }
+        point
+    }
+
+    /// Converts an area in drawing coordinates to buffer coordinates.
+    ///
+    /// The area is clipped to the framebuffer.
+    fn buffer_area(&self, area: &Rectangle) -> Rectangle {
+        let area = area.intersection(&self.bounding_box());
+        match area.bottom_right() {
+            Some(bottom_right) => Rectangle::with_corners(
+                self.buffer_point(area.top_left),
+                self.buffer_point(bottom_right),
+            ),
+            None => Rectangle::zero(),
+        }
+    }
+
     pub fn width(&self) -> usize {
         self.width
     }
@@ -89,6 +375,34 @@ where
         self.height
     }
 
+    /// Returns the areas which were changed since the dirty state was last cleared.
+    ///
+    /// Drawing operations record the bounding box of the changed pixels in
+    /// buffer coordinates.
+    /// [`as_mut_bytes`](Self::as_mut_bytes) marks the entire framebuffer as dirty.
+    pub fn dirty_region(&self) -> &DirtyRegion {
+        &self.dirty
+    }
+
+    /// Marks an area, given in drawing coordinates, as dirty.
+    ///
+    /// This can be used to force a redraw of an area by
+    /// [`Display::flush_dirty`](crate::Display::flush_dirty).
+    pub fn mark_dirty(&mut self, area: &Rectangle) {
+        let area = self.buffer_area(area);
+        self.dirty.add(area);
+    }
+
+    /// Clears the dirty state.
+    pub fn clear_dirty(&mut self) {
+        self.dirty.clear();
+    }
+
+    /// Releases the underlying buffer.
+    pub fn into_inner(self) -> BUF {
+        self.buffer
+    }
+
     pub fn as_bytes(&self) -> &[u8] {
         let expected_len = self.width * self.height * N;
         &self.buffer.as_u8_slice()[0..expected_len]
@@ -96,9 +410,31 @@ where
 
     pub fn as_mut_bytes(&mut self) -> &mut [u8] {
         let expected_len = self.width * self.height * N;
+        self.dirty.add(self.buffer_bounds());
         &mut self.buffer.as_mut_u8_slice()[0..expected_len]
     }
 
+    /// Returns the area of the buffer in buffer coordinates.
+    fn buffer_bounds(&self) -> Rectangle {
+        Rectangle::new(
+            Point::zero(),
+            Size::new(self.width as u32, self.height as u32),
+        )
+    }
+
+    /// Returns the bytes of every row of `area`.
+    ///
+    /// `area` must be inside the framebuffer.
+    pub(crate) fn area_rows(&self, area: &Rectangle) -> impl Iterator<Item = &[u8]> {
+        let bytes = self.as_bytes();
+        let stride = self.width * N;
+        let start = area.top_left.x as usize * N;
+        let len = area.size.width as usize * N;
+
+        area.rows()
+            .map(move |y| &bytes[y as usize * stride + start..][..len])
+    }
+
     // This method is not strictly needed if calculations are done in place,
     // but if kept, it should be `&self`.
     // fn point_to_byte_index(&self, p: Point) -> usize {
@@ -106,13 +442,51 @@ where
     // }
 }
 
+impl<C, const N: usize, const S: usize> RawFrameBuf<C, [u8; S], N>
+where
+    C: IntoRawBytes<N>,
+{
+    /// Creates a zero initialized framebuffer which owns its buffer.
+    ///
+    /// The array size `S` is checked against `W * H * N` at compile time,
+    /// which makes this constructor suitable for `static` framebuffers:
+    ///
+    /// ```
+    /// use embedded_graphics::pixelcolor::Rgb565;
+    /// use mipidsi::raw_framebuf::RawFrameBuf;
+    ///
+    /// const WIDTH: usize = 240;
+    /// const HEIGHT: usize = 135;
+    ///
+    /// static FRAMEBUFFER: RawFrameBuf<Rgb565, [u8; WIDTH * HEIGHT * 2], 2> =
+    ///     RawFrameBuf::new_array::<WIDTH, HEIGHT>();
+    /// ```
+    pub const fn new_array<const W: usize, const H: usize>() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = ArraySize::<S, W, H, N>::CHECK;
+
+        Self {
+            buffer: [0; S],
+            width: W,
+            height: H,
+            dirty: DirtyRegion::new(),
+            orientation: Orientation::new(),
+            _phantom_color: core::marker::PhantomData,
+        }
+    }
+}
+
 impl<C, BUF, const N: usize> OriginDimensions for RawFrameBuf<C, BUF, N>
 where
     C: IntoRawBytes<N>,
     BUF: RawBufferBackendMut,
 {
     fn size(&self) -> Size {
-        Size::new(self.width as u32, self.height as u32)
+        if self.orientation.rotation.is_vertical() {
+            Size::new(self.height as u32, self.width as u32)
+        } else {
+            Size::new(self.width as u32, self.height as u32)
+        }
     }
 }
 
@@ -130,28 +504,46 @@ where
     {
         let current_width = self.width; // Capture width to avoid re-borrowing self later
         let current_height = self.height; // Capture height
-        let buffer_slice = self.buffer.as_mut_u8_slice();
+        let size = self.size();
+        let oriented = self.orientation != Orientation::new();
         let active_buffer_len = current_width * current_height * N;
 
+        // Bounding box of the changed pixels
+        let mut min = Point::new(i32::MAX, i32::MAX);
+        let mut max = Point::new(i32::MIN, i32::MIN);
+
         for Pixel(coord, color) in pixels.into_iter() {
             if coord.x >= 0
-                && coord.x < current_width as i32
+                && coord.x < size.width as i32
                 && coord.y >= 0
-                && coord.y < current_height as i32
+                && coord.y < size.height as i32
             {
+                let coord = if oriented {
+                    self.buffer_point(coord)
+                } else {
+                    coord
+                };
+                let buffer_slice = self.buffer.as_mut_u8_slice();
                 let byte_index = (coord.y as usize * current_width + coord.x as usize) * N;
                 let color_bytes = color.into_raw_bytes();
 
                 if byte_index + N <= active_buffer_len {
                     buffer_slice[byte_index..byte_index + N].copy_from_slice(&color_bytes);
+                    min = min.component_min(coord);
+                    max = max.component_max(coord);
                 }
             }
         }
+
+        if min.x <= max.x {
+            self.dirty.add(Rectangle::with_corners(min, max));
+        }
         Ok(())
     }
 
     fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
         let color_bytes = color.into_raw_bytes(); // [byte1, byte2, ..., byteN]
+        self.dirty.add(self.buffer_bounds());
         let buffer_slice = self.buffer.as_mut_u8_slice();
         let active_buffer_len = self.width * self.height * N;
         let active_slice = &mut buffer_slice[0..active_buffer_len];
@@ -187,13 +579,15 @@ where
     }
 
     fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
-        let drawable_area = area.intersection(&self.bounding_box()); // self.bounding_box() is fine
+        // A solid area covers the same pixels in buffer coordinates.
+        let drawable_area = self.buffer_area(area);
         if drawable_area.is_zero_sized() {
             return Ok(());
         }
 
         let color_bytes = color.into_raw_bytes();
         let current_width = self.width; // Capture width
+        self.dirty.add(drawable_area);
         let buffer_slice = self.buffer.as_mut_u8_slice();
         let active_buffer_len = current_width * self.height * N;
 
@@ -214,3 +608,444 @@ where
         Ok(())
     }
 }
+
+// --- Packed 12 bit Rgb444 ---
+
+/// Returns the number of bytes required to store `pixels` in the packed Rgb444 format.
+///
+/// For an odd number of pixels the lower nibble of the last byte is padding,
+/// which is ignored by the display.
+pub const fn rgb444_len(pixels: usize) -> usize {
+    (pixels * 3).div_ceil(2)
+}
+
+/// Packs two colors into the 12 bit format used by COLMOD `0x03`.
+///
+/// The 4 most significant bits of every channel are used. The first pixel is
+/// stored in the first byte and the upper nibble of the second byte, the
+/// second pixel in the lower nibble of the second byte and the third byte.
+pub fn rgb444_pack(first: Rgb888, second: Rgb888) -> [u8; 3] {
+    [
+        (first.r() & 0xF0) | (first.g() >> 4),
+        (first.b() & 0xF0) | (second.r() >> 4),
+        (second.g() & 0xF0) | (second.b() >> 4),
+    ]
+}
+
+/// A 12 bit color with 4 bits per channel.
+///
+/// embedded-graphics has no 12 bit color type, this type can be used with
+/// [`Rgb444FrameBuf`] and [`Dither`](crate::dither::Dither) to draw colors
+/// which are already reduced to 4 bits per channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgb444 {
+    r: u8,
+    g: u8,
+    b: u8,
+}
+
+impl Rgb444 {
+    /// Creates a new color, only the 4 least significant bits of every channel are used.
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self {
+            r: r & 0x0F,
+            g: g & 0x0F,
+            b: b & 0x0F,
+        }
+    }
+
+    pub const fn r(self) -> u8 {
+        self.r
+    }
+    pub const fn g(self) -> u8 {
+        self.g
+    }
+    pub const fn b(self) -> u8 {
+        self.b
+    }
+}
+
+impl PixelColor for Rgb444 {
+    type Raw = embedded_graphics::pixelcolor::raw::RawU16;
+}
+
+impl From<Rgb444> for Rgb888 {
+    fn from(color: Rgb444) -> Self {
+        Rgb888::new(color.r * 0x11, color.g * 0x11, color.b * 0x11)
+    }
+}
+
+/// A framebuffer that stores pixels in the packed 12 bit Rgb444 format.
+///
+/// Two pixels are packed into three bytes, see [`rgb444_pack`]. Pixels are
+/// packed continuously across row boundaries, just like the display expects
+/// them in a single memory write.
+///
+/// Colors are converted to [`Rgb888`] and truncated to 4 bits per channel.
+pub struct Rgb444FrameBuf<C, BUF>
+where
+    C: PixelColor + Into<Rgb888>,
+    BUF: RawBufferBackendMut,
+{
+    buffer: BUF,
+    width: usize,
+    height: usize,
+    _phantom_color: core::marker::PhantomData<C>,
+}
+
+impl<C, BUF> Rgb444FrameBuf<C, BUF>
+where
+    C: PixelColor + Into<Rgb888>,
+    BUF: RawBufferBackendMut,
+{
+    pub fn new(buffer: BUF, width: usize, height: usize) -> Self {
+        let expected_len = rgb444_len(width * height);
+        assert!(
+            buffer.u8_len() >= expected_len,
+            "Rgb444FrameBuf underlying buffer is too small. Expected at least {}, got {}.",
+            expected_len,
+            buffer.u8_len()
+        );
+        Self {
+            buffer,
+            width,
+            height,
+            _phantom_color: core::marker::PhantomData,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        let expected_len = rgb444_len(self.width * self.height);
+        &self.buffer.as_u8_slice()[0..expected_len]
+    }
+
+    pub fn as_mut_bytes(&mut self) -> &mut [u8] {
+        let expected_len = rgb444_len(self.width * self.height);
+        &mut self.buffer.as_mut_u8_slice()[0..expected_len]
+    }
+
+    fn set_pixel(buffer: &mut [u8], index: usize, color: Rgb888) {
+        let (r, g, b) = (color.r() >> 4, color.g() >> 4, color.b() >> 4);
+        let byte_index = index / 2 * 3;
+
+        if index % 2 == 0 {
+            buffer[byte_index] = r << 4 | g;
+            buffer[byte_index + 1] = (b << 4) | (buffer[byte_index + 1] & 0x0F);
+        } else {
+            buffer[byte_index + 1] = (buffer[byte_index + 1] & 0xF0) | r;
+            buffer[byte_index + 2] = g << 4 | b;
+        }
+    }
+}
+
+impl<C, BUF> OriginDimensions for Rgb444FrameBuf<C, BUF>
+where
+    C: PixelColor + Into<Rgb888>,
+    BUF: RawBufferBackendMut,
+{
+    fn size(&self) -> Size {
+        Size::new(self.width as u32, self.height as u32)
+    }
+}
+
+impl<C, BUF> DrawTarget for Rgb444FrameBuf<C, BUF>
+where
+    C: PixelColor + Into<Rgb888>,
+    BUF: RawBufferBackendMut,
+{
+    type Color = C;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let current_width = self.width;
+        let current_height = self.height;
+        let buffer_slice = self.as_mut_bytes();
+
+        for Pixel(coord, color) in pixels.into_iter() {
+            if coord.x >= 0
+                && coord.x < current_width as i32
+                && coord.y >= 0
+                && coord.y < current_height as i32
+            {
+                let index = coord.y as usize * current_width + coord.x as usize;
+                Self::set_pixel(buffer_slice, index, color.into());
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let color = color.into();
+        let pair_bytes = rgb444_pack(color, color);
+
+        let mut chunks = self.as_mut_bytes().chunks_exact_mut(3);
+        for chunk in &mut chunks {
+            chunk.copy_from_slice(&pair_bytes);
+        }
+        // Odd number of pixels: the last pixel uses 1.5 bytes.
+        let remainder = chunks.into_remainder();
+        if !remainder.is_empty() {
+            remainder[0] = pair_bytes[0];
+            remainder[1] = pair_bytes[1] & 0xF0;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::pixelcolor::{
+        Bgr565, Bgr888, BinaryColor, Gray8, GrayColor, Rgb565, Rgb666,
+    };
+
+    use embedded_graphics::{prelude::Primitive, primitives::PrimitiveStyle, Drawable};
+
+    use super::*;
+
+    #[test]
+    fn rgb565_is_big_endian() {
+        assert_eq!(
+            Rgb565::new(0b11111, 0, 0b00001).into_raw_bytes(),
+            [0xF8, 0x01]
+        );
+        assert_eq!(Rgb565::new(0, 0b111111, 0).into_raw_bytes(), [0x07, 0xE0]);
+    }
+
+    #[test]
+    fn bgr565_starts_with_blue() {
+        assert_eq!(Bgr565::new(0, 0, 0b11111).into_raw_bytes(), [0xF8, 0x00]);
+        assert_eq!(
+            Bgr565::new(0b00001, 0b111111, 0).into_raw_bytes(),
+            [0x07, 0xE1]
+        );
+    }
+
+    #[test]
+    fn rgb666_is_left_aligned() {
+        assert_eq!(
+            Rgb666::new(0b111111, 0b000001, 0b100000).into_raw_bytes(),
+            [0xFC, 0x04, 0x80]
+        );
+    }
+
+    #[test]
+    fn rgb888_and_bgr888_byte_order() {
+        assert_eq!(Rgb888::new(1, 2, 3).into_raw_bytes(), [1, 2, 3]);
+        assert_eq!(Bgr888::new(1, 2, 3).into_raw_bytes(), [3, 2, 1]);
+    }
+
+    #[test]
+    fn gray_is_expanded() {
+        let gray = Gray8::new(0x84);
+        assert_eq!(IntoRawBytes::<2>::into_raw_bytes(gray), [0x84, 0x30]);
+        assert_eq!(IntoRawBytes::<3>::into_raw_bytes(gray), [0x84, 0x84, 0x84]);
+        assert_eq!(
+            IntoRawBytes::<2>::into_raw_bytes(Gray8::WHITE),
+            [0xFF, 0xFF]
+        );
+    }
+
+    #[test]
+    fn binary_color_is_expanded() {
+        assert_eq!(
+            IntoRawBytes::<2>::into_raw_bytes(BinaryColor::On),
+            [0xFF, 0xFF]
+        );
+        assert_eq!(
+            IntoRawBytes::<2>::into_raw_bytes(BinaryColor::Off),
+            [0x00, 0x00]
+        );
+        assert_eq!(
+            IntoRawBytes::<3>::into_raw_bytes(BinaryColor::On),
+            [0xFF, 0xFF, 0xFF]
+        );
+        assert_eq!(
+            IntoRawBytes::<3>::into_raw_bytes(BinaryColor::Off),
+            [0x00, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn rgb444_packs_two_pixels_into_three_bytes() {
+        assert_eq!(
+            rgb444_pack(Rgb888::new(0x12, 0x34, 0x56), Rgb888::new(0x78, 0x9A, 0xBC)),
+            [0x13, 0x57, 0x9B]
+        );
+        assert_eq!(rgb444_len(3), 5);
+        assert_eq!(rgb444_len(4), 6);
+    }
+
+    #[test]
+    fn rgb444_framebuffer_packs_across_rows() {
+        let mut buffer = [0u8; 6];
+        let mut fb = Rgb444FrameBuf::<Rgb888, _>::new(&mut buffer[..], 3, 1);
+
+        fb.clear(Rgb888::new(0x10, 0x20, 0x30)).unwrap();
+        assert_eq!(fb.as_bytes(), &[0x12, 0x31, 0x23, 0x12, 0x30]);
+
+        Pixel(Point::new(1, 0), Rgb888::new(0xF0, 0xE0, 0xD0))
+            .draw(&mut fb)
+            .unwrap();
+        assert_eq!(fb.as_bytes(), &[0x12, 0x3F, 0xED, 0x12, 0x30]);
+    }
+
+    #[test]
+    fn array_backed_framebuffer() {
+        let mut fb = RawFrameBuf::<Rgb565, [u8; 8], 2>::new_array::<2, 2>();
+        assert_eq!(fb.size(), Size::new(2, 2));
+
+        fb.clear(Rgb565::new(0b11111, 0, 0)).unwrap();
+        assert_eq!(
+            fb.into_inner(),
+            [0xF8, 0x00, 0xF8, 0x00, 0xF8, 0x00, 0xF8, 0x00]
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn boxed_framebuffer() {
+        let buffer = alloc::vec![0u8; 3 * 2].into_boxed_slice();
+        let mut fb = RawFrameBuf::<Rgb888, _, 3>::new(buffer, 2, 1);
+
+        fb.clear(Rgb888::new(1, 2, 3)).unwrap();
+        assert_eq!(fb.as_bytes(), &[1, 2, 3, 1, 2, 3]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    #[should_panic(expected = "buffer is too small")]
+    fn empty_vec_is_too_small() {
+        RawFrameBuf::<Rgb565, _, 2>::new(alloc::vec::Vec::with_capacity(4), 2, 1);
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn heapless_framebuffer() {
+        let mut buffer = heapless::Vec::<u8, 8>::new();
+        buffer.resize(4, 0).unwrap();
+        let mut fb = RawFrameBuf::<Rgb565, _, 2>::new(buffer, 2, 1);
+
+        fb.clear(Rgb565::new(0, 0, 1)).unwrap();
+        assert_eq!(fb.as_bytes(), &[0x00, 0x01, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn drawing_marks_areas_dirty() {
+        let mut buffer = [0u8; 8 * 8 * 2];
+        let mut fb = RawFrameBuf::<Rgb565, _, 2>::new(&mut buffer[..], 8, 8);
+        assert!(fb.dirty_region().is_empty());
+
+        Pixel(Point::new(1, 2), Rgb565::new(1, 1, 1))
+            .draw(&mut fb)
+            .unwrap();
+        Pixel(Point::new(2, 1), Rgb565::new(1, 1, 1))
+            .draw(&mut fb)
+            .unwrap();
+        Pixel(Point::new(20, 1), Rgb565::new(1, 1, 1))
+            .draw(&mut fb)
+            .unwrap();
+        assert_eq!(
+            fb.dirty_region().as_slice(),
+            &[Rectangle::new(Point::new(1, 1), Size::new(2, 2))]
+        );
+
+        Rectangle::new(Point::new(6, 6), Size::new(10, 10))
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::new(1, 1, 1)))
+            .draw(&mut fb)
+            .unwrap();
+        assert_eq!(
+            fb.dirty_region().as_slice()[1],
+            Rectangle::new(Point::new(6, 6), Size::new(2, 2))
+        );
+
+        fb.clear_dirty();
+        fb.clear(Rgb565::new(0, 0, 0)).unwrap();
+        assert_eq!(fb.dirty_region().as_slice(), &[fb.bounding_box()]);
+    }
+
+    #[test]
+    fn area_rows_are_strided() {
+        let mut buffer = [0u8; 4 * 3];
+        let mut fb = RawFrameBuf::<Gray8, _, 3>::new(&mut buffer[..], 2, 2);
+        fb.as_mut_bytes()
+            .copy_from_slice(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]);
+
+        let area = Rectangle::new(Point::new(1, 0), Size::new(1, 2));
+        let mut rows = fb.area_rows(&area);
+        assert_eq!(rows.next(), Some(&[3, 4, 5][..]));
+        assert_eq!(rows.next(), Some(&[9, 10, 11][..]));
+        assert_eq!(rows.next(), None);
+    }
+
+    #[test]
+    fn encode_pixels_writes_complete_pixels() {
+        let mut pixels = [Rgb565::new(0b11111, 0, 0), Rgb565::new(0, 0, 1)].into_iter();
+        let mut buffer = [0u8; 3];
+
+        assert_eq!(encode_pixels(&mut pixels, &mut buffer), 2);
+        assert_eq!(buffer, [0xF8, 0x00, 0x00]);
+        assert_eq!(encode_pixels(&mut pixels, &mut buffer), 2);
+        assert_eq!(buffer, [0x00, 0x01, 0x00]);
+        assert_eq!(encode_pixels(&mut pixels, &mut buffer), 0);
+    }
+
+    #[test]
+    fn raw_framebuffer_uses_native_depth() {
+        let mut buffer = [0u8; 6];
+        let mut fb = RawFrameBuf::<Rgb666, _, 3>::new(&mut buffer[..], 2, 1);
+
+        Pixel(Point::new(1, 0), Rgb666::new(0b111111, 0, 1))
+            .draw(&mut fb)
+            .unwrap();
+        assert_eq!(fb.as_bytes(), &[0, 0, 0, 0xFC, 0x00, 0x04]);
+    }
+
+    #[test]
+    fn orientation_maps_to_native_scan_order() {
+        use crate::options::Rotation;
+
+        const RED: Rgb565 = Rgb565::new(0b11111, 0, 0);
+        let pixel_index = |bytes: &[u8]| {
+            let indices = bytes.chunks_exact(2).enumerate();
+            let mut indices = indices.filter(|(_, pixel)| pixel[0] != 0).map(|(i, _)| i);
+            [indices.next(), indices.next()]
+        };
+
+        let mut buffer = [0u8; 3 * 2 * 2];
+        let mut fb = RawFrameBuf::<Rgb565, _, 2>::new(&mut buffer[..], 3, 2)
+            .with_orientation(Orientation::new().rotate(Rotation::Deg90));
+        assert_eq!(fb.size(), Size::new(2, 3));
+
+        Pixel(Point::new(0, 0), RED).draw(&mut fb).unwrap();
+        Pixel(Point::new(1, 2), RED).draw(&mut fb).unwrap();
+        Pixel(Point::new(2, 0), RED).draw(&mut fb).unwrap();
+        assert_eq!(pixel_index(fb.as_bytes()), [Some(2), Some(3)]);
+        assert_eq!(
+            fb.dirty_region().as_slice(),
+            &[
+                Rectangle::new(Point::new(2, 0), Size::new(1, 1)),
+                Rectangle::new(Point::new(0, 1), Size::new(1, 1)),
+            ]
+        );
+
+        fb.clear(Rgb565::new(0, 0, 0)).unwrap();
+        fb.fill_solid(&Rectangle::new(Point::zero(), Size::new(2, 1)), RED)
+            .unwrap();
+        assert_eq!(pixel_index(fb.as_bytes()), [Some(2), Some(5)]);
+
+        fb.clear(Rgb565::new(0, 0, 0)).unwrap();
+        fb.set_orientation(Orientation::new().flip_horizontal());
+        Pixel(Point::new(0, 1), RED).draw(&mut fb).unwrap();
+        assert_eq!(pixel_index(fb.as_bytes()), [Some(5), None]);
+    }
+}
@@ -0,0 +1,250 @@
+//! Strip rendering for displays without RAM for a full framebuffer.
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::OriginDimensions,
+    prelude::{Point, Size, Transform},
+    primitives::Rectangle,
+    Pixel,
+};
+use embedded_hal::digital::OutputPin;
+
+use crate::{interface::Interface, models::Model, Display};
+
+use super::{IntoRawBytes, RawFrameBuf};
+
+/// A horizontal strip of the display.
+///
+/// A band is a [`DrawTarget`] with the size of the entire display, which only
+/// stores the pixels inside the current strip. All other pixels are discarded.
+/// See [`Display::render_banded`] for more information.
+pub struct Band<'a, C, const N: usize>
+where
+    C: IntoRawBytes<N>,
+{
+    framebuffer: RawFrameBuf<C, &'a mut [u8], N>,
+    top: i32,
+    size: Size,
+}
+
+impl<'a, C, const N: usize> Band<'a, C, N>
+where
+    C: IntoRawBytes<N>,
+{
+    pub(crate) fn new(buffer: &'a mut [u8], size: Size, top: usize, height: usize) -> Self {
+        Self {
+            framebuffer: RawFrameBuf::new(buffer, size.width as usize, height),
+            top: top as i32,
+            size,
+        }
+    }
+
+    /// Returns the area of the display which is covered by this band.
+    pub fn area(&self) -> Rectangle {
+        Rectangle::new(
+            Point::new(0, self.top),
+            Size::new(self.size.width, self.framebuffer.height() as u32),
+        )
+    }
+
+    /// Returns the raw bytes of this band.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.framebuffer.as_bytes()
+    }
+}
+
+impl<C, const N: usize> OriginDimensions for Band<'_, C, N>
+where
+    C: IntoRawBytes<N>,
+{
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+impl<C, const N: usize> DrawTarget for Band<'_, C, N>
+where
+    C: IntoRawBytes<N>,
+{
+    type Color = C;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let offset = Point::new(0, self.top);
+        let height = self.framebuffer.height() as i32;
+
+        self.framebuffer.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point - offset, color))
+                .filter(|Pixel(point, _)| point.y >= 0 && point.y < height),
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.area());
+        self.framebuffer
+            .fill_solid(&area.translate(Point::new(0, -self.top)), color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.framebuffer.clear(color)
+    }
+}
+
+impl<DI, M, RST> Display<DI, M, RST>
+where
+    DI: Interface<Word = u8>,
+    M: Model,
+    RST: OutputPin,
+{
+    /// Renders a scene strip by strip using a small buffer.
+    ///
+    /// The display is split into horizontal strips which fit into `buffer`.
+    /// `draw` is called once per strip and should draw the entire scene into
+    /// the passed [`Band`], which discards all pixels outside the current
+    /// strip. Every strip is sent to the display once `draw` returns. The
+    /// address window is only set once, subsequent strips are appended using
+    /// the Write Memory Continue command.
+    ///
+    /// The buffer isn't cleared between strips, so `draw` should always draw
+    /// the background of the scene as well.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` can't hold at least one row of pixels.
+    pub async fn render_banded<C, const N: usize, F>(
+        &mut self,
+        buffer: &mut [u8],
+        mut draw: F,
+    ) -> Result<(), DI::Error>
+    where
+        C: IntoRawBytes<N>,
+        F: FnMut(&mut Band<'_, C, N>),
+    {
+        let (width, height) = self.options.display_size();
+        let (width, height) = (usize::from(width), usize::from(height));
+        let rows = buffer.len() / (width * N);
+        assert!(
+            rows > 0,
+            "Band buffer is too small. Expected at least {}, got {}.",
+            width * N,
+            buffer.len()
+        );
+
+        // All strips share one address window. Every strip after the first
+        // resumes the memory write where the previous strip stopped.
+        self.set_address_window(0, 0, width as u16 - 1, height as u16 - 1)
+            .await?;
+
+        let size = Size::new(width as u32, height as u32);
+        for top in (0..height).step_by(rows) {
+            let band_rows = rows.min(height - top);
+            let mut band = Band::new(&mut *buffer, size, top, band_rows);
+            draw(&mut band);
+
+            if top == 0 {
+                M::write_memory_start(&mut self.di).await?;
+            } else {
+                M::write_memory_continue(&mut self.di).await?;
+            }
+            self.di.send_data_slice(band.as_bytes()).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use embassy_futures::block_on;
+    use embedded_graphics::{
+        geometry::Dimensions,
+        pixelcolor::{Gray8, Rgb565},
+        prelude::{GrayColor, Primitive},
+        primitives::{PointsIter, PrimitiveStyle},
+        Drawable,
+    };
+    use std::{vec, vec::Vec};
+
+    use crate::{
+        models::ST7789,
+        testing::{mock_display, MockInterface, Transfer},
+    };
+
+    use super::*;
+
+    #[test]
+    fn band_clips_and_translates() {
+        let mut buffer = [0u8; 4 * 2 * 3];
+        let mut band = Band::<Gray8, 3>::new(&mut buffer, Size::new(4, 8), 2, 2);
+        assert_eq!(
+            band.bounding_box(),
+            Rectangle::new(Point::zero(), Size::new(4, 8))
+        );
+        assert_eq!(
+            band.area(),
+            Rectangle::new(Point::new(0, 2), Size::new(4, 2))
+        );
+
+        Pixel(Point::new(0, 1), Gray8::WHITE)
+            .draw(&mut band)
+            .unwrap();
+        Pixel(Point::new(1, 2), Gray8::new(1))
+            .draw(&mut band)
+            .unwrap();
+        Pixel(Point::new(2, 4), Gray8::WHITE)
+            .draw(&mut band)
+            .unwrap();
+        Rectangle::new(Point::new(3, 0), Size::new(1, 8))
+            .into_styled(PrimitiveStyle::with_fill(Gray8::new(2)))
+            .draw(&mut band)
+            .unwrap();
+
+        assert_eq!(
+            band.as_bytes(),
+            &[
+                0, 0, 0, 1, 1, 1, 0, 0, 0, 2, 2, 2, //
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 2, 2, //
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_strips_into_one_window() {
+        let color = |y: i32| Rgb565::new(y as u8, 0, 0);
+        let rows = |rows: core::ops::Range<i32>| -> Vec<u8> {
+            rows.flat_map(|y| [color(y).into_raw_bytes(); 2])
+                .flatten()
+                .collect()
+        };
+
+        let mut display = mock_display(ST7789, MockInterface::new(), (2, 5));
+        let mut buffer = [0u8; 2 * 2 * 2 + 3];
+        let mut strips = 0;
+        block_on(display.render_banded(&mut buffer, |band| {
+            strips += 1;
+            let area = band.bounding_box();
+            band.draw_iter(area.points().map(|p| Pixel(p, color(p.y))))
+                .unwrap();
+        }))
+        .unwrap();
+
+        assert_eq!(strips, 3);
+        display.di.assert_transfers(&[
+            Transfer::Command(0x2A, vec![0, 0, 0, 1]),
+            Transfer::Command(0x2B, vec![0, 0, 0, 4]),
+            Transfer::Command(0x2C, vec![]),
+            Transfer::Data(rows(0..2)),
+            Transfer::Command(0x3C, vec![]),
+            Transfer::Data(rows(2..4)),
+            Transfer::Command(0x3C, vec![]),
+            Transfer::Data(rows(4..5)),
+        ]);
+    }
+}
@@ -0,0 +1,338 @@
+//! Alpha blending.
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, OriginDimensions},
+    pixelcolor::{PixelColor, Rgb888, RgbColor},
+    prelude::{Point, Size},
+    primitives::{PointsIter, Rectangle},
+    Pixel,
+};
+
+use super::{FromRawBytes, RawBufferBackendMut, RawFrameBuf};
+
+/// A color with an alpha value.
+///
+/// An alpha value of `0` is fully transparent, `255` is fully opaque.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alpha<C> {
+    /// The color.
+    pub color: C,
+    /// The opacity of the color.
+    pub alpha: u8,
+}
+
+impl<C> Alpha<C> {
+    /// Creates a new color with an alpha value.
+    pub const fn new(color: C, alpha: u8) -> Self {
+        Self { color, alpha }
+    }
+}
+
+impl<C: PixelColor> PixelColor for Alpha<C> {
+    type Raw = ();
+}
+
+/// Color space in which colors are blended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendSpace {
+    /// Blends the gamma encoded channel values directly.
+    ///
+    /// This is the fastest mode, but blends between bright and dark colors
+    /// appear too dark.
+    #[default]
+    Srgb,
+    /// Blends in linear light.
+    ///
+    /// The sRGB transfer function is approximated by a gamma of 2.
+    Linear,
+}
+
+impl BlendSpace {
+    /// Blends a single channel.
+    fn blend_channel(self, src: u8, dst: u8, alpha: u8) -> u8 {
+        let (src, dst, alpha) = (u32::from(src), u32::from(dst), u32::from(alpha));
+        match self {
+            Self::Srgb => ((src * alpha + dst * (255 - alpha) + 127) / 255) as u8,
+            Self::Linear => {
+                let linear = (src * src * alpha + dst * dst * (255 - alpha) + 127) / 255;
+                isqrt(linear) as u8
+            }
+        }
+    }
+
+    /// Blends `src` over `dst`.
+    pub fn blend(self, src: Rgb888, dst: Rgb888, alpha: u8) -> Rgb888 {
+        match alpha {
+            0 => dst,
+            255 => src,
+            _ => Rgb888::new(
+                self.blend_channel(src.r(), dst.r(), alpha),
+                self.blend_channel(src.g(), dst.g(), alpha),
+                self.blend_channel(src.b(), dst.b(), alpha),
+            ),
+        }
+    }
+}
+
+/// Returns the rounded square root.
+fn isqrt(value: u32) -> u32 {
+    let mut root = 0;
+    let mut bit = 1 << 30;
+    let mut rest = value;
+    while bit > rest {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if rest >= root + bit {
+            rest -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+
+    if rest > root {
+        root + 1
+    } else {
+        root
+    }
+}
+
+impl<C, BUF, const N: usize> RawFrameBuf<C, BUF, N>
+where
+    C: FromRawBytes<N> + From<Rgb888> + Into<Rgb888>,
+    BUF: RawBufferBackendMut,
+{
+    /// Draws pixels with an alpha value over the existing content.
+    ///
+    /// The existing pixels are decoded from the buffer, blended in the
+    /// given color space and encoded again.
+    pub fn draw_blended<S, I>(&mut self, pixels: I, space: BlendSpace)
+    where
+        S: PixelColor + Into<Rgb888>,
+        I: IntoIterator<Item = Pixel<Alpha<S>>>,
+    {
+        let size = self.size();
+
+        // Bounding box of the changed pixels
+        let mut min = Point::new(i32::MAX, i32::MAX);
+        let mut max = Point::new(i32::MIN, i32::MIN);
+
+        for Pixel(point, Alpha { color, alpha }) in pixels {
+            if alpha == 0
+                || point.x < 0
+                || point.y < 0
+                || point.x >= size.width as i32
+                || point.y >= size.height as i32
+            {
+                continue;
+            }
+
+            let point = self.buffer_point(point);
+            let index = (point.y as usize * self.width + point.x as usize) * N;
+            let bytes = &mut self.buffer.as_mut_u8_slice()[index..index + N];
+
+            let dst = C::from_raw_bytes(bytes.try_into().unwrap()).into();
+            let blended = C::from(space.blend(color.into(), dst, alpha));
+            bytes.copy_from_slice(&blended.into_raw_bytes());
+
+            min = min.component_min(point);
+            max = max.component_max(point);
+        }
+
+        if min.x <= max.x {
+            self.dirty.add(Rectangle::with_corners(min, max));
+        }
+    }
+
+    /// Returns a draw target which blends all pixels with a constant alpha value.
+    pub fn blended(&mut self, alpha: u8, space: BlendSpace) -> Blended<'_, C, BUF, N> {
+        Blended {
+            framebuffer: self,
+            alpha,
+            space,
+        }
+    }
+}
+
+/// Draw target which draws translucent colors into a [`RawFrameBuf`].
+///
+/// Created by [`RawFrameBuf::blended`].
+pub struct Blended<'a, C, BUF, const N: usize>
+where
+    C: FromRawBytes<N> + From<Rgb888> + Into<Rgb888>,
+    BUF: RawBufferBackendMut,
+{
+    framebuffer: &'a mut RawFrameBuf<C, BUF, N>,
+    alpha: u8,
+    space: BlendSpace,
+}
+
+impl<C, BUF, const N: usize> OriginDimensions for Blended<'_, C, BUF, N>
+where
+    C: FromRawBytes<N> + From<Rgb888> + Into<Rgb888>,
+    BUF: RawBufferBackendMut,
+{
+    fn size(&self) -> Size {
+        self.framebuffer.size()
+    }
+}
+
+impl<C, BUF, const N: usize> DrawTarget for Blended<'_, C, BUF, N>
+where
+    C: FromRawBytes<N> + From<Rgb888> + Into<Rgb888>,
+    BUF: RawBufferBackendMut,
+{
+    type Color = C;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let alpha = self.alpha;
+        self.framebuffer.draw_blended(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point, Alpha::new(color, alpha))),
+            self.space,
+        );
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        self.draw_iter(area.points().map(|point| Pixel(point, color)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::{
+        pixelcolor::{Bgr888, BinaryColor, Gray8, GrayColor, Rgb565, Rgb666},
+        prelude::{Primitive, WebColors},
+        primitives::PrimitiveStyle,
+        Drawable,
+    };
+
+    use crate::raw_framebuf::IntoRawBytes;
+
+    use super::*;
+
+    #[test]
+    fn blends_channels() {
+        let (white, black) = (Rgb888::WHITE, Rgb888::BLACK);
+        assert_eq!(
+            BlendSpace::Srgb.blend(white, black, 128),
+            Rgb888::new(128, 128, 128)
+        );
+        assert_eq!(
+            BlendSpace::Linear.blend(white, black, 128),
+            Rgb888::new(181, 181, 181)
+        );
+        assert_eq!(BlendSpace::Linear.blend(white, black, 0), black);
+        assert_eq!(BlendSpace::Linear.blend(white, black, 255), white);
+        assert_eq!(
+            BlendSpace::Srgb.blend(Rgb888::new(200, 100, 0), Rgb888::new(0, 100, 200), 64),
+            Rgb888::new(50, 100, 150)
+        );
+    }
+
+    #[test]
+    fn isqrt_rounds() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(2), 1);
+        assert_eq!(isqrt(3), 2);
+        assert_eq!(isqrt(255 * 255), 255);
+        assert_eq!(isqrt(65024), 255);
+    }
+
+    #[test]
+    fn raw_bytes_round_trip() {
+        fn round_trip<C: FromRawBytes<N> + core::fmt::Debug, const N: usize>(color: C) {
+            assert_eq!(C::from_raw_bytes(color.into_raw_bytes()), color);
+        }
+
+        round_trip(Rgb565::CSS_ORCHID);
+        round_trip(embedded_graphics::pixelcolor::Bgr565::CSS_ORCHID);
+        round_trip(Rgb666::CSS_ORCHID);
+        round_trip(Rgb888::CSS_ORCHID);
+        round_trip(Bgr888::CSS_ORCHID);
+        round_trip::<_, 3>(Gray8::new(0x42));
+        round_trip::<_, 2>(Gray8::WHITE);
+        round_trip::<_, 2>(BinaryColor::On);
+        round_trip::<_, 3>(BinaryColor::Off);
+    }
+
+    #[test]
+    fn draws_blended_pixels() {
+        let mut buffer = [0u8; 2 * 3];
+        let mut fb = RawFrameBuf::<Rgb888, _, 3>::new(&mut buffer[..], 2, 1);
+        fb.clear(Rgb888::new(0, 0, 200)).unwrap();
+        fb.clear_dirty();
+
+        fb.draw_blended(
+            [
+                Pixel(Point::new(0, 0), Alpha::new(Rgb888::new(200, 0, 0), 0)),
+                Pixel(Point::new(1, 0), Alpha::new(Rgb888::new(200, 0, 0), 64)),
+                Pixel(Point::new(2, 0), Alpha::new(Rgb888::new(200, 0, 0), 255)),
+            ],
+            BlendSpace::Srgb,
+        );
+        assert_eq!(fb.as_bytes(), &[0, 0, 200, 50, 0, 150]);
+        assert_eq!(
+            fb.dirty_region().as_slice(),
+            &[Rectangle::new(Point::new(1, 0), Size::new(1, 1))]
+        );
+    }
+
+    #[test]
+    fn blended_draw_target() {
+        let mut buffer = [0u8; 2 * 2];
+        let mut fb = RawFrameBuf::<Rgb565, _, 2>::new(&mut buffer[..], 2, 1);
+        fb.clear(Rgb565::BLACK).unwrap();
+
+        Rectangle::new(Point::zero(), Size::new(1, 1))
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::WHITE))
+            .draw(&mut fb.blended(128, BlendSpace::Srgb))
+            .unwrap();
+
+        let gray = Rgb565::from(Rgb888::new(128, 128, 128));
+        assert_eq!(&fb.as_bytes()[..2], &gray.into_raw_bytes());
+        assert_eq!(&fb.as_bytes()[2..], &[0, 0]);
+    }
+
+    #[test]
+    fn draws_blended_gray8() {
+        let mut buffer = [0u8; 2 * 3];
+        let mut fb = RawFrameBuf::<Gray8, _, 3>::new(&mut buffer[..], 2, 1);
+        fb.clear(Gray8::BLACK).unwrap();
+
+        fb.draw_blended(
+            [
+                Pixel(Point::new(0, 0), Alpha::new(Gray8::new(200), 64)),
+                Pixel(Point::new(1, 0), Alpha::new(Gray8::new(200), 255)),
+            ],
+            BlendSpace::Srgb,
+        );
+        assert_eq!(fb.as_bytes(), &[50, 50, 50, 200, 200, 200]);
+    }
+
+    #[test]
+    fn draws_blended_rgb666() {
+        let mut buffer = [0u8; 2 * 3];
+        let mut fb = RawFrameBuf::<Rgb666, _, 3>::new(&mut buffer[..], 2, 1);
+        fb.clear(Rgb666::BLUE).unwrap();
+
+        fb.draw_blended(
+            [
+                Pixel(Point::new(0, 0), Alpha::new(Rgb666::RED, 64)),
+                Pixel(Point::new(1, 0), Alpha::new(Rgb666::RED, 0)),
+            ],
+            BlendSpace::Srgb,
+        );
+        assert_eq!(fb.as_bytes(), &[64, 0, 188, 0, 0, 252]);
+    }
+}
@@ -0,0 +1,471 @@
+//! Frame diffing.
+
+use embedded_hal::digital::OutputPin;
+
+use crate::{interface::Interface, models::Model, Display};
+
+use super::{IntoRawBytes, RawBufferBackendMut, RawFrameBuf};
+
+/// Returns the number of hashes required by [`FrameDiff::with_hashes`].
+pub const fn diff_hash_len(width: usize, height: usize, segment_width: usize) -> usize {
+    width.div_ceil(segment_width) * height
+}
+
+enum Reference<'a> {
+    Shadow(&'a mut [u8]),
+    Hashes(&'a mut [u32]),
+}
+
+/// Content of the display after the last flush.
+///
+/// A frame diff is used by [`Display::flush_diff`] to find the parts of a
+/// [`RawFrameBuf`] which changed since the previous flush. Every row of the
+/// framebuffer is split into segments of `segment_width` pixels, only the
+/// changed segments are sent to the display. Adjacent changed segments are
+/// sent together. Smaller segments reduce the amount of transferred data,
+/// but require more transfers and, for hashes, more RAM.
+///
+/// The previous frame is either stored as a full shadow copy of the
+/// framebuffer, which detects every change, or as a hash per segment. Hashes
+/// require a lot less RAM, but a change can be missed in the rare case of a
+/// hash collision.
+///
+/// The first flush always sends the entire framebuffer.
+pub struct FrameDiff<'a> {
+    reference: Reference<'a>,
+    segment_width: usize,
+    valid: bool,
+}
+
+impl<'a> FrameDiff<'a> {
+    /// Creates a frame diff which compares the framebuffer with a shadow copy.
+    ///
+    /// The shadow buffer must be at least as large as the framebuffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `segment_width` is `0`.
+    pub fn with_shadow(shadow: &'a mut [u8], segment_width: usize) -> Self {
+        assert!(segment_width > 0, "Segment width must not be 0.");
+        Self {
+            reference: Reference::Shadow(shadow),
+            segment_width,
+            valid: false,
+        }
+    }
+
+    /// Creates a frame diff which compares hashes of the framebuffer segments.
+    ///
+    /// The hash buffer must hold [`diff_hash_len`] values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `segment_width` is `0`.
+    pub fn with_hashes(hashes: &'a mut [u32], segment_width: usize) -> Self {
+        assert!(segment_width > 0, "Segment width must not be 0.");
+        Self {
+            reference: Reference::Hashes(hashes),
+            segment_width,
+            valid: false,
+        }
+    }
+
+    /// Returns the segment width in pixels.
+    pub fn segment_width(&self) -> usize {
+        self.segment_width
+    }
+
+    /// Forces the next flush to send the entire framebuffer.
+    ///
+    /// This must be called if the display content was changed by other means.
+    pub fn invalidate(&mut self) {
+        self.valid = false;
+    }
+
+    /// Checks the buffer size for a framebuffer.
+    fn check_len(&self, width: usize, height: usize, bytes_per_pixel: usize) {
+        let (expected, len) = match &self.reference {
+            Reference::Shadow(shadow) => (width * height * bytes_per_pixel, shadow.len()),
+            Reference::Hashes(hashes) => (
+                diff_hash_len(width, height, self.segment_width),
+                hashes.len(),
+            ),
+        };
+        assert!(
+            len >= expected,
+            "Frame diff buffer is too small. Expected at least {}, got {}.",
+            expected,
+            len
+        );
+    }
+
+    /// Returns `true` if a segment changed since the last call and updates the reference.
+    ///
+    /// `bytes` are the bytes of the segment, which start at `offset` in the
+    /// framebuffer. `index` is the index of the segment in the framebuffer.
+    fn segment_changed(&mut self, index: usize, offset: usize, bytes: &[u8]) -> bool {
+        match &mut self.reference {
+            Reference::Shadow(shadow) => {
+                let shadow = &mut shadow[offset..offset + bytes.len()];
+                let changed = shadow != bytes;
+                if changed {
+                    shadow.copy_from_slice(bytes);
+                }
+                changed
+            }
+            Reference::Hashes(hashes) => {
+                let hash = fnv1a(bytes);
+                let changed = hashes[index] != hash;
+                hashes[index] = hash;
+                changed
+            }
+        }
+    }
+}
+
+/// 32 bit FNV-1a hash.
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(*byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+impl<DI, M, RST> Display<DI, M, RST>
+where
+    DI: Interface<Word = u8>,
+    M: Model,
+    RST: OutputPin,
+{
+    /// Sends the parts of a framebuffer which changed since the last flush.
+    ///
+    /// The framebuffer is assumed to be located at the top left corner of the
+    /// display and is compared with the state stored in `diff`, see
+    /// [`FrameDiff`]. Changed spans are sent using
+    /// [`show_raw_data`](Self::show_raw_data), consecutive fully changed rows
+    /// are combined into a single transfer. The dirty state of the
+    /// framebuffer isn't used or changed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer of `diff` is too small for the framebuffer.
+    pub async fn flush_diff<C, BUF, const N: usize>(
+        &mut self,
+        framebuffer: &RawFrameBuf<C, BUF, N>,
+        diff: &mut FrameDiff<'_>,
+    ) -> Result<(), DI::Error>
+    where
+        C: IntoRawBytes<N>,
+        BUF: RawBufferBackendMut,
+    {
+        let (width, height) = (framebuffer.width(), framebuffer.height());
+        diff.check_len(width, height, N);
+
+        let bytes = framebuffer.as_bytes();
+        let stride = width * N;
+        let segment_width = diff.segment_width;
+        let segments = width.div_ceil(segment_width);
+
+        // The reference is updated before the data is sent. If a transfer
+        // fails the next flush needs to send everything.
+        let valid = core::mem::replace(&mut diff.valid, false);
+        let mut full_rows: Option<(usize, usize)> = None;
+
+        for y in 0..height {
+            let row = &bytes[y * stride..][..stride];
+
+            let mut segment = 0;
+            while segment < segments {
+                let start = segment;
+                while segment < segments {
+                    let index = y * segments + segment;
+                    let x = segment * segment_width;
+                    let end = (x + segment_width).min(width);
+                    let segment_bytes = &row[x * N..end * N];
+                    let changed = diff.segment_changed(index, y * stride + x * N, segment_bytes);
+                    if valid && !changed {
+                        break;
+                    }
+                    segment += 1;
+                }
+
+                if segment > start {
+                    let x = start * segment_width;
+                    let end = (segment * segment_width).min(width);
+
+                    if x == 0 && end == width {
+                        full_rows = match full_rows {
+                            Some((first, rows)) => Some((first, rows + 1)),
+                            None => Some((y, 1)),
+                        };
+                        continue;
+                    }
+
+                    self.show_raw_data(x, y, end - x, 1, &row[x * N..end * N])
+                        .await?;
+                }
+                segment += 1;
+            }
+
+            // Send the collected full rows once a row isn't fully changed.
+            if let Some((first, rows)) = full_rows {
+                if first + rows <= y || y + 1 == height {
+                    let data = &bytes[first * stride..(first + rows) * stride];
+                    self.show_raw_data(0, first, width, rows, data).await?;
+                    full_rows = None;
+                }
+            }
+        }
+
+        diff.valid = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use embassy_futures::block_on;
+    use embedded_graphics::{
+        pixelcolor::{Gray8, Rgb565},
+        prelude::{DrawTarget, Point, RgbColor},
+        Pixel,
+    };
+    use std::vec::Vec;
+
+    use crate::{
+        models::ST7789,
+        testing::{mock_display, MockDisplay, MockError, MockInterface, Transfer},
+    };
+
+    use super::*;
+
+    const WIDTH: usize = 6;
+    const HEIGHT: usize = 4;
+
+    fn display() -> MockDisplay<ST7789> {
+        mock_display(ST7789, MockInterface::new(), (8, 8))
+    }
+
+    /// Returns all memory writes since the display was created.
+    ///
+    /// The address window is tracked across writes, because unchanged
+    /// coordinates aren't sent again.
+    fn writes(display: &MockDisplay<ST7789>) -> Vec<Write> {
+        let (mut columns, mut rows) = ((0, 0), (0, 0));
+        let mut writes = Vec::new();
+        for transfer in display.di.transfers() {
+            match transfer {
+                Transfer::Command(0x2A, args) => columns = (args[1], args[3]),
+                Transfer::Command(0x2B, args) => rows = (args[1], args[3]),
+                Transfer::Command(..) => {}
+                Transfer::Data(data) => writes.push(Write {
+                    x: usize::from(columns.0),
+                    y: usize::from(rows.0),
+                    width: usize::from(columns.1 - columns.0 + 1),
+                    height: usize::from(rows.1 - rows.0 + 1),
+                    data: data.clone(),
+                }),
+            }
+        }
+        writes
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Write {
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        data: Vec<u8>,
+    }
+
+    /// Returns the expected write of an area of the framebuffer.
+    fn write<BUF: RawBufferBackendMut>(
+        framebuffer: &RawFrameBuf<Rgb565, BUF, 2>,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> Write {
+        let bytes = framebuffer.as_bytes();
+        let data = (y..y + height)
+            .flat_map(|y| &bytes[(y * WIDTH + x) * 2..(y * WIDTH + x + width) * 2])
+            .copied()
+            .collect();
+        Write {
+            x,
+            y,
+            width,
+            height,
+            data,
+        }
+    }
+
+    fn set_pixels<BUF: RawBufferBackendMut>(
+        framebuffer: &mut RawFrameBuf<Rgb565, BUF, 2>,
+        points: impl IntoIterator<Item = (i32, i32)>,
+        color: Rgb565,
+    ) {
+        framebuffer
+            .draw_iter(
+                points
+                    .into_iter()
+                    .map(|(x, y)| Pixel(Point::new(x, y), color)),
+            )
+            .unwrap();
+    }
+
+    fn set_rows<BUF: RawBufferBackendMut>(
+        framebuffer: &mut RawFrameBuf<Rgb565, BUF, 2>,
+        rows: core::ops::Range<i32>,
+        color: Rgb565,
+    ) {
+        let points = rows.flat_map(|y| (0..WIDTH as i32).map(move |x| (x, y)));
+        set_pixels(framebuffer, points, color);
+    }
+
+    /// Flushes a gradient frame, so that later flushes only send changes.
+    ///
+    /// The gradient is the first write of the display.
+    fn flushed_frame<'a>(
+        display: &mut MockDisplay<ST7789>,
+        buffer: &'a mut [u8],
+        diff: &mut FrameDiff<'_>,
+    ) -> RawFrameBuf<Rgb565, &'a mut [u8], 2> {
+        let mut framebuffer = RawFrameBuf::<Rgb565, _, 2>::new(buffer, WIDTH, HEIGHT);
+        for (i, byte) in framebuffer.as_mut_bytes().iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        block_on(display.flush_diff(&framebuffer, diff)).unwrap();
+        framebuffer
+    }
+
+    #[test]
+    fn first_flush_sends_whole_frame() {
+        let mut display = display();
+        let mut buffer = [0u8; WIDTH * HEIGHT * 2];
+        let mut shadow = [0u8; WIDTH * HEIGHT * 2];
+        let mut diff = FrameDiff::with_shadow(&mut shadow, 2);
+
+        let framebuffer = RawFrameBuf::<Rgb565, _, 2>::new(&mut buffer[..], WIDTH, HEIGHT);
+        block_on(display.flush_diff(&framebuffer, &mut diff)).unwrap();
+
+        assert_eq!(writes(&display), [write(&framebuffer, 0, 0, WIDTH, HEIGHT)]);
+
+        block_on(display.flush_diff(&framebuffer, &mut diff)).unwrap();
+        assert_eq!(writes(&display).len(), 1);
+    }
+
+    #[test]
+    fn sends_changed_segment() {
+        let mut display = display();
+        let mut buffer = [0u8; WIDTH * HEIGHT * 2];
+        let mut hashes = [0u32; diff_hash_len(WIDTH, HEIGHT, 2)];
+        let mut diff = FrameDiff::with_hashes(&mut hashes, 2);
+        let mut framebuffer = flushed_frame(&mut display, &mut buffer, &mut diff);
+
+        set_pixels(&mut framebuffer, [(3, 1)], Rgb565::WHITE);
+        block_on(display.flush_diff(&framebuffer, &mut diff)).unwrap();
+
+        assert_eq!(writes(&display)[1..], [write(&framebuffer, 2, 1, 2, 1)]);
+    }
+
+    #[test]
+    fn merges_adjacent_segments() {
+        let mut display = display();
+        let mut buffer = [0u8; WIDTH * HEIGHT * 2];
+        let mut shadow = [0u8; WIDTH * HEIGHT * 2];
+        let mut diff = FrameDiff::with_shadow(&mut shadow, 2);
+        let mut framebuffer = flushed_frame(&mut display, &mut buffer, &mut diff);
+
+        set_pixels(&mut framebuffer, [(0, 2), (3, 2), (5, 3)], Rgb565::WHITE);
+        block_on(display.flush_diff(&framebuffer, &mut diff)).unwrap();
+
+        assert_eq!(
+            writes(&display)[1..],
+            [
+                write(&framebuffer, 0, 2, 4, 1),
+                write(&framebuffer, 4, 3, 2, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn merges_full_rows() {
+        let mut display = display();
+        let mut buffer = [0u8; WIDTH * HEIGHT * 2];
+        let mut shadow = [0u8; WIDTH * HEIGHT * 2];
+        let mut diff = FrameDiff::with_shadow(&mut shadow, 2);
+        let mut framebuffer = flushed_frame(&mut display, &mut buffer, &mut diff);
+
+        set_rows(&mut framebuffer, 1..3, Rgb565::WHITE);
+        block_on(display.flush_diff(&framebuffer, &mut diff)).unwrap();
+        assert_eq!(writes(&display)[1..], [write(&framebuffer, 0, 1, WIDTH, 2)]);
+
+        // The collected rows are also sent if they end at the last row.
+        set_rows(&mut framebuffer, 2..4, Rgb565::RED);
+        block_on(display.flush_diff(&framebuffer, &mut diff)).unwrap();
+        assert_eq!(writes(&display)[2..], [write(&framebuffer, 0, 2, WIDTH, 2)]);
+    }
+
+    #[test]
+    fn sends_everything_after_error() {
+        let mut display = display();
+        let mut buffer = [0u8; WIDTH * HEIGHT * 2];
+        let mut shadow = [0u8; WIDTH * HEIGHT * 2];
+        let mut diff = FrameDiff::with_shadow(&mut shadow, 2);
+        let mut framebuffer = flushed_frame(&mut display, &mut buffer, &mut diff);
+
+        set_pixels(&mut framebuffer, [(0, 0)], Rgb565::WHITE);
+        display.di.fail_next_transfer();
+        assert_eq!(
+            block_on(display.flush_diff(&framebuffer, &mut diff)),
+            Err(MockError)
+        );
+
+        block_on(display.flush_diff(&framebuffer, &mut diff)).unwrap();
+        assert_eq!(
+            writes(&display)[1..],
+            [write(&framebuffer, 0, 0, WIDTH, HEIGHT)]
+        );
+    }
+
+    #[test]
+    fn shadow_detects_changed_segments() {
+        let mut shadow = [0u8; 8];
+        let mut diff = FrameDiff::with_shadow(&mut shadow, 2);
+
+        assert!(!diff.segment_changed(0, 0, &[0, 0]));
+        assert!(diff.segment_changed(1, 2, &[0, 1]));
+        assert!(!diff.segment_changed(1, 2, &[0, 1]));
+        assert_eq!(shadow, [0, 0, 0, 1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn hashes_detect_changed_segments() {
+        let mut hashes = [0u32; 2];
+        let mut diff = FrameDiff::with_hashes(&mut hashes, 4);
+
+        assert!(diff.segment_changed(1, 0, &[1, 2, 3]));
+        assert!(!diff.segment_changed(1, 0, &[1, 2, 3]));
+        assert!(diff.segment_changed(1, 0, &[1, 2, 4]));
+        assert!(diff.segment_changed(0, 0, &[1, 2, 4]));
+    }
+
+    #[test]
+    fn hash_len() {
+        assert_eq!(diff_hash_len(240, 320, 16), 15 * 320);
+        assert_eq!(diff_hash_len(10, 2, 4), 6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn checks_buffer_len() {
+        let mut buffer = [0u8; 2 * 2 * 3];
+        let framebuffer = RawFrameBuf::<Gray8, _, 3>::new(&mut buffer[..], 2, 2);
+        let mut hashes = [0u32; 3];
+        let diff = FrameDiff::with_hashes(&mut hashes, 1);
+        diff.check_len(framebuffer.width(), framebuffer.height(), 3);
+    }
+}
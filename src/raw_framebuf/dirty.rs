@@ -0,0 +1,180 @@
+//! Dirty rectangle tracking.
+
+use embedded_graphics::{
+    prelude::{Point, Size},
+    primitives::Rectangle,
+};
+
+/// Maximum number of rectangles tracked by a [`DirtyRegion`].
+pub const MAX_DIRTY_RECTS: usize = 4;
+
+/// Set of rectangles which were changed since the last flush.
+///
+/// Added areas that overlap or touch an existing rectangle are merged into
+/// their bounding box. If all [`MAX_DIRTY_RECTS`] slots are in use, a new
+/// area is merged with the rectangle whose bounding box grows the least.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirtyRegion {
+    rects: [Rectangle; MAX_DIRTY_RECTS],
+    len: usize,
+}
+
+impl DirtyRegion {
+    /// Creates an empty dirty region.
+    pub const fn new() -> Self {
+        Self {
+            rects: [Rectangle::zero(); MAX_DIRTY_RECTS],
+            len: 0,
+        }
+    }
+
+    /// Returns the dirty rectangles.
+    pub fn as_slice(&self) -> &[Rectangle] {
+        &self.rects[..self.len]
+    }
+
+    /// Returns `true` if nothing was changed.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes all rectangles.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Adds an area to the dirty region.
+    pub fn add(&mut self, area: Rectangle) {
+        if area.is_zero_sized() {
+            return;
+        }
+
+        let mut area = area;
+        loop {
+            let touching = self.as_slice().iter().position(|r| touches(r, &area));
+            let index = match touching {
+                Some(index) => index,
+                None if self.len < MAX_DIRTY_RECTS => {
+                    self.rects[self.len] = area;
+                    self.len += 1;
+                    return;
+                }
+                None => self.cheapest_merge(&area),
+            };
+
+            area = envelope(&self.rects[index], &area);
+            self.remove(index);
+        }
+    }
+
+    /// Returns the index of the rectangle which grows the least if merged with `area`.
+    fn cheapest_merge(&self, area: &Rectangle) -> usize {
+        let mut best = (0, u64::MAX);
+        for (index, rect) in self.as_slice().iter().enumerate() {
+            let growth = size(&envelope(rect, area)) - size(rect);
+            if growth < best.1 {
+                best = (index, growth);
+            }
+        }
+        best.0
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.rects[index] = self.rects[self.len - 1];
+        self.len -= 1;
+    }
+}
+
+impl Default for DirtyRegion {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn size(rect: &Rectangle) -> u64 {
+    u64::from(rect.size.width) * u64::from(rect.size.height)
+}
+
+/// Returns `true` if the rectangles overlap or share an edge.
+fn touches(a: &Rectangle, b: &Rectangle) -> bool {
+    let (a_end, b_end) = (end(a), end(b));
+
+    a.top_left.x <= b_end.x
+        && b.top_left.x <= a_end.x
+        && a.top_left.y <= b_end.y
+        && b.top_left.y <= a_end.y
+}
+
+/// Returns the smallest rectangle which contains both rectangles.
+fn envelope(a: &Rectangle, b: &Rectangle) -> Rectangle {
+    let (a_end, b_end) = (end(a), end(b));
+    let top_left = a.top_left.component_min(b.top_left);
+    let bottom_right = a_end.component_max(b_end);
+
+    Rectangle::new(
+        top_left,
+        Size::new(
+            (bottom_right.x - top_left.x) as u32,
+            (bottom_right.y - top_left.y) as u32,
+        ),
+    )
+}
+
+/// Returns the exclusive bottom right corner.
+fn end(rect: &Rectangle) -> Point {
+    rect.top_left + rect.size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Rectangle {
+        Rectangle::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn merges_overlapping_and_touching_areas() {
+        let mut region = DirtyRegion::new();
+        region.add(rect(0, 0, 10, 10));
+        region.add(rect(5, 5, 10, 10));
+        assert_eq!(region.as_slice(), &[rect(0, 0, 15, 15)]);
+
+        region.add(rect(15, 0, 5, 5));
+        assert_eq!(region.as_slice(), &[rect(0, 0, 20, 15)]);
+    }
+
+    #[test]
+    fn keeps_separate_areas() {
+        let mut region = DirtyRegion::new();
+        region.add(rect(0, 0, 2, 2));
+        region.add(rect(10, 10, 2, 2));
+        region.add(rect(0, 0, 0, 5));
+        assert_eq!(region.as_slice(), &[rect(0, 0, 2, 2), rect(10, 10, 2, 2)]);
+
+        region.clear();
+        assert!(region.is_empty());
+    }
+
+    #[test]
+    fn merges_cheapest_rectangle_when_full() {
+        let mut region = DirtyRegion::new();
+        region.add(rect(0, 0, 2, 2));
+        region.add(rect(10, 0, 2, 2));
+        region.add(rect(20, 0, 2, 2));
+        region.add(rect(30, 0, 2, 2));
+
+        region.add(rect(23, 0, 2, 2));
+        assert_eq!(region.as_slice().len(), MAX_DIRTY_RECTS);
+        assert!(region.as_slice().contains(&rect(20, 0, 5, 2)));
+    }
+
+    #[test]
+    fn merge_cascades() {
+        let mut region = DirtyRegion::new();
+        region.add(rect(0, 0, 2, 2));
+        region.add(rect(10, 0, 2, 2));
+        region.add(rect(0, 0, 12, 1));
+        assert_eq!(region.as_slice(), &[rect(0, 0, 12, 2)]);
+    }
+}
@@ -0,0 +1,486 @@
+//! Palette based framebuffer.
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, OriginDimensions},
+    pixelcolor::{PixelColor, Rgb888, RgbColor},
+    prelude::{Point, Size},
+    primitives::Rectangle,
+    Pixel,
+};
+use embedded_hal::digital::OutputPin;
+
+use crate::{
+    interface::{Interface, InterfacePixelFormat},
+    models::Model,
+    Display,
+};
+
+use super::RawBufferBackendMut;
+
+/// Returns the number of bytes required by an [`IndexedFrameBuf`].
+///
+/// Every row starts at a byte boundary.
+pub const fn indexed_len(width: usize, height: usize, bits: usize) -> usize {
+    (width * bits).div_ceil(8) * height
+}
+
+/// Color type used to draw palette indices into an [`IndexedFrameBuf`].
+///
+/// See [`IndexedFrameBuf::indices`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaletteIndex(pub u8);
+
+impl PixelColor for PaletteIndex {
+    type Raw = ();
+}
+
+struct BitsCheck<const BITS: usize>;
+
+impl<const BITS: usize> BitsCheck<BITS> {
+    const CHECK: () = assert!(
+        matches!(BITS, 1 | 2 | 4 | 8),
+        "IndexedFrameBuf only supports 1, 2, 4 or 8 bits per pixel."
+    );
+}
+
+/// A framebuffer that stores `BITS` bit palette indices instead of colors.
+///
+/// Pixels are packed most significant bits first and every row starts at a
+/// byte boundary. Colors drawn into the framebuffer are replaced by the index
+/// of the nearest palette entry, use [`indices`](Self::indices) to draw
+/// palette indices directly.
+///
+/// The indices are expanded to the wire format of the display while they are
+/// sent by [`Display::show_indexed`]. Indices which are outside the palette
+/// are shown using the first palette entry.
+pub struct IndexedFrameBuf<'a, C, BUF, const BITS: usize>
+where
+    C: PixelColor,
+    BUF: RawBufferBackendMut,
+{
+    buffer: BUF,
+    width: usize,
+    height: usize,
+    palette: &'a [C],
+}
+
+impl<'a, C, BUF, const BITS: usize> IndexedFrameBuf<'a, C, BUF, BITS>
+where
+    C: PixelColor,
+    BUF: RawBufferBackendMut,
+{
+    /// Creates a new indexed framebuffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is too small, see [`indexed_len`], or if the
+    /// palette is empty or has more than `2^BITS` entries.
+    pub fn new(buffer: BUF, width: usize, height: usize, palette: &'a [C]) -> Self {
+        let () = BitsCheck::<BITS>::CHECK;
+
+        let expected_len = indexed_len(width, height, BITS);
+        assert!(
+            buffer.u8_len() >= expected_len,
+            "IndexedFrameBuf underlying buffer is too small. Expected at least {}, got {}.",
+            expected_len,
+            buffer.u8_len()
+        );
+
+        let mut framebuffer = Self {
+            buffer,
+            width,
+            height,
+            palette,
+        };
+        framebuffer.set_palette(palette);
+        framebuffer
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the palette.
+    pub fn palette(&self) -> &'a [C] {
+        self.palette
+    }
+
+    /// Replaces the palette.
+    ///
+    /// The stored indices are kept, which allows palette animations without
+    /// redrawing the framebuffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the palette is empty or has more than `2^BITS` entries.
+    pub fn set_palette(&mut self, palette: &'a [C]) {
+        assert!(
+            !palette.is_empty() && palette.len() <= 1 << BITS,
+            "Palette must contain between 1 and {} colors, got {}.",
+            1 << BITS,
+            palette.len()
+        );
+        self.palette = palette;
+    }
+
+    /// Returns the palette index of the pixel at `point`.
+    pub fn index(&self, point: Point) -> Option<u8> {
+        self.contains(point)
+            .then(|| self.index_at(point.x as usize, point.y as usize))
+    }
+
+    /// Returns a draw target which draws palette indices into this framebuffer.
+    ///
+    /// Only the lower `BITS` bits of every index are stored.
+    pub fn indices(&mut self) -> Indices<'_, 'a, C, BUF, BITS> {
+        Indices { framebuffer: self }
+    }
+
+    /// Returns the colors of all pixels in row major order.
+    pub fn colors(&self) -> impl Iterator<Item = C> + '_ {
+        (0..self.height).flat_map(move |y| {
+            (0..self.width).map(move |x| {
+                let index = usize::from(self.index_at(x, y));
+                self.palette.get(index).copied().unwrap_or(self.palette[0])
+            })
+        })
+    }
+
+    /// Releases the underlying buffer.
+    pub fn into_inner(self) -> BUF {
+        self.buffer
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        let expected_len = indexed_len(self.width, self.height, BITS);
+        &self.buffer.as_u8_slice()[0..expected_len]
+    }
+
+    pub fn as_mut_bytes(&mut self) -> &mut [u8] {
+        let expected_len = indexed_len(self.width, self.height, BITS);
+        &mut self.buffer.as_mut_u8_slice()[0..expected_len]
+    }
+
+    fn contains(&self, point: Point) -> bool {
+        point.x >= 0
+            && point.y >= 0
+            && (point.x as usize) < self.width
+            && (point.y as usize) < self.height
+    }
+
+    /// Returns the byte index and bit shift of a pixel.
+    fn position(&self, x: usize, y: usize) -> (usize, usize) {
+        let stride = (self.width * BITS).div_ceil(8);
+        let bit = x * BITS;
+        (y * stride + bit / 8, 8 - BITS - bit % 8)
+    }
+
+    fn index_at(&self, x: usize, y: usize) -> u8 {
+        let (byte, shift) = self.position(x, y);
+        (self.buffer.as_u8_slice()[byte] >> shift) & Self::MASK
+    }
+
+    fn set_index(&mut self, x: usize, y: usize, index: u8) {
+        let (byte, shift) = self.position(x, y);
+        let byte = &mut self.buffer.as_mut_u8_slice()[byte];
+        *byte = (*byte & !(Self::MASK << shift)) | ((index & Self::MASK) << shift);
+    }
+
+    fn fill_index(&mut self, area: &Rectangle, index: u8) {
+        let area = area.intersection(&self.bounding_box());
+        for y in area.rows() {
+            for x in area.columns() {
+                self.set_index(x as usize, y as usize, index);
+            }
+        }
+    }
+
+    fn clear_index(&mut self, index: u8) {
+        let mut pattern = 0;
+        for shift in (0..8).step_by(BITS) {
+            pattern |= (index & Self::MASK) << shift;
+        }
+        self.as_mut_bytes().fill(pattern);
+    }
+
+    const MASK: u8 = (((1u16) << BITS) - 1) as u8;
+}
+
+impl<C, BUF, const BITS: usize> IndexedFrameBuf<'_, C, BUF, BITS>
+where
+    C: PixelColor + Into<Rgb888>,
+    BUF: RawBufferBackendMut,
+{
+    /// Returns the index of the palette entry which is closest to `color`.
+    ///
+    /// The distance is the squared euclidean distance in the Rgb888 color space.
+    pub fn nearest(&self, color: C) -> u8 {
+        let target = color.into();
+        let mut best = (0, u32::MAX);
+        for (index, entry) in self.palette.iter().enumerate() {
+            if *entry == color {
+                return index as u8;
+            }
+
+            let distance = distance(target, (*entry).into());
+            if distance < best.1 {
+                best = (index as u8, distance);
+            }
+        }
+        best.0
+    }
+}
+
+fn distance(a: Rgb888, b: Rgb888) -> u32 {
+    let channel = |a: u8, b: u8| (i32::from(a) - i32::from(b)).unsigned_abs().pow(2);
+    channel(a.r(), b.r()) + channel(a.g(), b.g()) + channel(a.b(), b.b())
+}
+
+impl<C, BUF, const BITS: usize> OriginDimensions for IndexedFrameBuf<'_, C, BUF, BITS>
+where
+    C: PixelColor,
+    BUF: RawBufferBackendMut,
+{
+    fn size(&self) -> Size {
+        Size::new(self.width as u32, self.height as u32)
+    }
+}
+
+impl<C, BUF, const BITS: usize> DrawTarget for IndexedFrameBuf<'_, C, BUF, BITS>
+where
+    C: PixelColor + Into<Rgb888>,
+    BUF: RawBufferBackendMut,
+{
+    type Color = C;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        // Consecutive pixels often share a color, avoid searching the palette again.
+        let mut last: Option<(C, u8)> = None;
+        for Pixel(point, color) in pixels {
+            if !self.contains(point) {
+                continue;
+            }
+
+            let index = match last {
+                Some((last_color, index)) if last_color == color => index,
+                _ => self.nearest(color),
+            };
+            last = Some((color, index));
+            self.set_index(point.x as usize, point.y as usize, index);
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let index = self.nearest(color);
+        self.fill_index(area, index);
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let index = self.nearest(color);
+        self.clear_index(index);
+        Ok(())
+    }
+}
+
+/// Draw target which draws palette indices into an [`IndexedFrameBuf`].
+///
+/// Created by [`IndexedFrameBuf::indices`].
+pub struct Indices<'f, 'a, C, BUF, const BITS: usize>
+where
+    C: PixelColor,
+    BUF: RawBufferBackendMut,
+{
+    framebuffer: &'f mut IndexedFrameBuf<'a, C, BUF, BITS>,
+}
+
+impl<C, BUF, const BITS: usize> OriginDimensions for Indices<'_, '_, C, BUF, BITS>
+where
+    C: PixelColor,
+    BUF: RawBufferBackendMut,
+{
+    fn size(&self) -> Size {
+        self.framebuffer.size()
+    }
+}
+
+impl<C, BUF, const BITS: usize> DrawTarget for Indices<'_, '_, C, BUF, BITS>
+where
+    C: PixelColor,
+    BUF: RawBufferBackendMut,
+{
+    type Color = PaletteIndex;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, PaletteIndex(index)) in pixels {
+            if self.framebuffer.contains(point) {
+                self.framebuffer
+                    .set_index(point.x as usize, point.y as usize, index);
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.framebuffer.fill_index(area, color.0);
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.framebuffer.clear_index(color.0);
+        Ok(())
+    }
+}
+
+impl<DI, M, RST> Display<DI, M, RST>
+where
+    DI: Interface,
+    M: Model,
+    RST: OutputPin,
+{
+    /// Sends an indexed framebuffer to the display.
+    ///
+    /// The framebuffer is drawn with its top left corner at `top_left` and
+    /// clipped to the display. The palette indices are expanded to the wire
+    /// format in small chunks during the transfer, see [`set_pixels`](Self::set_pixels).
+    pub async fn show_indexed<C, BUF, const BITS: usize>(
+        &mut self,
+        top_left: Point,
+        framebuffer: &IndexedFrameBuf<'_, C, BUF, BITS>,
+    ) -> Result<(), DI::Error>
+    where
+        C: InterfacePixelFormat<DI::Word>,
+        DI::Word: Default,
+        BUF: RawBufferBackendMut,
+    {
+        let area = Rectangle::new(top_left, framebuffer.size());
+        self.set_pixels(&area, framebuffer.colors()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::{
+        pixelcolor::Rgb565,
+        prelude::{Primitive, WebColors},
+        primitives::PrimitiveStyle,
+        Drawable,
+    };
+
+    use super::*;
+
+    const PALETTE: [Rgb565; 4] = [
+        Rgb565::BLACK,
+        Rgb565::RED,
+        Rgb565::GREEN,
+        Rgb565::CSS_DARK_BLUE,
+    ];
+
+    #[test]
+    fn packs_indices_msb_first_with_row_padding() {
+        let mut buffer = [0u8; 4];
+        let mut fb = IndexedFrameBuf::<_, _, 2>::new(&mut buffer[..], 5, 2, &PALETTE);
+
+        let mut indices = fb.indices();
+        Pixel(Point::new(0, 0), PaletteIndex(1))
+            .draw(&mut indices)
+            .unwrap();
+        Pixel(Point::new(4, 0), PaletteIndex(3))
+            .draw(&mut indices)
+            .unwrap();
+        Pixel(Point::new(1, 1), PaletteIndex(2))
+            .draw(&mut indices)
+            .unwrap();
+        Pixel(Point::new(5, 1), PaletteIndex(2))
+            .draw(&mut indices)
+            .unwrap();
+
+        assert_eq!(fb.as_bytes(), &[0b0100_0000, 0b1100_0000, 0b0010_0000, 0]);
+        assert_eq!(fb.index(Point::new(4, 0)), Some(3));
+        assert_eq!(fb.index(Point::new(5, 0)), None);
+    }
+
+    #[test]
+    fn draws_nearest_colors() {
+        let mut buffer = [0u8; 8];
+        let mut fb = IndexedFrameBuf::<_, _, 4>::new(&mut buffer[..], 4, 4, &PALETTE);
+
+        assert_eq!(fb.nearest(Rgb565::CSS_DARK_RED), 1);
+        assert_eq!(fb.nearest(Rgb565::BLUE), 3);
+
+        Rectangle::new(Point::new(1, 1), Size::new(2, 2))
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::CSS_LIME))
+            .draw(&mut fb)
+            .unwrap();
+        assert_eq!(fb.as_bytes(), &[0, 0, 0x02, 0x20, 0x02, 0x20, 0, 0]);
+
+        fb.clear(Rgb565::CSS_ORANGE_RED).unwrap();
+        assert_eq!(fb.as_bytes(), &[0x11; 8]);
+    }
+
+    #[test]
+    fn expands_indices_to_colors() {
+        let mut buffer = [0u8; 2];
+        let mut fb = IndexedFrameBuf::<_, _, 1>::new(&mut buffer[..], 3, 2, &PALETTE[..2]);
+        fb.as_mut_bytes()[0] = 0b1010_0000;
+
+        assert!(fb.colors().eq([
+            Rgb565::RED,
+            Rgb565::BLACK,
+            Rgb565::RED,
+            Rgb565::BLACK,
+            Rgb565::BLACK,
+            Rgb565::BLACK,
+        ]));
+    }
+
+    #[test]
+    fn shows_partly_visible_framebuffer() {
+        extern crate std;
+
+        use embassy_futures::block_on;
+        use std::vec;
+
+        use crate::{
+            models::ST7789,
+            raw_framebuf::IntoRawBytes,
+            testing::{mock_display, MockInterface, Transfer},
+        };
+
+        let mut buffer = [0u8; 3];
+        let mut fb = IndexedFrameBuf::<_, _, 2>::new(&mut buffer[..], 3, 3, &PALETTE);
+        // Rows 0 to 2: 0 1 2, 3 0 1, 2 3 0
+        fb.as_mut_bytes()
+            .copy_from_slice(&[0b0001_1000, 0b1100_0100, 0b1011_0000]);
+
+        let mut display = mock_display(ST7789, MockInterface::new(), (4, 4));
+        block_on(display.show_indexed(Point::new(-1, -2), &fb)).unwrap();
+
+        let dark_blue = Rgb565::CSS_DARK_BLUE.into_raw_bytes();
+        display.di.assert_transfers(&[
+            Transfer::Command(0x2A, vec![0, 0, 0, 1]),
+            Transfer::Command(0x2B, vec![0, 0, 0, 0]),
+            Transfer::Command(0x2C, vec![]),
+            Transfer::Data([dark_blue, [0, 0]].concat()),
+        ]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn palette_must_fit_bits() {
+        IndexedFrameBuf::<_, _, 1>::new([0u8; 1], 1, 1, &PALETTE);
+    }
+}
@@ -0,0 +1,346 @@
+//! Upscaled framebuffer flushes.
+
+use embedded_hal::digital::OutputPin;
+
+use crate::{
+    interface::{Interface, RawDataWord, PIXEL_CHUNK_WORDS},
+    models::Model,
+    Display,
+};
+
+use super::{IntoRawBytes, RawBufferBackendMut, RawFrameBuf};
+
+/// Fills `buffer` with pixels from `pixels` and returns the number of words written.
+///
+/// Every pixel is converted into `N / W::BYTES` words. Only whole pixels are
+/// written to the buffer.
+fn fill_words<'a, W, const N: usize>(
+    pixels: &mut impl Iterator<Item = &'a [u8]>,
+    buffer: &mut [W],
+) -> usize
+where
+    W: RawDataWord,
+{
+    let pixel_words = N / W::BYTES;
+    let mut len = 0;
+    while len + pixel_words <= buffer.len() {
+        let Some(pixel) = pixels.next() else {
+            break;
+        };
+        for (word, bytes) in buffer[len..len + pixel_words]
+            .iter_mut()
+            .zip(pixel.chunks_exact(W::BYTES))
+        {
+            *word = W::from_be_bytes(bytes);
+        }
+        len += pixel_words;
+    }
+    len
+}
+
+/// Returns the pixels of a framebuffer row, with every pixel repeated `scale` times.
+fn scaled_row<const N: usize>(row: &[u8], scale: usize) -> impl Iterator<Item = &[u8]> {
+    row.chunks_exact(N)
+        .flat_map(move |pixel| core::iter::repeat(pixel).take(scale))
+}
+
+impl<DI, M, RST> Display<DI, M, RST>
+where
+    DI: Interface,
+    DI::Word: RawDataWord,
+    M: Model,
+    RST: OutputPin,
+{
+    /// Sends a framebuffer to the display and enlarges every pixel to `scale` x `scale` pixels.
+    ///
+    /// The framebuffer is shown at the top left corner of the display and the
+    /// enlarged image is clipped to the display. The enlarged pixels are
+    /// created on the fly in a small buffer on the stack while the data is
+    /// streamed through a single address window.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scale` is `0` or if the pixel size isn't a multiple of the
+    /// interface word size.
+    pub async fn show_scaled<C, BUF, const N: usize>(
+        &mut self,
+        framebuffer: &RawFrameBuf<C, BUF, N>,
+        scale: usize,
+    ) -> Result<(), DI::Error>
+    where
+        C: IntoRawBytes<N>,
+        BUF: RawBufferBackendMut,
+    {
+        let mut buffer = [DI::Word::default(); PIXEL_CHUNK_WORDS];
+        self.show_scaled_with_buffer(framebuffer, scale, &mut buffer)
+            .await
+    }
+
+    /// Sends an enlarged framebuffer using a user supplied scratch buffer.
+    ///
+    /// If the buffer can hold an entire enlarged row, every row is only
+    /// expanded once and sent `scale` times. Otherwise rows are expanded in
+    /// buffer sized chunks. See [`show_scaled`](Self::show_scaled) for more
+    /// information.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scale` is `0`, if the pixel size isn't a multiple of the
+    /// interface word size or if `buffer` can't hold at least one pixel.
+    pub async fn show_scaled_with_buffer<C, BUF, const N: usize>(
+        &mut self,
+        framebuffer: &RawFrameBuf<C, BUF, N>,
+        scale: usize,
+        buffer: &mut [DI::Word],
+    ) -> Result<(), DI::Error>
+    where
+        C: IntoRawBytes<N>,
+        BUF: RawBufferBackendMut,
+    {
+        assert!(scale > 0, "Scale must not be 0.");
+        assert!(
+            N % DI::Word::BYTES == 0,
+            "Pixel size must be a multiple of the interface word size."
+        );
+        let pixel_words = N / DI::Word::BYTES;
+        assert!(
+            buffer.len() >= pixel_words,
+            "Scale buffer is too small. Expected at least {}, got {}.",
+            pixel_words,
+            buffer.len()
+        );
+
+        let (width, height) = (framebuffer.width(), framebuffer.height());
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        // Clip the enlarged image to the display.
+        let (display_width, display_height) = self.options.display_size();
+        let scaled_width = (width * scale).min(usize::from(display_width));
+        let scaled_height = (height * scale).min(usize::from(display_height));
+        if scaled_width == 0 || scaled_height == 0 {
+            return Ok(());
+        }
+
+        self.set_address_window(0, 0, scaled_width as u16 - 1, scaled_height as u16 - 1)
+            .await?;
+        M::write_memory_start(&mut self.di).await?;
+
+        let row_words = scaled_width * pixel_words;
+        let rows = framebuffer.as_bytes().chunks_exact(width * N);
+        for (y, row) in rows.take(scaled_height.div_ceil(scale)).enumerate() {
+            let repeats = scale.min(scaled_height - y * scale);
+            if buffer.len() >= row_words {
+                fill_words::<_, N>(&mut scaled_row::<N>(row, scale).take(scaled_width), buffer);
+                for _ in 0..repeats {
+                    self.di.send_data_slice(&buffer[..row_words]).await?;
+                }
+            } else {
+                for _ in 0..repeats {
+                    let mut pixels = scaled_row::<N>(row, scale).take(scaled_width);
+                    loop {
+                        let len = fill_words::<_, N>(&mut pixels, buffer);
+                        if len == 0 {
+                            break;
+                        }
+                        self.di.send_data_slice(&buffer[..len]).await?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use embassy_futures::block_on;
+    use embedded_graphics::{
+        pixelcolor::Rgb565,
+        prelude::{DrawTarget, Point, RgbColor, WebColors},
+        Pixel,
+    };
+    use std::{vec, vec::Vec};
+
+    use crate::{
+        models::ST7789,
+        testing::{mock_display, MockDisplay, MockInterface, MockKind, Parallel16Bit, Transfer},
+    };
+
+    use super::*;
+
+    const COLORS: [Rgb565; 4] = [Rgb565::RED, Rgb565::GREEN, Rgb565::BLUE, Rgb565::CSS_ORANGE];
+
+    /// Shows a 2x2 framebuffer with the colors in `COLORS` enlarged by 2.
+    fn show<K: MockKind>(display: &mut MockDisplay<ST7789, K>, buffer_len: usize)
+    where
+        K::Word: RawDataWord,
+    {
+        let mut bytes = [0u8; 2 * 2 * 2];
+        let mut framebuffer = RawFrameBuf::<Rgb565, _, 2>::new(&mut bytes[..], 2, 2);
+        let pixels = [(0, 0), (1, 0), (0, 1), (1, 1)]
+            .into_iter()
+            .zip(COLORS)
+            .map(|((x, y), color)| Pixel(Point::new(x, y), color));
+        framebuffer.draw_iter(pixels).unwrap();
+
+        let mut buffer = vec![K::Word::default(); buffer_len];
+        block_on(display.show_scaled_with_buffer(&framebuffer, 2, &mut buffer)).unwrap();
+    }
+
+    fn bytes(colors: &[usize]) -> Vec<u8> {
+        colors
+            .iter()
+            .flat_map(|&i| COLORS[i].into_raw_bytes())
+            .collect()
+    }
+
+    fn words(colors: &[usize]) -> Vec<u16> {
+        colors
+            .iter()
+            .map(|&i| u16::from_be_bytes(COLORS[i].into_raw_bytes()))
+            .collect()
+    }
+
+    /// Returns the commands which start a write to the top left `columns` x `rows` pixels.
+    fn window<W>(columns: u8, rows: u8) -> [Transfer<W>; 3] {
+        [
+            Transfer::Command(0x2A, vec![0, 0, 0, columns - 1]),
+            Transfer::Command(0x2B, vec![0, 0, 0, rows - 1]),
+            Transfer::Command(0x2C, vec![]),
+        ]
+    }
+
+    #[test]
+    fn expands_rows_into_words() {
+        let row = [0x12, 0x34, 0x56, 0x78];
+
+        let mut bytes = [0u8; 8];
+        assert_eq!(
+            fill_words::<u8, 2>(&mut scaled_row::<2>(&row, 2), &mut bytes),
+            8
+        );
+        assert_eq!(bytes, [0x12, 0x34, 0x12, 0x34, 0x56, 0x78, 0x56, 0x78]);
+
+        let mut words = [0u16; 3];
+        let mut pixels = scaled_row::<2>(&row, 3);
+        assert_eq!(fill_words::<u16, 2>(&mut pixels, &mut words), 3);
+        assert_eq!(words, [0x1234, 0x1234, 0x1234]);
+        assert_eq!(fill_words::<u16, 2>(&mut pixels, &mut words), 3);
+        assert_eq!(words, [0x5678; 3]);
+        assert_eq!(fill_words::<u16, 2>(&mut pixels, &mut words), 0);
+    }
+
+    #[test]
+    fn fills_whole_pixels() {
+        let row = [1, 2, 3, 4, 5, 6];
+        let mut bytes = [0u8; 5];
+        let mut pixels = scaled_row::<3>(&row, 1);
+        assert_eq!(fill_words::<u8, 3>(&mut pixels, &mut bytes), 3);
+        assert_eq!(&bytes[..3], &[1, 2, 3]);
+        assert_eq!(fill_words::<u8, 3>(&mut pixels, &mut bytes), 3);
+        assert_eq!(&bytes[..3], &[4, 5, 6]);
+    }
+
+    #[test]
+    fn shows_scaled_u8_rows() {
+        let mut display = mock_display(ST7789, MockInterface::new(), (8, 8));
+        show(&mut display, 8);
+
+        let (top, bottom) = (bytes(&[0, 0, 1, 1]), bytes(&[2, 2, 3, 3]));
+        display.di.assert_transfers(
+            &[
+                &window(4, 4)[..],
+                &[
+                    Transfer::Data(top.clone()),
+                    Transfer::Data(top),
+                    Transfer::Data(bottom.clone()),
+                    Transfer::Data(bottom),
+                ],
+            ]
+            .concat(),
+        );
+    }
+
+    #[test]
+    fn shows_scaled_u8_chunks() {
+        let mut display = mock_display(ST7789, MockInterface::new(), (8, 8));
+        show(&mut display, 5);
+
+        let data = [
+            [0, 0],
+            [1, 1],
+            [0, 0],
+            [1, 1],
+            [2, 2],
+            [3, 3],
+            [2, 2],
+            [3, 3],
+        ]
+        .map(|colors| Transfer::Data(bytes(&colors)));
+        display
+            .di
+            .assert_transfers(&[&window(4, 4)[..], &data].concat());
+    }
+
+    #[test]
+    fn shows_scaled_u16_rows() {
+        let mut display = mock_display(ST7789, MockInterface::<Parallel16Bit>::default(), (8, 8));
+        show(&mut display, 4);
+
+        let (top, bottom) = (words(&[0, 0, 1, 1]), words(&[2, 2, 3, 3]));
+        let mut expected = window(4, 4).to_vec();
+        expected.extend([
+            Transfer::Data(top.clone()),
+            Transfer::Data(top),
+            Transfer::Data(bottom.clone()),
+            Transfer::Data(bottom),
+        ]);
+        display.di.assert_transfers(&expected);
+    }
+
+    #[test]
+    fn shows_scaled_u16_chunks() {
+        let mut display = mock_display(ST7789, MockInterface::<Parallel16Bit>::default(), (8, 8));
+        show(&mut display, 3);
+
+        let mut expected = window(4, 4).to_vec();
+        expected.extend(
+            [
+                [0, 0, 1].as_slice(),
+                &[1],
+                &[0, 0, 1],
+                &[1],
+                &[2, 2, 3],
+                &[3],
+                &[2, 2, 3],
+                &[3],
+            ]
+            .map(|colors| Transfer::Data(words(colors))),
+        );
+        display.di.assert_transfers(&expected);
+    }
+
+    #[test]
+    fn clips_to_display() {
+        let mut display = mock_display(ST7789, MockInterface::new(), (3, 3));
+        show(&mut display, 8);
+
+        let (top, bottom) = (bytes(&[0, 0, 1]), bytes(&[2, 2, 3]));
+        display.di.assert_transfers(
+            &[
+                &window(3, 3)[..],
+                &[
+                    Transfer::Data(top.clone()),
+                    Transfer::Data(top),
+                    Transfer::Data(bottom),
+                ],
+            ]
+            .concat(),
+        );
+    }
+}
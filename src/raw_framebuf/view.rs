@@ -0,0 +1,340 @@
+//! Sub-views of framebuffers and blitting.
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, OriginDimensions},
+    prelude::{Point, Size, Transform},
+    primitives::{PointsIter, Rectangle},
+    Pixel,
+};
+use embedded_hal::digital::OutputPin;
+
+use crate::{interface::Interface, models::Model, options::Orientation, Display};
+
+use super::{IntoRawBytes, RawBufferBackendMut, RawFrameBuf};
+
+/// A rectangular part of a [`RawFrameBuf`].
+///
+/// A view is a [`DrawTarget`] with its origin at the top left corner of the
+/// viewed area. Drawing operations are clipped to the area and update the
+/// dirty region of the framebuffer. Created by [`RawFrameBuf::sub_view`].
+pub struct RawView<'a, C, BUF, const N: usize>
+where
+    C: IntoRawBytes<N>,
+    BUF: RawBufferBackendMut,
+{
+    framebuffer: &'a mut RawFrameBuf<C, BUF, N>,
+    area: Rectangle,
+}
+
+impl<C, BUF, const N: usize> RawView<'_, C, BUF, N>
+where
+    C: IntoRawBytes<N>,
+    BUF: RawBufferBackendMut,
+{
+    /// Returns the viewed area in framebuffer coordinates.
+    pub fn area(&self) -> Rectangle {
+        self.area
+    }
+
+    /// Returns the framebuffer.
+    pub fn framebuffer(&self) -> &RawFrameBuf<C, BUF, N> {
+        self.framebuffer
+    }
+}
+
+impl<C, BUF, const N: usize> RawFrameBuf<C, BUF, N>
+where
+    C: IntoRawBytes<N>,
+    BUF: RawBufferBackendMut,
+{
+    /// Returns a view of a rectangular area of the framebuffer.
+    ///
+    /// The area is clipped to the framebuffer.
+    pub fn sub_view(&mut self, area: Rectangle) -> RawView<'_, C, BUF, N> {
+        let area = area.intersection(&self.bounding_box());
+        RawView {
+            framebuffer: self,
+            area,
+        }
+    }
+
+    /// Copies an area of another framebuffer into this framebuffer.
+    ///
+    /// The top left corner of `src_area` is copied to `dst`. Source pixels
+    /// which match `color_key` are transparent and aren't copied. The copied
+    /// area is clipped to both framebuffers.
+    pub fn blit<SRC>(
+        &mut self,
+        src: &RawFrameBuf<C, SRC, N>,
+        src_area: &Rectangle,
+        dst: Point,
+        color_key: Option<C>,
+    ) where
+        SRC: RawBufferBackendMut,
+    {
+        // Clip the source area to both framebuffers.
+        let src_area = src_area.intersection(&src.bounding_box());
+        let offset = dst - src_area.top_left;
+        let src_area = src_area.intersection(&self.bounding_box().translate(-offset));
+        if src_area.is_zero_sized() {
+            return;
+        }
+
+        let dst_area = src_area.translate(offset);
+        if color_key.is_none()
+            && self.orientation == Orientation::new()
+            && src.orientation == Orientation::new()
+        {
+            // Rows are contiguous in both buffers.
+            let len = src_area.size.width as usize * N;
+            let src_rows = src.area_rows(&src_area);
+            let dst_start = dst_area.top_left.x as usize * N;
+            let dst_stride = self.width * N;
+            let dst_bytes = self.buffer.as_mut_u8_slice();
+            for (y, row) in dst_area.rows().zip(src_rows) {
+                let start = y as usize * dst_stride + dst_start;
+                dst_bytes[start..start + len].copy_from_slice(row);
+            }
+        } else {
+            let key = color_key.map(IntoRawBytes::into_raw_bytes);
+            for point in src_area.points() {
+                let src_point = src.buffer_point(point);
+                let src_index = (src_point.y as usize * src.width + src_point.x as usize) * N;
+                let bytes = &src.buffer.as_u8_slice()[src_index..src_index + N];
+                if key.is_some_and(|key| key == bytes) {
+                    continue;
+                }
+
+                let dst_point = self.buffer_point(point + offset);
+                let dst_index = (dst_point.y as usize * self.width + dst_point.x as usize) * N;
+                self.buffer.as_mut_u8_slice()[dst_index..dst_index + N].copy_from_slice(bytes);
+            }
+        }
+
+        let dirty = self.buffer_area(&dst_area);
+        self.dirty.add(dirty);
+    }
+}
+
+impl<C, BUF, const N: usize> OriginDimensions for RawView<'_, C, BUF, N>
+where
+    C: IntoRawBytes<N>,
+    BUF: RawBufferBackendMut,
+{
+    fn size(&self) -> Size {
+        self.area.size
+    }
+}
+
+impl<C, BUF, const N: usize> DrawTarget for RawView<'_, C, BUF, N>
+where
+    C: IntoRawBytes<N>,
+    BUF: RawBufferBackendMut,
+{
+    type Color = C;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let bounds = self.bounding_box();
+        let offset = self.area.top_left;
+
+        self.framebuffer.draw_iter(
+            pixels
+                .into_iter()
+                .filter(|Pixel(point, _)| bounds.contains(*point))
+                .map(|Pixel(point, color)| Pixel(point + offset, color)),
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        self.framebuffer
+            .fill_solid(&area.translate(self.area.top_left), color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.framebuffer.fill_solid(&self.area, color)
+    }
+}
+
+impl<DI, M, RST> Display<DI, M, RST>
+where
+    DI: Interface<Word = u8>,
+    M: Model,
+    RST: OutputPin,
+{
+    /// Sends the area of a framebuffer view to the display.
+    ///
+    /// The top left corner of the view is shown at `top_left` and the view is
+    /// clipped to the display. The rows of the view aren't contiguous in the
+    /// framebuffer and are sent one by one using a single address window.
+    ///
+    /// The view is sent in buffer order. For framebuffers with a
+    /// [rotated orientation](RawFrameBuf::set_orientation) the size of the
+    /// updated display area is swapped for 90° and 270° rotations.
+    pub async fn show_raw_view<C, BUF, const N: usize>(
+        &mut self,
+        view: &RawView<'_, C, BUF, N>,
+        top_left: Point,
+    ) -> Result<(), DI::Error>
+    where
+        C: IntoRawBytes<N>,
+        BUF: RawBufferBackendMut,
+    {
+        let framebuffer = view.framebuffer();
+        let area = framebuffer.buffer_area(&view.area());
+
+        let (width, height) = self.options.display_size();
+        let bounds = Rectangle::new(
+            Point::zero(),
+            Size::new(u32::from(width), u32::from(height)),
+        );
+        let visible = Rectangle::new(top_left, area.size).intersection(&bounds);
+        if visible.is_zero_sized() {
+            return Ok(());
+        }
+
+        // Skip the source rows and columns which are outside of the display.
+        let source = Rectangle::new(area.top_left + (visible.top_left - top_left), visible.size);
+        self.show_raw_rows(&visible, framebuffer.area_rows(&source))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use embassy_futures::block_on;
+    use embedded_graphics::{
+        pixelcolor::{Gray8, Rgb565},
+        prelude::{GrayColor, Primitive},
+        primitives::PrimitiveStyle,
+        Drawable,
+    };
+    use std::vec;
+
+    use crate::{
+        models::ST7789,
+        testing::{mock_display, MockInterface, Transfer},
+    };
+
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Rectangle {
+        Rectangle::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn view_translates_and_clips() {
+        let mut buffer = [0u8; 4 * 3 * 3];
+        let mut fb = RawFrameBuf::<Gray8, _, 3>::new(&mut buffer[..], 4, 3);
+
+        let mut view = fb.sub_view(rect(1, 1, 2, 5));
+        assert_eq!(view.area(), rect(1, 1, 2, 2));
+        assert_eq!(view.size(), Size::new(2, 2));
+
+        Pixel(Point::new(0, 0), Gray8::new(1))
+            .draw(&mut view)
+            .unwrap();
+        Pixel(Point::new(2, 0), Gray8::new(2))
+            .draw(&mut view)
+            .unwrap();
+        rect(1, 1, 5, 5)
+            .into_styled(PrimitiveStyle::with_fill(Gray8::new(3)))
+            .draw(&mut view)
+            .unwrap();
+
+        let pixels: [u8; 12] = core::array::from_fn(|i| fb.as_bytes()[i * 3]);
+        assert_eq!(pixels, [0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 3, 0]);
+        assert_eq!(fb.dirty_region().as_slice(), &[rect(1, 1, 2, 2)]);
+    }
+
+    #[test]
+    fn blit_copies_and_clips() {
+        let mut src_buffer = [0u8; 3 * 2 * 3];
+        let mut src = RawFrameBuf::<Gray8, _, 3>::new(&mut src_buffer[..], 3, 2);
+        for (i, chunk) in src.as_mut_bytes().chunks_exact_mut(3).enumerate() {
+            chunk.fill(i as u8 + 1);
+        }
+
+        let mut buffer = [0u8; 3 * 3 * 3];
+        let mut fb = RawFrameBuf::<Gray8, _, 3>::new(&mut buffer[..], 3, 3);
+        fb.blit(&src, &rect(1, 0, 2, 2), Point::new(2, 1), None);
+
+        let pixels: [u8; 9] = core::array::from_fn(|i| fb.as_bytes()[i * 3]);
+        assert_eq!(pixels, [0, 0, 0, 0, 0, 2, 0, 0, 5]);
+        assert_eq!(fb.dirty_region().as_slice(), &[rect(2, 1, 1, 2)]);
+    }
+
+    #[test]
+    fn blit_skips_color_key() {
+        let mut src_buffer = [0u8; 2 * 2 * 3];
+        let mut src = RawFrameBuf::<Gray8, _, 3>::new(&mut src_buffer[..], 2, 2);
+        src.clear(Gray8::WHITE).unwrap();
+        Pixel(Point::new(1, 0), Gray8::BLACK)
+            .draw(&mut src)
+            .unwrap();
+
+        let mut buffer = [0u8; 2 * 2 * 3];
+        let mut fb = RawFrameBuf::<Gray8, _, 3>::new(&mut buffer[..], 2, 2);
+        fb.clear(Gray8::new(7)).unwrap();
+        fb.blit(&src, &src.bounding_box(), Point::zero(), Some(Gray8::WHITE));
+
+        let pixels: [u8; 4] = core::array::from_fn(|i| fb.as_bytes()[i * 3]);
+        assert_eq!(pixels, [7, 0, 7, 7]);
+    }
+
+    #[test]
+    fn shows_view() {
+        let mut buffer: [u8; 4 * 3 * 2] = core::array::from_fn(|i| i as u8);
+        let mut fb = RawFrameBuf::<Rgb565, _, 2>::new(&mut buffer[..], 4, 3);
+        let view = fb.sub_view(rect(1, 1, 2, 2));
+
+        let mut display = mock_display(ST7789, MockInterface::new(), (8, 8));
+        block_on(display.show_raw_view(&view, Point::new(2, 3))).unwrap();
+
+        display.di.assert_transfers(&[
+            Transfer::Command(0x2A, vec![0, 2, 0, 3]),
+            Transfer::Command(0x2B, vec![0, 3, 0, 4]),
+            Transfer::Command(0x2C, vec![]),
+            Transfer::Data(vec![10, 11, 12, 13]),
+            Transfer::Data(vec![18, 19, 20, 21]),
+        ]);
+    }
+
+    #[test]
+    fn clips_view_to_display() {
+        let mut buffer: [u8; 4 * 3 * 2] = core::array::from_fn(|i| i as u8);
+        let mut fb = RawFrameBuf::<Rgb565, _, 2>::new(&mut buffer[..], 4, 3);
+        let view = fb.sub_view(rect(0, 0, 4, 3));
+
+        // Only the bottom row of the three rightmost columns is visible.
+        let mut display = mock_display(ST7789, MockInterface::new(), (8, 8));
+        block_on(display.show_raw_view(&view, Point::new(-1, -2))).unwrap();
+        display.di.assert_transfers(&[
+            Transfer::Command(0x2A, vec![0, 0, 0, 2]),
+            Transfer::Command(0x2B, vec![0, 0, 0, 0]),
+            Transfer::Command(0x2C, vec![]),
+            Transfer::Data(vec![18, 19, 20, 21, 22, 23]),
+        ]);
+
+        // Only the top row of the two leftmost columns is visible.
+        let mut display = mock_display(ST7789, MockInterface::new(), (8, 8));
+        block_on(display.show_raw_view(&view, Point::new(6, 7))).unwrap();
+        display.di.assert_transfers(&[
+            Transfer::Command(0x2A, vec![0, 6, 0, 7]),
+            Transfer::Command(0x2B, vec![0, 7, 0, 7]),
+            Transfer::Command(0x2C, vec![]),
+            Transfer::Data(vec![0, 1, 2, 3]),
+        ]);
+
+        let mut display = mock_display(ST7789, MockInterface::new(), (8, 8));
+        block_on(display.show_raw_view(&view, Point::new(8, 0))).unwrap();
+        display.di.assert_transfers(&[]);
+    }
+}
@@ -0,0 +1,275 @@
+//! Orientation aware hardware scrolling.
+
+use embedded_hal::digital::OutputPin;
+
+use crate::{
+    interface::Interface,
+    models::Model,
+    options::{MemoryMapping, ModelOptions},
+    Display,
+};
+
+/// Hardware scroll state.
+///
+/// The MIPI DCS scroll commands (VSCRDEF and VSCRSADD) always operate on the
+/// native framebuffer rows of the controller. A [`Scroller`] translates
+/// between these physical rows and the logical coordinates used by the
+/// application, taking the display offset, the panel size and the current
+/// [`Orientation`](crate::options::Orientation) into account.
+///
+/// For orientations that swap rows and columns (90° and 270°) the hardware
+/// scrolls along the logical x axis. In this case the "top" and "bottom" fixed
+/// areas are located at the left and right side of the display, see
+/// [`is_horizontal`](Self::is_horizontal).
+///
+/// A scroller is created by [`Display::scroller`] and becomes invalid if the
+/// orientation of the display is changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scroller {
+    top_fixed_area: u16,
+    bottom_fixed_area: u16,
+    height: u16,
+    physical_top: u16,
+    physical_bottom: u16,
+    reverse: bool,
+    horizontal: bool,
+    offset: u16,
+}
+
+impl Scroller {
+    /// Creates a new scroller for the given options and fixed areas.
+    ///
+    /// Panics if the fixed areas don't leave at least one row to scroll.
+    pub(crate) fn new(
+        options: &ModelOptions,
+        framebuffer_rows: u16,
+        top_fixed_area: u16,
+        bottom_fixed_area: u16,
+    ) -> Self {
+        let mapping = MemoryMapping::from(options.orientation);
+        let rows = options.display_size.1;
+        let offset = options.display_offset.1;
+
+        assert!(
+            u32::from(top_fixed_area) + u32::from(bottom_fixed_area) < u32::from(rows),
+            "Scroll area fixed areas are too large. Expected less than {} rows, got {}.",
+            rows,
+            u32::from(top_fixed_area) + u32::from(bottom_fixed_area)
+        );
+
+        let below = framebuffer_rows.saturating_sub(offset.saturating_add(rows));
+        let (physical_top, physical_bottom) = if mapping.reverse_rows {
+            (offset + bottom_fixed_area, below + top_fixed_area)
+        } else {
+            (offset + top_fixed_area, below + bottom_fixed_area)
+        };
+
+        Self {
+            top_fixed_area,
+            bottom_fixed_area,
+            height: rows - top_fixed_area - bottom_fixed_area,
+            physical_top,
+            physical_bottom,
+            reverse: mapping.reverse_rows,
+            horizontal: mapping.swap_rows_and_columns,
+            offset: 0,
+        }
+    }
+
+    /// Returns the size of the top fixed area in logical rows.
+    pub fn top_fixed_area(&self) -> u16 {
+        self.top_fixed_area
+    }
+
+    /// Returns the size of the bottom fixed area in logical rows.
+    pub fn bottom_fixed_area(&self) -> u16 {
+        self.bottom_fixed_area
+    }
+
+    /// Returns the number of rows in the scroll area.
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Returns the current scroll offset.
+    ///
+    /// The offset is the number of rows the content was scrolled by, modulo
+    /// the scroll area [`height`](Self::height).
+    pub fn offset(&self) -> u16 {
+        self.offset
+    }
+
+    /// Returns `true` if the scroll area runs along the logical x axis.
+    ///
+    /// If this method returns `true` all row values returned by the scroller
+    /// are logical x coordinates.
+    pub fn is_horizontal(&self) -> bool {
+        self.horizontal
+    }
+
+    /// Returns the logical row that is currently shown at `line` of the scroll area.
+    ///
+    /// `line` is counted from the start of the scroll area, `0` being the first
+    /// row below the top fixed area. Content drawn into the returned row will be
+    /// visible at this position of the screen.
+    pub fn row_at(&self, line: u16) -> u16 {
+        let line = (u32::from(self.offset) + u32::from(line)) % u32::from(self.height);
+        self.top_fixed_area + line as u16
+    }
+
+    /// Returns the physical VSCRDEF top and bottom fixed areas.
+    pub(crate) fn physical_fixed_areas(&self) -> (u16, u16) {
+        (self.physical_top, self.physical_bottom)
+    }
+
+    /// Returns the physical VSCRSADD value for the current offset.
+    pub(crate) fn physical_offset(&self) -> u16 {
+        let offset = if self.reverse {
+            (self.height - self.offset) % self.height
+        } else {
+            self.offset
+        };
+
+        self.physical_top + offset
+    }
+
+    /// Moves the content by `rows` and returns the first newly exposed row.
+    fn advance(&mut self, rows: i32) -> u16 {
+        let height = i32::from(self.height);
+        let rows = rows.rem_euclid(height);
+        self.offset = ((i32::from(self.offset) + rows) % height) as u16;
+
+        if rows == 0 {
+            self.row_at(0)
+        } else {
+            self.row_at((height - rows) as u16)
+        }
+    }
+}
+
+impl<DI, M, RST> Display<DI, M, RST>
+where
+    DI: Interface,
+    M: Model,
+    RST: OutputPin,
+{
+    /// Sets up hardware scrolling and returns a [`Scroller`].
+    ///
+    /// The fixed areas are given in logical rows for the current orientation.
+    /// The scroll offset is reset to `0`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fixed areas don't leave at least one row to scroll.
+    pub async fn scroller(
+        &mut self,
+        top_fixed_area: u16,
+        bottom_fixed_area: u16,
+    ) -> Result<Scroller, DI::Error> {
+        let scroller = Scroller::new(
+            &self.options,
+            M::FRAMEBUFFER_SIZE.1,
+            top_fixed_area,
+            bottom_fixed_area,
+        );
+
+        let (top, bottom) = scroller.physical_fixed_areas();
+        self.set_vertical_scroll_region(top, bottom).await?;
+        self.set_vertical_scroll_offset(scroller.physical_offset())
+            .await?;
+
+        Ok(scroller)
+    }
+
+    /// Scrolls the content of the scroll area by `rows`.
+    ///
+    /// Positive values move the content towards the top fixed area, negative
+    /// values towards the bottom fixed area. The returned value is the logical
+    /// row which is shown at the first newly exposed line: the first of the
+    /// last `rows` lines for positive values and the first line of the scroll
+    /// area for negative values. The exposed lines wrap around at the end of
+    /// the scroll area, use [`Scroller::row_at`] to get the row of every line.
+    pub async fn scroll(&mut self, scroller: &mut Scroller, rows: i16) -> Result<u16, DI::Error> {
+        let row = if rows < 0 {
+            scroller.advance(i32::from(rows));
+            scroller.row_at(0)
+        } else {
+            scroller.advance(i32::from(rows))
+        };
+
+        self.set_vertical_scroll_offset(scroller.physical_offset())
+            .await?;
+
+        Ok(row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::options::{Orientation, Rotation};
+
+    use super::*;
+
+    fn options(orientation: Orientation) -> ModelOptions {
+        let mut options = ModelOptions::with_all((240, 280), (0, 20));
+        options.orientation = orientation;
+        options
+    }
+
+    #[test]
+    fn maps_fixed_areas_to_physical_rows() {
+        let scroller = Scroller::new(&options(Orientation::new()), 320, 10, 30);
+        assert_eq!(scroller.height(), 240);
+        assert_eq!(scroller.physical_fixed_areas(), (30, 50));
+        assert_eq!(scroller.physical_offset(), 30);
+        assert!(!scroller.is_horizontal());
+
+        let rotated = Orientation::new().rotate(Rotation::Deg180);
+        let scroller = Scroller::new(&options(rotated), 320, 10, 30);
+        assert_eq!(scroller.physical_fixed_areas(), (50, 30));
+        assert_eq!(scroller.physical_offset(), 50);
+
+        let rotated = Orientation::new().rotate(Rotation::Deg90);
+        let scroller = Scroller::new(&options(rotated), 320, 10, 30);
+        assert_eq!(scroller.physical_fixed_areas(), (30, 50));
+        assert!(scroller.is_horizontal());
+
+        let rotated = Orientation::new().rotate(Rotation::Deg270);
+        let scroller = Scroller::new(&options(rotated), 320, 10, 30);
+        assert_eq!(scroller.physical_fixed_areas(), (50, 30));
+    }
+
+    #[test]
+    fn tracks_offset_and_exposed_rows() {
+        let mut scroller = Scroller::new(&options(Orientation::new()), 320, 10, 30);
+
+        assert_eq!(scroller.advance(16), 10);
+        assert_eq!(scroller.offset(), 16);
+        assert_eq!(scroller.physical_offset(), 30 + 16);
+        assert_eq!(scroller.row_at(0), 26);
+        assert_eq!(scroller.row_at(239), 25);
+
+        assert_eq!(scroller.advance(240), 26);
+        assert_eq!(scroller.offset(), 16);
+
+        scroller.advance(-32);
+        assert_eq!(scroller.offset(), 224);
+        assert_eq!(scroller.row_at(0), 234);
+    }
+
+    #[test]
+    fn reversed_rows_scroll_in_opposite_physical_direction() {
+        let rotated = Orientation::new().rotate(Rotation::Deg180);
+        let mut scroller = Scroller::new(&options(rotated), 320, 10, 30);
+
+        scroller.advance(16);
+        assert_eq!(scroller.offset(), 16);
+        assert_eq!(scroller.physical_offset(), 50 + 240 - 16);
+    }
+
+    #[test]
+    #[should_panic]
+    fn fixed_areas_must_leave_scroll_rows() {
+        Scroller::new(&options(Orientation::new()), 320, 140, 140);
+    }
+}
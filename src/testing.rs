@@ -0,0 +1,408 @@
+//! Test doubles for host side unit tests.
+//!
+//! This module requires the `testing` feature and the standard library. It
+//! provides a [`MockInterface`], which records all commands and data
+//! transfers, a [`MockDelay`], which records the requested delays without
+//! waiting, a [`SimulatedPanel`], which emulates the GRAM of a display
+//! controller, and a [`Timeline`], which checks init and sleep sequences
+//! against the [`TimingRules`] of a controller. Initialized displays for
+//! tests are created by [`mock_display`] and [`mock_display_with`].
+//!
+//! ```
+//! use embassy_futures::block_on;
+//! use mipidsi::{
+//!     models::{Model, ST7789},
+//!     options::ModelOptions,
+//!     testing::{MockDelay, MockInterface},
+//! };
+//!
+//! let mut di = MockInterface::new();
+//! let mut delay = MockDelay::new();
+//! let options = ModelOptions::full_size::<ST7789>();
+//! block_on(ST7789.init(&mut di, &mut delay, &options)).unwrap();
+//!
+//! assert_eq!(di.commands().last(), Some((0x29, &[][..]))); // display on
+//! delay.assert_delays_us(&[150_000, 10_000, 10_000, 10_000, 120_000]);
+//! ```
+
+extern crate std;
+
+use core::{fmt::Debug, marker::PhantomData};
+use std::{format, string::String, vec::Vec};
+
+use embassy_futures::block_on;
+use embedded_hal_async::delay::DelayNs;
+
+use crate::{
+    interface::{Interface, InterfaceKind},
+    models::Model,
+    Builder, Display, NoResetPin,
+};
+
+mod simulator;
+pub use simulator::*;
+
+mod timing;
+pub use timing::*;
+
+/// Interface kind which is simulated by a [`MockInterface`].
+pub trait MockKind {
+    /// Word type of the interface.
+    type Word: Copy + Debug + Default + PartialEq + Into<u16>;
+    /// Kind of the interface.
+    const KIND: InterfaceKind;
+}
+
+/// SPI interface with a data/command pin.
+#[derive(Debug)]
+pub struct Serial4Line;
+
+impl MockKind for Serial4Line {
+    type Word = u8;
+    const KIND: InterfaceKind = InterfaceKind::Serial4Line;
+}
+
+/// 8 bit parallel interface.
+#[derive(Debug)]
+pub struct Parallel8Bit;
+
+impl MockKind for Parallel8Bit {
+    type Word = u8;
+    const KIND: InterfaceKind = InterfaceKind::Parallel8Bit;
+}
+
+/// 16 bit parallel interface.
+#[derive(Debug)]
+pub struct Parallel16Bit;
+
+impl MockKind for Parallel16Bit {
+    type Word = u16;
+    const KIND: InterfaceKind = InterfaceKind::Parallel16Bit;
+}
+
+/// Transfer which was recorded by a [`MockInterface`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transfer<W> {
+    /// Command with its parameters.
+    Command(u8, Vec<u8>),
+    /// Data which was sent by a single call of
+    /// [`send_data_slice`](Interface::send_data_slice).
+    Data(Vec<W>),
+}
+
+/// Error returned by a [`MockInterface`] after
+/// [`fail_next_transfer`](MockInterface::fail_next_transfer).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MockError;
+
+/// Interface which records all transfers.
+///
+/// The interface kind is selected by the `K` parameter. Data which is sent
+/// by [`send_data_pipelined`](Interface::send_data_pipelined) is recorded as
+/// one [`Transfer::Data`] per chunk.
+#[derive(Debug)]
+pub struct MockInterface<K: MockKind = Serial4Line> {
+    transfers: Vec<Transfer<K::Word>>,
+    fail: bool,
+    kind: PhantomData<K>,
+}
+
+impl MockInterface {
+    /// Creates a new mock SPI interface.
+    ///
+    /// Mocks of other interface kinds are created by [`Default::default`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K: MockKind> MockInterface<K> {
+    /// Returns all recorded transfers.
+    pub fn transfers(&self) -> &[Transfer<K::Word>] {
+        &self.transfers
+    }
+
+    /// Returns all recorded transfers and clears the record.
+    pub fn take_transfers(&mut self) -> Vec<Transfer<K::Word>> {
+        core::mem::take(&mut self.transfers)
+    }
+
+    /// Clears the record.
+    pub fn clear(&mut self) {
+        self.transfers.clear();
+    }
+
+    /// Makes the next command or data transfer fail with a [`MockError`].
+    ///
+    /// The failed transfer isn't recorded.
+    pub fn fail_next_transfer(&mut self) {
+        self.fail = true;
+    }
+
+    /// Returns `MockError` if the next transfer should fail.
+    fn check_failure(&mut self) -> Result<(), MockError> {
+        if core::mem::take(&mut self.fail) {
+            Err(MockError)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the recorded commands and their parameters.
+    pub fn commands(&self) -> impl Iterator<Item = (u8, &[u8])> {
+        self.transfers.iter().filter_map(|transfer| match transfer {
+            Transfer::Command(instruction, params) => Some((*instruction, params.as_slice())),
+            Transfer::Data(_) => None,
+        })
+    }
+
+    /// Returns all recorded data words.
+    pub fn data(&self) -> Vec<K::Word> {
+        self.transfers
+            .iter()
+            .flat_map(|transfer| match transfer {
+                Transfer::Command(..) => &[][..],
+                Transfer::Data(data) => data.as_slice(),
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Asserts that the recorded commands match `expected`.
+    ///
+    /// Data transfers are ignored.
+    #[track_caller]
+    pub fn assert_commands(&self, expected: &[(u8, &[u8])]) {
+        let actual = self.commands().collect::<Vec<_>>();
+        if actual != expected {
+            panic!(
+                "commands don't match\nexpected:\n{}actual:\n{}",
+                format_commands(expected.iter().copied()),
+                format_commands(actual.into_iter()),
+            );
+        }
+    }
+
+    /// Asserts that the recorded transfers match `expected`.
+    #[track_caller]
+    pub fn assert_transfers(&self, expected: &[Transfer<K::Word>]) {
+        if self.transfers != expected {
+            panic!(
+                "transfers don't match\nexpected:\n{}actual:\n{}",
+                format_transfers(expected),
+                format_transfers(&self.transfers),
+            );
+        }
+    }
+}
+
+impl<K: MockKind> Default for MockInterface<K> {
+    fn default() -> Self {
+        Self {
+            transfers: Vec::new(),
+            fail: false,
+            kind: PhantomData,
+        }
+    }
+}
+
+impl<K: MockKind> Interface for MockInterface<K> {
+    type Word = K::Word;
+    type Error = MockError;
+    const KIND: InterfaceKind = K::KIND;
+
+    async fn send_command(&mut self, command: u8, args: &[u8]) -> Result<(), Self::Error> {
+        self.check_failure()?;
+        self.transfers
+            .push(Transfer::Command(command, args.to_vec()));
+        Ok(())
+    }
+
+    async fn send_data_slice(&mut self, data: &[Self::Word]) -> Result<(), Self::Error> {
+        self.check_failure()?;
+        self.transfers.push(Transfer::Data(data.to_vec()));
+        Ok(())
+    }
+}
+
+/// Delay which records the requested durations and returns immediately.
+#[derive(Debug, Default)]
+pub struct MockDelay {
+    delays_ns: Vec<u64>,
+}
+
+impl MockDelay {
+    /// Creates a new mock delay.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the recorded delays in nanoseconds.
+    pub fn delays_ns(&self) -> &[u64] {
+        &self.delays_ns
+    }
+
+    /// Returns the recorded delays in microseconds, rounded down.
+    pub fn delays_us(&self) -> Vec<u64> {
+        self.delays_ns.iter().map(|ns| ns / 1000).collect()
+    }
+
+    /// Returns the sum of all recorded delays in nanoseconds.
+    pub fn total_ns(&self) -> u64 {
+        self.delays_ns.iter().sum()
+    }
+
+    /// Clears the record.
+    pub fn clear(&mut self) {
+        self.delays_ns.clear();
+    }
+
+    /// Asserts that the recorded delays in microseconds match `expected`.
+    #[track_caller]
+    pub fn assert_delays_us(&self, expected: &[u64]) {
+        assert_eq!(self.delays_us(), expected, "delays don't match");
+    }
+}
+
+impl DelayNs for MockDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.delays_ns.push(u64::from(ns));
+    }
+
+    async fn delay_us(&mut self, us: u32) {
+        self.delays_ns.push(u64::from(us) * 1000);
+    }
+
+    async fn delay_ms(&mut self, ms: u32) {
+        self.delays_ns.push(u64::from(ms) * 1_000_000);
+    }
+}
+
+/// Display with a [`MockInterface`].
+pub type MockDisplay<M, K = Serial4Line> = Display<MockInterface<K>, M, NoResetPin>;
+
+/// Creates an initialized display with a [`MockInterface`].
+///
+/// The transfers which were sent during the initialization are cleared.
+///
+/// ```
+/// use mipidsi::{models::ST7789, testing::{mock_display, MockInterface}};
+///
+/// let display = mock_display(ST7789, MockInterface::new(), (240, 240));
+/// let (di, _, _) = display.release();
+/// assert!(di.transfers().is_empty());
+/// ```
+pub fn mock_display<K: MockKind, M: Model>(
+    model: M,
+    di: MockInterface<K>,
+    size: (usize, usize),
+) -> MockDisplay<M, K> {
+    let mut display = mock_display_with(model, di, size, |builder| builder);
+    display.di.clear();
+    display
+}
+
+/// Creates an initialized display with any interface.
+///
+/// `configure` can set additional options on the builder. The display is
+/// initialized with a [`MockDelay`]. Unlike [`mock_display`] the transfers
+/// which were sent during the initialization aren't cleared.
+pub fn mock_display_with<DI: Interface, M: Model>(
+    model: M,
+    di: DI,
+    size: (usize, usize),
+    configure: impl FnOnce(Builder<DI, M, NoResetPin>) -> Builder<DI, M, NoResetPin>,
+) -> Display<DI, M, NoResetPin> {
+    let builder = Builder::new(model, di).display_size(size.0, size.1);
+    block_on(configure(builder).init(&mut MockDelay::new())).unwrap()
+}
+
+fn format_params(params: &[u8]) -> String {
+    let params = params
+        .iter()
+        .map(|param| format!("{param:02X}"))
+        .collect::<Vec<_>>();
+    format!("[{}]", params.join(" "))
+}
+
+fn format_commands<'a>(commands: impl Iterator<Item = (u8, &'a [u8])>) -> String {
+    commands
+        .map(|(instruction, params)| format!("  {instruction:02X} {}\n", format_params(params)))
+        .collect()
+}
+
+fn format_transfers<W: Debug>(transfers: &[Transfer<W>]) -> String {
+    transfers
+        .iter()
+        .map(|transfer| match transfer {
+            Transfer::Command(instruction, params) => {
+                format!("  {instruction:02X} {}\n", format_params(params))
+            }
+            Transfer::Data(data) => format!("  data {data:02X?}\n"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+
+    use crate::dcs::{InterfaceExt, SetColumnAddress};
+
+    use super::*;
+
+    #[test]
+    fn records_transfers() {
+        let mut di = MockInterface::<Parallel16Bit>::default();
+        block_on(async {
+            di.write_command(SetColumnAddress::new(0x10, 0x11F))
+                .await
+                .unwrap();
+            di.send_data_slice(&[0x1234, 0x5678]).await.unwrap();
+            di.send_data_pipelined([&mut [0; 2], &mut [0; 2]], {
+                let mut chunks = [&[1, 2][..], &[3]].into_iter();
+                move |buffer| {
+                    let chunk = chunks.next().unwrap_or_default();
+                    buffer[..chunk.len()].copy_from_slice(chunk);
+                    chunk.len()
+                }
+            })
+            .await
+            .unwrap();
+        });
+
+        di.assert_commands(&[(0x2A, &[0x00, 0x10, 0x01, 0x1F])]);
+        di.assert_transfers(&[
+            Transfer::Command(0x2A, std::vec![0x00, 0x10, 0x01, 0x1F]),
+            Transfer::Data(std::vec![0x1234, 0x5678]),
+            Transfer::Data(std::vec![1, 2]),
+            Transfer::Data(std::vec![3]),
+        ]);
+        assert_eq!(di.data(), [0x1234, 0x5678, 1, 2, 3]);
+
+        assert_eq!(di.take_transfers().len(), 4);
+        assert!(di.transfers().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "commands don't match")]
+    fn reports_mismatched_commands() {
+        let mut di = MockInterface::new();
+        block_on(di.send_command(0x11, &[])).unwrap();
+        di.assert_commands(&[(0x29, &[])]);
+    }
+
+    #[test]
+    fn records_delays() {
+        let mut delay = MockDelay::new();
+        block_on(async {
+            delay.delay_ns(500).await;
+            delay.delay_us(10).await;
+            delay.delay_ms(120).await;
+        });
+
+        assert_eq!(delay.delays_ns(), [500, 10_000, 120_000_000]);
+        delay.assert_delays_us(&[0, 10, 120_000]);
+        assert_eq!(delay.total_ns(), 120_010_500);
+    }
+}
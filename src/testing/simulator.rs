@@ -0,0 +1,748 @@
+//! Simulated display controller.
+
+extern crate std;
+
+use core::marker::PhantomData;
+use std::{io, vec, vec::Vec};
+
+use embedded_graphics::pixelcolor::{raw::RawU16, Rgb565, Rgb666, Rgb888, RgbColor};
+
+use crate::{
+    interface::{Interface, InterfaceKind},
+    options::{
+        ColorInversion, ColorOrder, HorizontalRefreshOrder, RefreshOrder, VerticalRefreshOrder,
+    },
+};
+
+use super::{MockKind, Serial4Line};
+
+/// Error returned by a [`SimulatedPanel`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimulatorError {
+    /// A command was sent with an invalid number of parameters.
+    InvalidParameters(u8),
+    /// COLMOD selected a pixel format which isn't supported by the simulator.
+    UnsupportedPixelFormat(u8),
+    /// Data was sent without a preceding RAMWR or WMC command.
+    UnexpectedData,
+}
+
+/// Simulated MIPI DCS display controller.
+///
+/// The simulator keeps a virtual GRAM and interprets the commands which
+/// change its contents or the way it is shown on the panel: CASET, RASET,
+/// RAMWR, WMC, MADCTL, COLMOD, VSCRDEF, VSCRSADD, INVON and INVOFF. SWRESET,
+/// SLPIN, SLPOUT, NORON, DISPON and DISPOFF update the controller state and
+/// all other commands are ignored.
+///
+/// Pixel data is accepted in the 12, 16, 18 and 24 bit formats. On 16 bit
+/// interfaces every data word is split into two bytes, most significant byte
+/// first.
+///
+/// The visible image is the part of the GRAM which is covered by the panel,
+/// see [`display_size`](Self::display_size) and
+/// [`display_offset`](Self::display_offset), with the vertical scrolling,
+/// color inversion and color order applied. The image is always shown in the
+/// native orientation of the panel. The refresh order bits of MADCTL don't
+/// change the image, they are reported by
+/// [`refresh_order`](Self::refresh_order).
+///
+/// ```
+/// use embassy_futures::block_on;
+/// use embedded_graphics::pixelcolor::{Rgb565, Rgb888, RgbColor};
+/// use mipidsi::{models::ST7789, testing::{MockDelay, SimulatedPanel}, Builder};
+///
+/// let panel = SimulatedPanel::new(240, 320).display_size(240, 240);
+/// let mut display = block_on(
+///     Builder::new(ST7789, panel)
+///         .display_size(240, 240)
+///         .init(&mut MockDelay::new()),
+/// )
+/// .unwrap();
+/// block_on(display.clear(Rgb565::RED)).unwrap();
+///
+/// let (panel, _, _) = display.release();
+/// assert!(panel.image().iter().all(|&color| color == Rgb888::RED));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SimulatedPanel<K: MockKind = Serial4Line> {
+    framebuffer_size: (u16, u16),
+    gram: Vec<Rgb888>,
+    display_size: (u16, u16),
+    display_offset: (u16, u16),
+    panel_color_order: ColorOrder,
+    panel_inversion: ColorInversion,
+
+    madctl: u8,
+    colmod: u8,
+    columns: (u16, u16),
+    pages: (u16, u16),
+    /// Logical column and page of the next pixel.
+    cursor: (u16, u16),
+    /// `true` after RAMWR or WMC until the next command.
+    writing: bool,
+    /// Bytes of an incomplete pixel.
+    pending: Vec<u8>,
+    inverted: bool,
+    scroll_area: (u16, u16, u16),
+    scroll_start: u16,
+    scrolling: bool,
+    sleeping: bool,
+    display_on: bool,
+    kind: PhantomData<K>,
+}
+
+impl SimulatedPanel {
+    /// Creates a new simulated SPI controller with a GRAM of `width` x `height`
+    /// pixels.
+    ///
+    /// Controllers with other interface kinds are created by
+    /// [`with_kind`](SimulatedPanel::with_kind).
+    pub fn new(width: u16, height: u16) -> Self {
+        Self::with_kind(width, height)
+    }
+}
+
+impl<K: MockKind> SimulatedPanel<K> {
+    /// Creates a new simulated controller with a GRAM of `width` x `height`
+    /// pixels.
+    ///
+    /// The panel covers the whole GRAM and all pixels are black.
+    pub fn with_kind(width: u16, height: u16) -> Self {
+        let mut panel = Self {
+            framebuffer_size: (width, height),
+            gram: vec![Rgb888::BLACK; usize::from(width) * usize::from(height)],
+            display_size: (width, height),
+            display_offset: (0, 0),
+            panel_color_order: ColorOrder::Rgb,
+            panel_inversion: ColorInversion::Normal,
+            madctl: 0,
+            colmod: 0,
+            columns: (0, 0),
+            pages: (0, 0),
+            cursor: (0, 0),
+            writing: false,
+            pending: Vec::new(),
+            inverted: false,
+            scroll_area: (0, 0, 0),
+            scroll_start: 0,
+            scrolling: false,
+            sleeping: true,
+            display_on: false,
+            kind: PhantomData,
+        };
+        panel.reset();
+        panel
+    }
+
+    /// Sets the size of the panel in pixels.
+    #[must_use]
+    pub fn display_size(mut self, width: u16, height: u16) -> Self {
+        self.display_size = (width, height);
+        self
+    }
+
+    /// Sets the offset of the panel in the GRAM.
+    #[must_use]
+    pub fn display_offset(mut self, x: u16, y: u16) -> Self {
+        self.display_offset = (x, y);
+        self
+    }
+
+    /// Sets the subpixel order of the panel.
+    ///
+    /// The red and blue channels are swapped if the MADCTL color order
+    /// doesn't match the panel.
+    #[must_use]
+    pub fn color_order(mut self, color_order: ColorOrder) -> Self {
+        self.panel_color_order = color_order;
+        self
+    }
+
+    /// Sets the color inversion of the panel.
+    ///
+    /// Panels with [`ColorInversion::Inverted`] show normal colors after INVON.
+    #[must_use]
+    pub fn invert_colors(mut self, color_inversion: ColorInversion) -> Self {
+        self.panel_inversion = color_inversion;
+        self
+    }
+
+    /// Returns the GRAM contents in row major order.
+    ///
+    /// The GRAM isn't affected by scrolling, color inversion or color order.
+    pub fn gram(&self) -> &[Rgb888] {
+        &self.gram
+    }
+
+    /// Returns the size of the visible image.
+    pub fn image_size(&self) -> (u16, u16) {
+        self.display_size
+    }
+
+    /// Returns the visible image in row major order.
+    pub fn image(&self) -> Vec<Rgb888> {
+        let (width, height) = self.display_size;
+        let (offset_x, offset_y) = self.display_offset;
+        let swap = (self.madctl & 0x08 != 0) != (self.panel_color_order == ColorOrder::Bgr);
+        let invert = self.inverted != (self.panel_inversion == ColorInversion::Inverted);
+
+        let mut image = Vec::with_capacity(usize::from(width) * usize::from(height));
+        for y in offset_y..offset_y + height {
+            let row = self.scrolled_row(y);
+            for x in offset_x..offset_x + width {
+                let mut color = self.gram[self.index(x, row)];
+                if swap {
+                    color = Rgb888::new(color.b(), color.g(), color.r());
+                }
+                if invert {
+                    color = Rgb888::new(!color.r(), !color.g(), !color.b());
+                }
+                image.push(color);
+            }
+        }
+        image
+    }
+
+    /// Writes the visible image as a binary PPM file.
+    pub fn write_ppm(&self, mut writer: impl io::Write) -> io::Result<()> {
+        let (width, height) = self.display_size;
+        write!(writer, "P6\n{width} {height}\n255\n")?;
+        for color in self.image() {
+            writer.write_all(&[color.r(), color.g(), color.b()])?;
+        }
+        Ok(())
+    }
+
+    /// Returns the last MADCTL value.
+    pub fn madctl(&self) -> u8 {
+        self.madctl
+    }
+
+    /// Returns the refresh order which was selected by the last MADCTL value.
+    pub fn refresh_order(&self) -> RefreshOrder {
+        let vertical = if self.madctl & 0x10 != 0 {
+            VerticalRefreshOrder::BottomToTop
+        } else {
+            VerticalRefreshOrder::TopToBottom
+        };
+        let horizontal = if self.madctl & 0x04 != 0 {
+            HorizontalRefreshOrder::RightToLeft
+        } else {
+            HorizontalRefreshOrder::LeftToRight
+        };
+        RefreshOrder::new(vertical, horizontal)
+    }
+
+    /// Returns the last COLMOD value.
+    pub fn colmod(&self) -> u8 {
+        self.colmod
+    }
+
+    /// Returns `true` after INVON.
+    pub fn is_inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// Returns `true` if the controller is in sleep mode.
+    pub fn is_sleeping(&self) -> bool {
+        self.sleeping
+    }
+
+    /// Returns `true` after DISPON.
+    pub fn is_display_on(&self) -> bool {
+        self.display_on
+    }
+
+    /// Returns the VSCRDEF top fixed, scroll and bottom fixed areas.
+    pub fn scroll_area(&self) -> (u16, u16, u16) {
+        self.scroll_area
+    }
+
+    /// Returns the VSCRSADD value.
+    pub fn scroll_start(&self) -> u16 {
+        self.scroll_start
+    }
+
+    /// Resets the controller state, the GRAM isn't changed.
+    fn reset(&mut self) {
+        let (width, height) = self.framebuffer_size;
+        self.madctl = 0;
+        self.colmod = 0x66;
+        self.columns = (0, width.saturating_sub(1));
+        self.pages = (0, height.saturating_sub(1));
+        self.cursor = (0, 0);
+        self.writing = false;
+        self.pending.clear();
+        self.inverted = false;
+        self.scroll_area = (0, height, 0);
+        self.scroll_start = 0;
+        self.scrolling = false;
+        self.sleeping = true;
+        self.display_on = false;
+    }
+
+    fn index(&self, x: u16, y: u16) -> usize {
+        usize::from(y) * usize::from(self.framebuffer_size.0) + usize::from(x)
+    }
+
+    /// Returns the GRAM row which is shown at `row` of the framebuffer.
+    fn scrolled_row(&self, row: u16) -> u16 {
+        let (top, scroll, _) = self.scroll_area;
+        if !self.scrolling || row < top || row - top >= scroll {
+            return row;
+        }
+        let start = self.scroll_start.clamp(top, top + scroll - 1) - top;
+        top + (row - top + start) % scroll
+    }
+
+    fn execute(&mut self, command: u8, args: &[u8]) -> Result<(), SimulatorError> {
+        let u16_at = |index: usize| u16::from_be_bytes([args[index], args[index + 1]]);
+        let expect_len = |len: usize| {
+            if args.len() == len {
+                Ok(())
+            } else {
+                Err(SimulatorError::InvalidParameters(command))
+            }
+        };
+
+        self.writing = false;
+        self.pending.clear();
+        match command {
+            0x01 => self.reset(),
+            0x10 => self.sleeping = true,
+            0x11 => self.sleeping = false,
+            0x13 => self.scrolling = false,
+            0x20 => self.inverted = false,
+            0x21 => self.inverted = true,
+            0x28 => self.display_on = false,
+            0x29 => self.display_on = true,
+            0x2A | 0x2B => {
+                expect_len(4)?;
+                let range = (u16_at(0), u16_at(2));
+                if command == 0x2A {
+                    self.columns = range;
+                } else {
+                    self.pages = range;
+                }
+            }
+            0x2C => {
+                self.cursor = (self.columns.0, self.pages.0);
+                self.writing = true;
+            }
+            0x3C => self.writing = true,
+            0x33 => {
+                expect_len(6)?;
+                self.scroll_area = (u16_at(0), u16_at(2), u16_at(4));
+            }
+            0x36 => {
+                expect_len(1)?;
+                self.madctl = args[0];
+            }
+            0x37 => {
+                expect_len(2)?;
+                self.scroll_start = u16_at(0);
+                self.scrolling = true;
+            }
+            0x3A => {
+                expect_len(1)?;
+                if !matches!(args[0] & 0x07, 0x03 | 0x05 | 0x06 | 0x07) {
+                    return Err(SimulatorError::UnsupportedPixelFormat(args[0]));
+                }
+                self.colmod = args[0];
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Writes a pixel at the cursor and advances the cursor.
+    fn write_pixel(&mut self, color: Rgb888) {
+        let (column, page) = self.cursor;
+        let (width, height) = self.framebuffer_size;
+
+        // MV exchanges rows and columns, MX and MY mirror the physical axes.
+        let (mut x, mut y) = if self.madctl & 0x20 != 0 {
+            (page, column)
+        } else {
+            (column, page)
+        };
+        if x < width && y < height {
+            if self.madctl & 0x40 != 0 {
+                x = width - 1 - x;
+            }
+            if self.madctl & 0x80 != 0 {
+                y = height - 1 - y;
+            }
+            let index = self.index(x, y);
+            self.gram[index] = color;
+        }
+
+        self.cursor = if column < self.columns.1 {
+            (column + 1, page)
+        } else if page < self.pages.1 {
+            (self.columns.0, page + 1)
+        } else {
+            (self.columns.0, self.pages.0)
+        };
+    }
+
+    fn write_data(&mut self, bytes: impl Iterator<Item = u8>) {
+        for byte in bytes {
+            self.pending.push(byte);
+            match (self.colmod & 0x07, self.pending.as_slice()) {
+                (0x03, &[rg0, br, gb1]) => {
+                    let expand = |value: u8| (value & 0x0F) * 17;
+                    let first = Rgb888::new(expand(rg0 >> 4), expand(rg0), expand(br >> 4));
+                    let second = Rgb888::new(expand(br), expand(gb1 >> 4), expand(gb1));
+                    self.write_pixel(first);
+                    self.write_pixel(second);
+                }
+                (0x05, &[high, low]) => {
+                    let color = Rgb565::from(RawU16::new(u16::from_be_bytes([high, low])));
+                    self.write_pixel(color.into());
+                }
+                (0x06, &[r, g, b]) => {
+                    self.write_pixel(Rgb666::new(r >> 2, g >> 2, b >> 2).into());
+                }
+                (0x07, &[r, g, b]) => self.write_pixel(Rgb888::new(r, g, b)),
+                _ => continue,
+            }
+            self.pending.clear();
+        }
+    }
+}
+
+impl<K: MockKind> Interface for SimulatedPanel<K> {
+    type Word = K::Word;
+    type Error = SimulatorError;
+    const KIND: InterfaceKind = K::KIND;
+
+    async fn send_command(&mut self, command: u8, args: &[u8]) -> Result<(), Self::Error> {
+        self.execute(command, args)
+    }
+
+    async fn send_data_slice(&mut self, data: &[Self::Word]) -> Result<(), Self::Error> {
+        if !self.writing {
+            return Err(SimulatorError::UnexpectedData);
+        }
+
+        let skip = 2 - core::mem::size_of::<K::Word>();
+        self.write_data(
+            data.iter()
+                .flat_map(|&word| word.into().to_be_bytes().into_iter().skip(skip)),
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+    use embedded_graphics::{prelude::*, primitives::Rectangle};
+    use proptest::prelude::*;
+
+    use crate::{
+        models::{DcsModelConfig, DcsPanel, GenericDcsModel},
+        options::{
+            HorizontalRefreshOrder, Orientation, RefreshOrder, Rotation, VerticalRefreshOrder,
+        },
+        testing::{mock_display_with, Parallel16Bit},
+        Display, NoResetPin,
+    };
+
+    use super::*;
+
+    const WIDTH: u16 = 20;
+    const HEIGHT: u16 = 30;
+
+    struct Small;
+
+    impl DcsPanel for Small {
+        const CONFIG: DcsModelConfig = DcsModelConfig::new(WIDTH, HEIGHT);
+    }
+
+    type SimulatedDisplay = Display<SimulatedPanel, GenericDcsModel<Small>, NoResetPin>;
+
+    /// Transformation of the display content.
+    #[derive(Debug, Clone, Copy)]
+    enum Op {
+        Rotate(Rotation),
+        FlipHorizontal,
+        FlipVertical,
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            Just(Op::Rotate(Rotation::Deg90)),
+            Just(Op::Rotate(Rotation::Deg180)),
+            Just(Op::Rotate(Rotation::Deg270)),
+            Just(Op::FlipHorizontal),
+            Just(Op::FlipVertical),
+        ]
+    }
+
+    fn orientation(ops: &[Op]) -> Orientation {
+        ops.iter()
+            .fold(Orientation::new(), |orientation, op| match *op {
+                Op::Rotate(rotation) => orientation.rotate(rotation),
+                Op::FlipHorizontal => orientation.flip_horizontal(),
+                Op::FlipVertical => orientation.flip_vertical(),
+            })
+    }
+
+    /// Returns the logical size and a function which maps logical points to
+    /// points on the panel.
+    ///
+    /// The transformations are applied to the display content, independent
+    /// of the MADCTL bits.
+    fn transform(ops: &[Op], size: (u16, u16)) -> ((u16, u16), impl Fn(u16, u16) -> (u16, u16)) {
+        // Logical size after every transformation.
+        let sizes = ops
+            .iter()
+            .scan(size, |size, op| {
+                if matches!(op, Op::Rotate(Rotation::Deg90 | Rotation::Deg270)) {
+                    *size = (size.1, size.0);
+                }
+                Some(*size)
+            })
+            .collect::<Vec<_>>();
+        let logical_size = sizes.last().copied().unwrap_or(size);
+
+        let ops = ops.to_vec();
+        let map = move |mut x: u16, mut y: u16| {
+            for (op, (width, height)) in ops.iter().zip(&sizes).rev() {
+                (x, y) = match op {
+                    Op::Rotate(Rotation::Deg0) => (x, y),
+                    Op::Rotate(Rotation::Deg90) => (height - 1 - y, x),
+                    Op::Rotate(Rotation::Deg180) => (width - 1 - x, height - 1 - y),
+                    Op::Rotate(Rotation::Deg270) => (y, width - 1 - x),
+                    Op::FlipHorizontal => (width - 1 - x, y),
+                    Op::FlipVertical => (x, height - 1 - y),
+                };
+            }
+            (x, y)
+        };
+        (logical_size, map)
+    }
+
+    /// Color which encodes a logical position.
+    fn color(x: u16, y: u16) -> Rgb565 {
+        Rgb565::new(x as u8, y as u8, 0)
+    }
+
+    fn display(
+        size: (u16, u16),
+        offset: (u16, u16),
+        orientation: Orientation,
+        refresh_order: RefreshOrder,
+    ) -> SimulatedDisplay {
+        let panel = SimulatedPanel::new(WIDTH, HEIGHT)
+            .display_size(size.0, size.1)
+            .display_offset(offset.0, offset.1);
+        let size = (size.0.into(), size.1.into());
+        mock_display_with(GenericDcsModel::<Small>::new(), panel, size, |builder| {
+            builder
+                .display_offset(offset.0, offset.1)
+                .orientation(orientation)
+                .refresh_order(refresh_order)
+        })
+    }
+
+    /// Fills the display with the position encoding colors.
+    fn draw_positions(display: &mut SimulatedDisplay, (width, height): (u16, u16)) {
+        let area = Rectangle::new(Point::zero(), Size::new(width.into(), height.into()));
+        let pixels = area.points().map(|p| color(p.x as u16, p.y as u16));
+        block_on(display.set_pixels(&area, pixels)).unwrap();
+    }
+
+    fn geometry() -> impl Strategy<Value = ((u16, u16), (u16, u16))> {
+        (1..=WIDTH, 1..=HEIGHT).prop_flat_map(|(width, height)| {
+            (
+                Just((width, height)),
+                (0..=WIDTH - width, 0..=HEIGHT - height),
+            )
+        })
+    }
+
+    fn refresh_order() -> impl Strategy<Value = RefreshOrder> {
+        (any::<bool>(), any::<bool>()).prop_map(|(bottom_to_top, right_to_left)| {
+            RefreshOrder::new(
+                if bottom_to_top {
+                    VerticalRefreshOrder::BottomToTop
+                } else {
+                    VerticalRefreshOrder::TopToBottom
+                },
+                if right_to_left {
+                    HorizontalRefreshOrder::RightToLeft
+                } else {
+                    HorizontalRefreshOrder::LeftToRight
+                },
+            )
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn pixels_land_in_place(
+            ops in prop::collection::vec(op(), 0..4),
+            (size, offset) in geometry(),
+            refresh_order in refresh_order(),
+            rect in (0..30u16, 0..30u16, 1..30u16, 1..30u16),
+        ) {
+            let (logical_size, map) = transform(&ops, size);
+            let mut display = display(size, offset, orientation(&ops), refresh_order);
+            draw_positions(&mut display, logical_size);
+
+            // Overwrite a rectangle, which is clipped to the display.
+            let (x, y) = (rect.0 % logical_size.0, rect.1 % logical_size.1);
+            let (width, height) = (
+                rect.2.min(logical_size.0 - x),
+                rect.3.min(logical_size.1 - y),
+            );
+            let rect = Rectangle::new(
+                Point::new(x.into(), y.into()),
+                Size::new(width.into(), height.into()),
+            );
+            block_on(display.fill_rect(&rect, Rgb565::WHITE)).unwrap();
+
+            let (panel, _, _) = display.release();
+            prop_assert_eq!(panel.refresh_order(), refresh_order);
+            let image = panel.image();
+            for y in 0..logical_size.1 {
+                for x in 0..logical_size.0 {
+                    let (px, py) = map(x, y);
+                    let expected = if rect.contains(Point::new(x.into(), y.into())) {
+                        Rgb565::WHITE
+                    } else {
+                        color(x, y)
+                    };
+                    let index = usize::from(py) * usize::from(size.0) + usize::from(px);
+                    prop_assert_eq!(image[index], expected.into(), "logical ({}, {})", x, y);
+                }
+            }
+        }
+
+        #[test]
+        fn scrolled_rows_land_in_place(
+            ops in prop::collection::vec(op(), 0..4),
+            (size, offset) in geometry(),
+            fixed_areas in (0..30u16, 0..30u16),
+            rows in -40i16..40,
+        ) {
+            let (logical_size, map) = transform(&ops, size);
+            let mut display = display(size, offset, orientation(&ops), RefreshOrder::default());
+            draw_positions(&mut display, logical_size);
+
+            let orientation = orientation(&ops);
+            let rows_total = if orientation.rotation.is_vertical() {
+                logical_size.0
+            } else {
+                logical_size.1
+            };
+            prop_assume!(rows_total > 1);
+            let top = fixed_areas.0 % rows_total;
+            let bottom = fixed_areas.1 % (rows_total - top);
+            let mut scroller = block_on(display.scroller(top, bottom)).unwrap();
+            block_on(display.scroll(&mut scroller, rows)).unwrap();
+
+            let (panel, _, _) = display.release();
+            let image = panel.image();
+            for y in 0..logical_size.1 {
+                for x in 0..logical_size.0 {
+                    let shown = |row: u16| match row.checked_sub(top) {
+                        Some(line) if line < scroller.height() => scroller.row_at(line),
+                        _ => row,
+                    };
+                    let content = if scroller.is_horizontal() {
+                        color(shown(x), y)
+                    } else {
+                        color(x, shown(y))
+                    };
+                    let (px, py) = map(x, y);
+                    let index = usize::from(py) * usize::from(size.0) + usize::from(px);
+                    prop_assert_eq!(image[index], content.into(), "logical ({}, {})", x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn decodes_pixel_formats() {
+        let mut panel = SimulatedPanel::new(2, 1);
+        block_on(async {
+            panel.send_command(0x2A, &[0, 0, 0, 1]).await.unwrap();
+            panel.send_command(0x2B, &[0, 0, 0, 0]).await.unwrap();
+
+            panel.send_command(0x3A, &[0x03]).await.unwrap();
+            panel.send_command(0x2C, &[]).await.unwrap();
+            panel.send_data_slice(&[0xF0, 0x0F, 0x80]).await.unwrap();
+        });
+        assert_eq!(
+            panel.gram(),
+            [Rgb888::new(0xFF, 0x00, 0x00), Rgb888::new(0xFF, 0x88, 0x00)]
+        );
+
+        block_on(async {
+            panel.send_command(0x3A, &[0x66]).await.unwrap();
+            panel.send_command(0x2C, &[]).await.unwrap();
+            // A pixel can be split across data transfers.
+            panel.send_data_slice(&[0xFC, 0x00]).await.unwrap();
+            panel.send_data_slice(&[0x04]).await.unwrap();
+            panel.send_command(0x3C, &[]).await.unwrap();
+            panel.send_data_slice(&[0x00, 0xFC, 0xFC]).await.unwrap();
+        });
+        assert_eq!(
+            panel.gram(),
+            [Rgb888::new(0xFF, 0x00, 0x04), Rgb888::new(0x00, 0xFF, 0xFF)]
+        );
+
+        let mut panel = SimulatedPanel::<Parallel16Bit>::with_kind(2, 1);
+        block_on(async {
+            panel.send_command(0x3A, &[0x55]).await.unwrap();
+            panel.send_command(0x2C, &[]).await.unwrap();
+            panel.send_data_slice(&[0xF800, 0x001F]).await.unwrap();
+        });
+        assert_eq!(panel.gram(), [Rgb888::RED, Rgb888::BLUE]);
+    }
+
+    #[test]
+    fn rejects_invalid_transfers() {
+        let mut panel = SimulatedPanel::new(2, 1);
+        block_on(async {
+            assert_eq!(
+                panel.send_data_slice(&[0]).await,
+                Err(SimulatorError::UnexpectedData)
+            );
+            assert_eq!(
+                panel.send_command(0x2A, &[0, 0]).await,
+                Err(SimulatorError::InvalidParameters(0x2A))
+            );
+            assert_eq!(
+                panel.send_command(0x3A, &[0x11]).await,
+                Err(SimulatorError::UnsupportedPixelFormat(0x11))
+            );
+        });
+    }
+
+    #[test]
+    fn shows_panel_colors() {
+        let panel = SimulatedPanel::new(1, 1)
+            .color_order(ColorOrder::Bgr)
+            .invert_colors(ColorInversion::Inverted);
+        let mut display =
+            mock_display_with(GenericDcsModel::<Small>::new(), panel, (1, 1), |builder| {
+                builder
+            });
+        block_on(display.clear(Rgb565::RED)).unwrap();
+
+        let (panel, _, _) = display.release();
+        assert!(panel.is_display_on() && !panel.is_sleeping());
+        // Red is shown as blue by the BGR panel and inverted by INVOFF.
+        assert_eq!(panel.image(), [Rgb888::YELLOW]);
+
+        let mut ppm = Vec::new();
+        panel.write_ppm(&mut ppm).unwrap();
+        assert_eq!(ppm, b"P6\n1 1\n255\n\xFF\xFF\x00");
+    }
+}
@@ -0,0 +1,645 @@
+//! Verification of the datasheet timing rules.
+
+extern crate std;
+
+use core::{cell::RefCell, convert::Infallible, fmt};
+use std::{format, rc::Rc, string::String, vec::Vec};
+
+use embassy_futures::block_on;
+use embedded_hal::digital::{ErrorType, OutputPin};
+use embedded_hal_async::delay::DelayNs;
+
+use crate::{
+    interface::{Interface, InterfaceKind},
+    models::Model,
+    Builder, Display,
+};
+
+use super::{MockInterface, MockKind};
+
+const SWRESET: u8 = 0x01;
+const SLPIN: u8 = 0x10;
+const SLPOUT: u8 = 0x11;
+const DISPON: u8 = 0x29;
+
+/// Minimum gaps between the reset, sleep and display on events of a
+/// controller.
+///
+/// All durations are in microseconds. The rules only apply to MIPI DCS
+/// controllers, because the commands are identified by their DCS
+/// instructions. Rules which don't apply to a controller are set to `0`.
+///
+/// ```
+/// use mipidsi::testing::TimingRules;
+///
+/// const ST7789: TimingRules = TimingRules {
+///     display_on_to_command_us: 120_000,
+///     ..TimingRules::MIPI_DCS
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingRules {
+    /// Minimum duration of the RESX low pulse.
+    pub reset_pulse_us: u32,
+    /// Gap between releasing RESX and the next transfer.
+    pub reset_to_command_us: u32,
+    /// Gap between SWRESET and the next transfer.
+    pub software_reset_to_command_us: u32,
+    /// Gap between a hardware or software reset and SLPOUT.
+    pub reset_to_sleep_out_us: u32,
+    /// Gap between SLPIN and the next transfer.
+    pub sleep_in_to_command_us: u32,
+    /// Gap between SLPOUT and the next transfer.
+    pub sleep_out_to_command_us: u32,
+    /// Gap between SLPIN and the next SLPOUT.
+    pub sleep_in_to_sleep_out_us: u32,
+    /// Gap between SLPOUT and the next SLPIN.
+    pub sleep_out_to_sleep_in_us: u32,
+    /// Gap between SLPOUT and DISPON.
+    pub sleep_out_to_display_on_us: u32,
+    /// Gap between DISPON and the next transfer.
+    ///
+    /// The datasheets don't require a gap, but data which is sent right
+    /// after DISPON can get corrupted on some modules. Models which wait
+    /// 120 ms after DISPON for this reason check the wait with this rule.
+    pub display_on_to_command_us: u32,
+}
+
+impl TimingRules {
+    /// Timing rules which are shared by most MIPI DCS controllers.
+    ///
+    /// Commands can be sent 5 ms after a reset or a sleep mode change. SLPOUT
+    /// can only be sent 120 ms after a reset or SLPIN, and the controller
+    /// needs 120 ms to leave the sleep mode before SLPIN or DISPON are sent.
+    pub const MIPI_DCS: Self = Self {
+        reset_pulse_us: 10,
+        reset_to_command_us: 5_000,
+        software_reset_to_command_us: 5_000,
+        reset_to_sleep_out_us: 120_000,
+        sleep_in_to_command_us: 5_000,
+        sleep_out_to_command_us: 5_000,
+        sleep_in_to_sleep_out_us: 120_000,
+        sleep_out_to_sleep_in_us: 120_000,
+        sleep_out_to_display_on_us: 120_000,
+        display_on_to_command_us: 0,
+    };
+
+    /// Timing rules of the Sitronix ST7789 and ST7796 controllers.
+    ///
+    /// The SLPOUT restrictions in the ST7789V and ST7796S datasheets only
+    /// require 5 ms before the next command. The 120 ms after SLPOUT apply
+    /// to SLPIN only, DISPON can follow after 5 ms. The ST7789 model waits
+    /// 120 ms after DISPON, see
+    /// [`display_on_to_command_us`](Self::display_on_to_command_us).
+    pub const ST7789: Self = Self {
+        sleep_out_to_display_on_us: 5_000,
+        display_on_to_command_us: 120_000,
+        ..Self::MIPI_DCS
+    };
+}
+
+/// Event which was recorded by a [`Timeline`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineEvent {
+    /// The reset pin was set low.
+    ResetLow,
+    /// The reset pin was set high.
+    ResetHigh,
+    /// A command was sent.
+    Command(u8),
+    /// Data was sent.
+    Data,
+}
+
+impl fmt::Display for TimelineEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ResetLow => f.write_str("RESX low"),
+            Self::ResetHigh => f.write_str("RESX high"),
+            Self::Command(instruction) => write!(f, "command {instruction:02X}"),
+            Self::Data => f.write_str("data"),
+        }
+    }
+}
+
+/// Violation of a [`TimingRules`] gap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingViolation {
+    /// Name of the violated rule.
+    pub rule: &'static str,
+    /// Time of the event which was too early in nanoseconds.
+    pub time_ns: u64,
+    /// Required gap in microseconds.
+    pub required_us: u32,
+    /// Actual gap in nanoseconds.
+    pub actual_ns: u64,
+}
+
+impl fmt::Display for TimingViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {} µs: gap of {} µs, required {} µs",
+            self.rule,
+            self.time_ns / 1000,
+            self.actual_ns / 1000,
+            self.required_us
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct TimelineState {
+    now_ns: u64,
+    events: Vec<(u64, TimelineEvent)>,
+}
+
+/// Shared clock which records the transfers, delays and reset pin changes.
+///
+/// The timeline hands out an interface wrapper, a delay and a reset pin,
+/// which all record into the same log. Delays advance the clock without
+/// waiting and all other events take no time. The log is checked against
+/// the [`TimingRules`] of a controller by [`check`](Self::check).
+#[derive(Debug, Clone, Default)]
+pub struct Timeline {
+    state: Rc<RefCell<TimelineState>>,
+}
+
+impl Timeline {
+    /// Creates a new timeline.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wraps an interface to record its transfers.
+    pub fn interface<DI: Interface>(&self, di: DI) -> TimedInterface<DI> {
+        TimedInterface {
+            di,
+            timeline: self.clone(),
+        }
+    }
+
+    /// Returns a delay which advances the clock.
+    pub fn delay(&self) -> TimedDelay {
+        TimedDelay {
+            timeline: self.clone(),
+        }
+    }
+
+    /// Returns a reset pin which records its changes.
+    pub fn reset_pin(&self) -> TimedResetPin {
+        TimedResetPin {
+            timeline: self.clone(),
+        }
+    }
+
+    /// Returns the current time in nanoseconds.
+    pub fn now_ns(&self) -> u64 {
+        self.state.borrow().now_ns
+    }
+
+    /// Returns the recorded events with their time in nanoseconds.
+    pub fn events(&self) -> Vec<(u64, TimelineEvent)> {
+        self.state.borrow().events.clone()
+    }
+
+    /// Checks the recorded events against `rules`.
+    ///
+    /// Returns all violations in the order of the events.
+    pub fn check(&self, rules: &TimingRules) -> Vec<TimingViolation> {
+        let mut violations = Vec::new();
+        let mut check = |rule, time_ns, since: Option<u64>, required_us: u32| {
+            if let Some(since) = since {
+                let actual_ns = time_ns - since;
+                if actual_ns < u64::from(required_us) * 1000 {
+                    violations.push(TimingViolation {
+                        rule,
+                        time_ns,
+                        required_us,
+                        actual_ns,
+                    });
+                }
+            }
+        };
+
+        let mut reset_low = None;
+        let mut reset_released = None;
+        let mut reset = None;
+        let mut sleep_in = None;
+        let mut sleep_out = None;
+        let mut previous = None;
+
+        for (time, event) in self.events() {
+            let command = match event {
+                TimelineEvent::ResetLow => {
+                    reset_low = Some(time);
+                    continue;
+                }
+                TimelineEvent::ResetHigh => {
+                    check("RESX pulse", time, reset_low.take(), rules.reset_pulse_us);
+                    reset_released = Some(time);
+                    reset = Some(time);
+                    sleep_in = None;
+                    sleep_out = None;
+                    previous = None;
+                    continue;
+                }
+                TimelineEvent::Command(command) => Some(command),
+                TimelineEvent::Data => None,
+            };
+
+            check(
+                "RESX -> transfer",
+                time,
+                reset_released.take(),
+                rules.reset_to_command_us,
+            );
+            if let Some((previous, since)) = previous {
+                let gap = match previous {
+                    SWRESET => Some(("SWRESET -> transfer", rules.software_reset_to_command_us)),
+                    SLPIN => Some(("SLPIN -> transfer", rules.sleep_in_to_command_us)),
+                    SLPOUT => Some(("SLPOUT -> transfer", rules.sleep_out_to_command_us)),
+                    DISPON => Some(("DISPON -> transfer", rules.display_on_to_command_us)),
+                    _ => None,
+                };
+                if let Some((rule, required_us)) = gap {
+                    check(rule, time, Some(since), required_us);
+                }
+            }
+
+            let Some(command) = command else {
+                continue;
+            };
+            match command {
+                SWRESET => {
+                    reset = Some(time);
+                    sleep_in = None;
+                    sleep_out = None;
+                }
+                SLPIN => {
+                    check(
+                        "SLPOUT -> SLPIN",
+                        time,
+                        sleep_out.take(),
+                        rules.sleep_out_to_sleep_in_us,
+                    );
+                    sleep_in = Some(time);
+                }
+                SLPOUT => {
+                    check("reset -> SLPOUT", time, reset, rules.reset_to_sleep_out_us);
+                    check(
+                        "SLPIN -> SLPOUT",
+                        time,
+                        sleep_in.take(),
+                        rules.sleep_in_to_sleep_out_us,
+                    );
+                    sleep_out = Some(time);
+                }
+                DISPON => check(
+                    "SLPOUT -> DISPON",
+                    time,
+                    sleep_out,
+                    rules.sleep_out_to_display_on_us,
+                ),
+                _ => {}
+            }
+            previous = Some((command, time));
+        }
+
+        violations
+    }
+
+    /// Asserts that the recorded events follow `rules`.
+    #[track_caller]
+    pub fn assert_timing(&self, rules: &TimingRules) {
+        let violations = self.check(rules);
+        if !violations.is_empty() {
+            panic!(
+                "timing rules violated\n{}events:\n{}",
+                violations
+                    .iter()
+                    .map(|violation| format!("  {violation}\n"))
+                    .collect::<String>(),
+                self.events()
+                    .iter()
+                    .map(|(time, event)| format!("  {:>8} µs  {event}\n", time / 1000))
+                    .collect::<String>(),
+            );
+        }
+    }
+
+    fn record(&self, event: TimelineEvent) {
+        let mut state = self.state.borrow_mut();
+        let now = state.now_ns;
+        state.events.push((now, event));
+    }
+
+    fn advance(&self, ns: u64) {
+        self.state.borrow_mut().now_ns += ns;
+    }
+}
+
+/// Interface which records its transfers on a [`Timeline`].
+#[derive(Debug)]
+pub struct TimedInterface<DI> {
+    di: DI,
+    timeline: Timeline,
+}
+
+impl<DI> TimedInterface<DI> {
+    /// Returns the wrapped interface.
+    pub fn inner(&self) -> &DI {
+        &self.di
+    }
+
+    /// Releases the wrapped interface.
+    pub fn release(self) -> DI {
+        self.di
+    }
+}
+
+impl<DI: Interface> Interface for TimedInterface<DI> {
+    type Word = DI::Word;
+    type Error = DI::Error;
+    const KIND: InterfaceKind = DI::KIND;
+
+    async fn send_command(&mut self, command: u8, args: &[u8]) -> Result<(), Self::Error> {
+        self.timeline.record(TimelineEvent::Command(command));
+        self.di.send_command(command, args).await
+    }
+
+    async fn send_data_slice(&mut self, data: &[Self::Word]) -> Result<(), Self::Error> {
+        self.timeline.record(TimelineEvent::Data);
+        self.di.send_data_slice(data).await
+    }
+}
+
+/// Delay which advances the clock of a [`Timeline`].
+#[derive(Debug)]
+pub struct TimedDelay {
+    timeline: Timeline,
+}
+
+impl DelayNs for TimedDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.timeline.advance(u64::from(ns));
+    }
+
+    async fn delay_us(&mut self, us: u32) {
+        self.timeline.advance(u64::from(us) * 1000);
+    }
+
+    async fn delay_ms(&mut self, ms: u32) {
+        self.timeline.advance(u64::from(ms) * 1_000_000);
+    }
+}
+
+/// Reset pin which records its changes on a [`Timeline`].
+#[derive(Debug)]
+pub struct TimedResetPin {
+    timeline: Timeline,
+}
+
+impl ErrorType for TimedResetPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for TimedResetPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.timeline.record(TimelineEvent::ResetLow);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.timeline.record(TimelineEvent::ResetHigh);
+        Ok(())
+    }
+}
+
+/// Asserts that a display follows the timing rules of its controller.
+///
+/// The display is initialized once with a reset pin and once with a software
+/// reset. Every display draws a pixel, enters and leaves the sleep mode and
+/// draws another pixel afterwards. The interface kind is selected by the `K`
+/// parameter.
+///
+/// ```
+/// use mipidsi::{
+///     models::ST7789,
+///     testing::{assert_display_timing, Serial4Line, TimingRules},
+/// };
+///
+/// assert_display_timing::<Serial4Line, _>(|| ST7789, &TimingRules::ST7789);
+/// ```
+#[track_caller]
+pub fn assert_display_timing<K: MockKind, M: Model>(model: impl Fn() -> M, rules: &TimingRules) {
+    let timeline = Timeline::new();
+    let builder = Builder::new(model(), timeline.interface(MockInterface::<K>::default()))
+        .reset_pin(timeline.reset_pin());
+    let display = block_on(builder.init(&mut timeline.delay())).unwrap();
+    exercise(display, &timeline);
+    timeline.assert_timing(rules);
+
+    let timeline = Timeline::new();
+    let builder = Builder::new(model(), timeline.interface(MockInterface::<K>::default()));
+    let display = block_on(builder.init(&mut timeline.delay())).unwrap();
+    exercise(display, &timeline);
+    timeline.assert_timing(rules);
+}
+
+fn exercise<K: MockKind, M: Model, RST: OutputPin>(
+    mut display: Display<TimedInterface<MockInterface<K>>, M, RST>,
+    timeline: &Timeline,
+) {
+    let pixel = [K::Word::default(); 4];
+    let mut delay = timeline.delay();
+    block_on(async {
+        display.show_raw_data(0, 0, 1, 1, &pixel).await.unwrap();
+        display.sleep(&mut delay).await.unwrap();
+        display.wake(&mut delay).await.unwrap();
+        display.show_raw_data(0, 0, 1, 1, &pixel).await.unwrap();
+    });
+}
+
+/// Defines a model which initializes the display with the shared init
+/// sequence of a model family and an 18 bit pixel format.
+///
+/// Used to check the timing of the shared init sequences, which don't belong
+/// to a single model.
+#[cfg(test)]
+macro_rules! rgb666_model {
+    ($name:ident, $framebuffer_size:expr, $init:path) => {
+        struct $name;
+
+        impl $crate::models::Model for $name {
+            const FRAMEBUFFER_SIZE: (u16, u16) = $framebuffer_size;
+
+            async fn init<DELAY, DI>(
+                &mut self,
+                di: &mut DI,
+                delay: &mut DELAY,
+                options: &$crate::options::ModelOptions,
+            ) -> Result<$crate::dcs::SetAddressMode, $crate::models::ModelInitError<DI::Error>>
+            where
+                DELAY: embedded_hal_async::delay::DelayNs,
+                DI: $crate::interface::Interface,
+            {
+                let pixel_format =
+                    $crate::dcs::PixelFormat::with_all($crate::dcs::BitsPerPixel::Eighteen);
+                $init(di, delay, options, pixel_format)
+                    .await
+                    .map_err(Into::into)
+            }
+        }
+    };
+}
+#[cfg(test)]
+pub(crate) use rgb666_model;
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        models::{run_init_script, InitStep},
+        options::ModelOptions,
+    };
+
+    use super::*;
+
+    fn timeline(events: &[(u32, TimelineEvent)]) -> Timeline {
+        let timeline = Timeline::new();
+        let mut di = timeline.interface(MockInterface::new());
+        let mut pin = timeline.reset_pin();
+        block_on(async {
+            for &(delay_us, event) in events {
+                timeline.delay().delay_us(delay_us).await;
+                match event {
+                    TimelineEvent::ResetLow => pin.set_low().unwrap(),
+                    TimelineEvent::ResetHigh => pin.set_high().unwrap(),
+                    TimelineEvent::Command(command) => di.send_command(command, &[]).await.unwrap(),
+                    TimelineEvent::Data => di.send_data_slice(&[0]).await.unwrap(),
+                }
+            }
+        });
+        timeline
+    }
+
+    fn rules(violations: &[TimingViolation]) -> Vec<&'static str> {
+        violations.iter().map(|violation| violation.rule).collect()
+    }
+
+    #[test]
+    fn records_events() {
+        use TimelineEvent::*;
+
+        let timeline = timeline(&[(0, ResetLow), (10, ResetHigh), (5_000, Command(0x11))]);
+        block_on(timeline.delay().delay_ms(1));
+
+        assert_eq!(
+            timeline.events(),
+            [
+                (0, ResetLow),
+                (10_000, ResetHigh),
+                (5_010_000, Command(0x11))
+            ]
+        );
+        assert_eq!(timeline.now_ns(), 6_010_000);
+    }
+
+    #[test]
+    fn accepts_valid_sequence() {
+        use TimelineEvent::*;
+
+        let timeline = timeline(&[
+            (0, ResetLow),
+            (10, ResetHigh),
+            (120_000, Command(SLPOUT)),
+            (5_000, Command(0x3A)),
+            (115_000, Command(DISPON)),
+            (0, Data),
+            (0, Command(SWRESET)),
+            (120_000, Command(SLPOUT)),
+            (120_000, Command(SLPIN)),
+            (120_000, Command(SLPOUT)),
+        ]);
+
+        assert_eq!(timeline.check(&TimingRules::MIPI_DCS), []);
+    }
+
+    #[test]
+    fn reports_violations() {
+        use TimelineEvent::*;
+
+        let timeline = timeline(&[
+            (0, ResetLow),
+            (5, ResetHigh),
+            (1_000, Command(SLPOUT)),
+            (0, Data),
+            (10_000, Command(DISPON)),
+            (0, Command(SWRESET)),
+            (5_000, Command(SLPOUT)),
+            (120_000, Command(SLPIN)),
+            (5_000, Command(SLPOUT)),
+        ]);
+
+        let violations = timeline.check(&TimingRules::MIPI_DCS);
+        assert_eq!(
+            rules(&violations),
+            [
+                "RESX pulse",
+                "RESX -> transfer",
+                "reset -> SLPOUT",
+                "SLPOUT -> transfer",
+                "SLPOUT -> DISPON",
+                "reset -> SLPOUT",
+                "SLPIN -> SLPOUT",
+            ]
+        );
+        assert_eq!(
+            violations[4],
+            TimingViolation {
+                rule: "SLPOUT -> DISPON",
+                time_ns: 11_005_000,
+                required_us: 120_000,
+                actual_ns: 10_000_000,
+            }
+        );
+    }
+
+    #[test]
+    fn checks_display_on_gap() {
+        use TimelineEvent::*;
+
+        let timeline = timeline(&[
+            (120_000, Command(SLPOUT)),
+            (120_000, Command(DISPON)),
+            (20_000, Data),
+        ]);
+        let rules = TimingRules {
+            display_on_to_command_us: 120_000,
+            ..TimingRules::MIPI_DCS
+        };
+
+        assert_eq!(self::rules(&timeline.check(&rules)), ["DISPON -> transfer"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "timing rules violated")]
+    fn detects_missing_delay() {
+        let timeline = Timeline::new();
+        let mut di = timeline.interface(MockInterface::new());
+        let script = [
+            InitStep::command(SLPOUT, &[]),
+            InitStep::command(DISPON, &[]),
+        ];
+        let options = ModelOptions::with_all((10, 10), (0, 0));
+        block_on(run_init_script(
+            &mut di,
+            &mut timeline.delay(),
+            &options,
+            &script,
+        ))
+        .unwrap();
+
+        timeline.assert_timing(&TimingRules::MIPI_DCS);
+    }
+}